@@ -0,0 +1,62 @@
+// Logs every trade of a simulated price feed to `trades.jsonl` as it executes,
+// then prints the trades with their evaluation
+//
+//     cargo run --example jsonl_log --features jsonl
+
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::str::FromStr;
+
+use plot::math::PriceRange;
+use plot::trade::evaluate::Evaluater;
+use plot::trade::jsonl::TradeWriter;
+use plot::trade::position::Position;
+use plot::trade::{Executor, Trade, Trader};
+use plot::types::{BaseQuantity, Decimal, Price, QuoteQuantity};
+
+struct Paper;
+
+impl Trader for Paper {
+    async fn buy(
+        &self,
+        price: &Price,
+        quantity: &QuoteQuantity,
+    ) -> Result<Vec<Trade>, Box<dyn Error>> {
+        Ok(vec![Trade::with_buy(*price, quantity / price, *quantity)])
+    }
+
+    async fn sell(
+        &self,
+        price: &Price,
+        quantity: &BaseQuantity,
+    ) -> Result<Vec<Trade>, Box<dyn Error>> {
+        Ok(vec![Trade::with_sell(*price, *quantity, quantity * price)])
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let dec = |value: &str| Decimal::from_str(value).unwrap();
+
+    let buying = vec![PriceRange::new(dec("90"), dec("100"))?];
+    let selling = vec![PriceRange::new(dec("110"), dec("120"))?];
+    let mut position = Position::new(buying, selling, dec("0"), dec("1000"));
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("trades.jsonl")?;
+    let mut log = TradeWriter::new(file);
+    let mut trades = Vec::new();
+
+    for price in ["105", "95", "100", "108", "115", "92"] {
+        for trade in position.trap(&Paper, &dec(price)).await? {
+            log.append(&trade)?;
+            trades.push(trade);
+        }
+    }
+
+    println!("{}", trades.evaluate().await.render(&trades));
+
+    Ok(())
+}
@@ -11,6 +11,8 @@ pub mod types {
     pub type Quantity = Decimal;
     pub type BaseQuantity = Quantity;
     pub type QuoteQuantity = Quantity;
+
+    pub use crate::time::Timestamp;
 }
 
 pub mod error {
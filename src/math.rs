@@ -1,12 +1,77 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use rust_decimal::{MathematicalOps, RoundingStrategy};
 use serde::{Deserialize, Serialize};
 
 use crate::types::Decimal;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+// Deserialization goes through `Range::new`, so `[205, 200]` loads as `Range(200, 205)`.
+// Both the tuple form and the `range_struct` object form are accepted.
+// Equality and ordering look at the normalized endpoints, ordering by min then max.
+// There is no `Ord`, its by-value `min`/`max` would shadow the accessors below.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    from = "RangeDef<T>",
+    bound(deserialize = "T: Deserialize<'de> + PartialOrd")
+)]
 pub struct Range<T>(pub T, pub T);
 
-impl Range<Decimal> {
-    pub fn min(&self) -> &Decimal {
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RangeDef<T> {
+    Tuple(T, T),
+    Struct { min: T, max: T },
+}
+
+impl<T: PartialOrd> From<RangeDef<T>> for Range<T> {
+    fn from(value: RangeDef<T>) -> Self {
+        match value {
+            RangeDef::Tuple(a, b) => Self::new(a, b),
+            RangeDef::Struct { min, max } => Self::new(min, max),
+        }
+    }
+}
+
+// Use with `#[serde(with = "plot::math::range_struct")]` to write `{"min": .., "max": ..}`
+pub mod range_struct {
+    use serde::ser::SerializeStruct;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Range;
+
+    pub fn serialize<S, T>(range: &Range<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize + PartialOrd,
+    {
+        let mut state = serializer.serialize_struct("Range", 2)?;
+        state.serialize_field("min", range.min())?;
+        state.serialize_field("max", range.max())?;
+        state.end()
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Range<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de> + PartialOrd,
+    {
+        Range::deserialize(deserializer)
+    }
+}
+
+impl<T: PartialOrd> Range<T> {
+    pub fn new(a: T, b: T) -> Self {
+        if b < a {
+            return Self(b, a);
+        }
+
+        Self(a, b)
+    }
+
+    pub fn min(&self) -> &T {
         if self.0 < self.1 {
             return &self.0;
         }
@@ -14,7 +79,7 @@ impl Range<Decimal> {
         &self.1
     }
 
-    pub fn max(&self) -> &Decimal {
+    pub fn max(&self) -> &T {
         if self.0 < self.1 {
             return &self.1;
         }
@@ -22,7 +87,1376 @@ impl Range<Decimal> {
         &self.0
     }
 
-    pub fn is_within(&self, value: &Decimal) -> bool {
-        self.min() <= value && value <= self.max()
+    pub fn is_within(&self, value: &T) -> bool {
+        self.is_within_bounds(value, Bound::Inclusive, Bound::Inclusive)
+    }
+
+    pub fn is_within_bounds(&self, value: &T, lower: Bound, upper: Bound) -> bool {
+        let above = match lower {
+            Bound::Inclusive => self.min() <= value,
+            Bound::Exclusive => self.min() < value,
+        };
+
+        let below = match upper {
+            Bound::Inclusive => value <= self.max(),
+            Bound::Exclusive => value < self.max(),
+        };
+
+        above && below
+    }
+}
+
+impl<T: PartialOrd> PartialEq for Range<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.min() == other.min() && self.max() == other.max()
+    }
+}
+
+impl<T: Eq + PartialOrd> Eq for Range<T> {}
+
+// Hashes the normalized endpoints so it agrees with `PartialEq`
+impl<T: Hash + PartialOrd> Hash for Range<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.min().hash(state);
+        self.max().hash(state);
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Range<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.min().partial_cmp(other.min()) {
+            Some(Ordering::Equal) => self.max().partial_cmp(other.max()),
+            ordering => ordering,
+        }
+    }
+}
+
+impl<T: PartialOrd> From<(T, T)> for Range<T> {
+    fn from(value: (T, T)) -> Self {
+        Self::new(value.0, value.1)
+    }
+}
+
+impl Range<Decimal> {
+    // Decimal has no NaN, so the only invalid input is a zero-width range
+    pub fn try_new(a: Decimal, b: Decimal, allow_equal: bool) -> Result<Self, RangeError> {
+        if a == b && !allow_equal {
+            return Err(RangeError::Degenerate(a));
+        }
+
+        Ok(Self::new(a, b))
+    }
+
+    pub fn try_into_price_range(self) -> Result<PriceRange, RangeError> {
+        PriceRange::try_from(self)
+    }
+
+    pub fn is_degenerate(&self) -> bool {
+        self.0 == self.1
+    }
+
+    pub fn width_at_least(&self, epsilon: Decimal) -> bool {
+        self.span() >= epsilon
+    }
+
+    pub fn span(&self) -> Decimal {
+        self.max() - self.min()
+    }
+
+    pub fn midpoint(&self) -> Decimal {
+        self.min() + self.span() / Decimal::TWO
+    }
+
+    pub fn midpoint_with_scale(&self, scale: u32) -> Decimal {
+        self.midpoint().trunc_with_scale(scale)
+    }
+
+    // Fraction in 0..=1 of where `value` sits, `None` when it is outside the range
+    pub fn position_of(&self, value: &Decimal) -> Option<Decimal> {
+        if !self.is_within(value) {
+            return None;
+        }
+
+        match inverse_lerp(self.min(), self.max(), value) {
+            Some(fraction) => Some(fraction),
+            None => Some(Decimal::ZERO),
+        }
+    }
+
+    pub fn position_of_with_scale(&self, value: &Decimal, scale: u32) -> Option<Decimal> {
+        self.position_of(value)
+            .map(|fraction| fraction.trunc_with_scale(scale))
+    }
+
+    // Inverse of `position_of`, fractions outside 0..=1 are clamped onto the bounds
+    pub fn value_at(&self, fraction: &Decimal) -> Decimal {
+        lerp(self.min(), self.max(), fraction)
+    }
+
+    // Yields min, min + step, ... up to the last value not above max,
+    // nothing at all when step is not positive
+    pub fn iter_step(&self, step: Decimal) -> impl Iterator<Item = Decimal> {
+        let max = *self.max();
+        let start = match step > Decimal::ZERO {
+            true => Some(*self.min()),
+            false => None,
+        };
+
+        std::iter::successors(start, move |value| value.checked_add(step))
+            .take_while(move |value| *value <= max)
+    }
+
+    // Yields min, min * ratio, ... up to the last value not above max,
+    // nothing at all when ratio is not above one or min is not positive
+    pub fn iter_step_geometric(&self, ratio: Decimal) -> impl Iterator<Item = Decimal> {
+        let max = *self.max();
+        let start = match ratio > Decimal::ONE && self.min() > &Decimal::ZERO {
+            true => Some(*self.min()),
+            false => None,
+        };
+
+        std::iter::successors(start, move |value| value.checked_mul(ratio))
+            .take_while(move |value| *value <= max)
+    }
+
+    // Ranges hold prices, so endpoints that would go below zero are clamped to zero
+    pub fn shift(&self, delta: Decimal) -> Range<Decimal> {
+        let min = std::cmp::max(self.min() + delta, Decimal::ZERO);
+        let max = std::cmp::max(self.max() + delta, Decimal::ZERO);
+
+        Range(min, max)
+    }
+
+    pub fn shift_mut(&mut self, delta: Decimal) {
+        *self = self.shift(delta);
+    }
+
+    // Endpoints are clamped to zero the same way as `shift`
+    pub fn scale(&self, factor: Decimal) -> Range<Decimal> {
+        let a = std::cmp::max(self.min() * factor, Decimal::ZERO);
+        let b = std::cmp::max(self.max() * factor, Decimal::ZERO);
+
+        Range::new(a, b)
+    }
+
+    pub fn scale_mut(&mut self, factor: Decimal) {
+        *self = self.scale(factor);
+    }
+
+    // Widens the span by `pct` around the midpoint, negative values shrink it and
+    // anything below -1 collapses onto the midpoint. The lower bound stops at zero.
+    pub fn expand_percent(&self, pct: Decimal) -> Range<Decimal> {
+        let pct = std::cmp::max(pct, Decimal::NEGATIVE_ONE);
+        let extra = self.span() * pct / Decimal::TWO;
+        let min = std::cmp::max(self.min() - extra, Decimal::ZERO);
+
+        Range(min, self.max() + extra)
+    }
+
+    pub fn clamp(&self, value: &Decimal) -> Decimal {
+        *value.clamp(self.min(), self.max())
+    }
+
+    pub fn clamp_in_place(&self, value: &mut Decimal) {
+        *value = self.clamp(value);
+    }
+
+    pub fn contains_range(&self, other: &Range<Decimal>) -> bool {
+        self.min() <= other.min() && other.max() <= self.max()
+    }
+
+    pub fn overlaps(&self, other: &Range<Decimal>) -> bool {
+        self.min() <= other.max() && other.min() <= self.max()
+    }
+
+    pub fn intersect(&self, other: &Range<Decimal>) -> Option<Range<Decimal>> {
+        if !self.overlaps(other) {
+            return None;
+        }
+
+        let min = std::cmp::max(self.min(), other.min());
+        let max = std::cmp::min(self.max(), other.max());
+
+        Some(Range(*min, *max))
+    }
+
+    // Splits into `n` contiguous sub-ranges whose width is truncated to `scale`,
+    // the last one absorbs the remainder so the union is exactly `self`.
+    // Returns nothing for `n == 0` or when truncation collapses the width to zero.
+    pub fn split_evenly(&self, n: usize, scale: u32) -> Vec<Range<Decimal>> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let min = *self.min();
+        let max = *self.max();
+        let step = ((max - min) / Decimal::from(n)).trunc_with_scale(scale);
+        if step.is_zero() && n > 1 {
+            return Vec::new();
+        }
+
+        let mut ranges = Vec::with_capacity(n);
+        for i in 0..n {
+            let lower = min + step * Decimal::from(i);
+            let upper = match i + 1 == n {
+                true => max,
+                false => min + step * Decimal::from(i + 1),
+            };
+
+            ranges.push(Range(lower, upper));
+        }
+
+        ranges
+    }
+
+    // Returns `n` evenly spaced points truncated to `scale`, the first is min and
+    // the last is exactly max; a single point is min
+    pub fn linspace(&self, n: usize, scale: u32) -> Vec<Decimal> {
+        let min = *self.min();
+        let max = *self.max();
+        match n {
+            0 => return Vec::new(),
+            1 => return vec![min],
+            _ => {}
+        }
+
+        let step = (max - min) / Decimal::from(n - 1);
+        let mut points = Vec::with_capacity(n);
+        for i in 0..n - 1 {
+            let point = min + step * Decimal::from(i);
+            points.push(point.trunc_with_scale(scale));
+        }
+        points.push(max);
+
+        points
+    }
+
+    // Returns `n + 1` levels from min to max where each level is the previous one
+    // times a constant ratio truncated to `scale`; the last level is exactly max
+    pub fn split_geometric(&self, n: usize, scale: u32) -> Result<Vec<Decimal>, RangeError> {
+        let min = *self.min();
+        let max = *self.max();
+        if min <= Decimal::ZERO {
+            return Err(RangeError::NonPositiveMin(min));
+        }
+
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        let ratio = match nth_root(max / min, n as u64) {
+            Some(ratio) => ratio.trunc_with_scale(scale),
+            None => Decimal::ONE,
+        };
+        if ratio <= Decimal::ONE {
+            return Err(RangeError::UnitRatio);
+        }
+
+        let mut levels = Vec::with_capacity(n + 1);
+        levels.push(min);
+        for i in 1..n {
+            let level = levels[i - 1] * ratio;
+            levels.push(level.trunc_with_scale(scale));
+        }
+        levels.push(max);
+
+        Ok(levels)
+    }
+
+    pub fn split_geometric_ranges(
+        &self,
+        n: usize,
+        scale: u32,
+    ) -> Result<Vec<Range<Decimal>>, RangeError> {
+        let levels = self.split_geometric(n, scale)?;
+        let ranges = levels
+            .windows(2)
+            .map(|pair| Range(pair[0], pair[1]))
+            .collect();
+
+        Ok(ranges)
+    }
+}
+
+// `powd` alone is only accurate to about 1e-9, so polish it with Newton's method
+fn nth_root(value: Decimal, n: u64) -> Option<Decimal> {
+    let degree = Decimal::from(n);
+    let mut root = value.checked_powd(Decimal::ONE / degree)?;
+    for _ in 0..3 {
+        let power = root.checked_powi(n as i64 - 1)?;
+        root -= (power * root - value) / (degree * power);
+    }
+
+    Some(root)
+}
+
+// A `Range<Decimal>` whose endpoints are known not to be negative
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceRange(Range<Decimal>);
+
+impl PriceRange {
+    pub fn new(a: Decimal, b: Decimal) -> Result<Self, RangeError> {
+        Self::try_from(Range::new(a, b))
+    }
+
+    pub fn into_inner(self) -> Range<Decimal> {
+        self.0
+    }
+}
+
+impl TryFrom<Range<Decimal>> for PriceRange {
+    type Error = RangeError;
+
+    fn try_from(value: Range<Decimal>) -> Result<Self, Self::Error> {
+        if value.min().is_sign_negative() && !value.min().is_zero() {
+            return Err(RangeError::NegativeMin(*value.min()));
+        }
+
+        if value.max().is_sign_negative() && !value.max().is_zero() {
+            return Err(RangeError::NegativeMax(*value.max()));
+        }
+
+        Ok(Self(value))
+    }
+}
+
+impl From<PriceRange> for Range<Decimal> {
+    fn from(value: PriceRange) -> Self {
+        value.0
+    }
+}
+
+impl std::ops::Deref for PriceRange {
+    type Target = Range<Decimal>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for Range<Decimal> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.min(), self.max())
+    }
+}
+
+impl FromStr for Range<Decimal> {
+    type Err = ParseRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (a, b) = s.split_once("..").ok_or(ParseRangeError::Separator)?;
+        let a = Decimal::from_str(a.trim()).map_err(ParseRangeError::Start)?;
+        let b = Decimal::from_str(b.trim()).map_err(ParseRangeError::End)?;
+
+        Ok(Self::new(a, b))
+    }
+}
+
+// Orders by min then max, and stores every range with its smaller value first
+pub fn sort_ranges(ranges: &mut [Range<Decimal>]) {
+    for range in ranges.iter_mut() {
+        if range.1 < range.0 {
+            std::mem::swap(&mut range.0, &mut range.1);
+        }
+    }
+
+    ranges.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+}
+
+// Whether each range ends at or before the next one starts, the order `sort_ranges` leaves
+// non-overlapping ranges in. Only such ranges can be searched with `partition_point`.
+pub fn is_sorted_disjoint(ranges: &[Range<Decimal>]) -> bool {
+    ranges.windows(2).all(|pair| pair[0].max() <= pair[1].min())
+}
+
+// Sorts the ranges and coalesces overlapping or touching ones into a disjoint set
+pub fn merge_ranges(ranges: Vec<Range<Decimal>>) -> Vec<Range<Decimal>> {
+    let mut ranges: Vec<Range<Decimal>> = ranges
+        .into_iter()
+        .map(|range| Range::new(range.0, range.1))
+        .collect();
+    sort_ranges(&mut ranges);
+
+    let mut merged: Vec<Range<Decimal>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.overlaps(&range) => {
+                if range.1 > last.1 {
+                    last.1 = range.1;
+                }
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    #[serde(rename = "FLOOR")]
+    Floor,
+
+    #[serde(rename = "CEIL")]
+    Ceil,
+
+    #[serde(rename = "NEAREST")]
+    Nearest, // Ties round away from zero
+}
+
+pub fn round_to_tick(
+    price: Decimal,
+    tick: Decimal,
+    mode: RoundingMode,
+) -> Result<Decimal, MathError> {
+    if tick <= Decimal::ZERO {
+        return Err(MathError::InvalidTick(tick));
+    }
+
+    let ticks = price / tick;
+    let ticks = match mode {
+        RoundingMode::Floor => ticks.floor(),
+        RoundingMode::Ceil => ticks.ceil(),
+        RoundingMode::Nearest => {
+            ticks.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        }
+    };
+
+    Ok(ticks * tick)
+}
+
+// Levels start, start * ratio, ... truncated to `scale` and kept below `limit`
+pub fn compound_levels(
+    start: Decimal,
+    ratio: Decimal,
+    limit: Decimal,
+    max_levels: usize,
+    scale: u32,
+) -> Result<Vec<Decimal>, MathError> {
+    if start <= Decimal::ZERO {
+        return Err(MathError::NonPositiveStart(start));
+    }
+
+    if ratio <= Decimal::ONE {
+        return Err(MathError::InvalidRatio(ratio));
+    }
+
+    let mut levels = vec![start];
+    let mut last = start;
+    loop {
+        let level = (last * ratio).trunc_with_scale(scale);
+        if level >= limit {
+            break;
+        }
+
+        // The increase truncated away, every further level would collapse onto this one
+        if level <= last {
+            return Err(MathError::InvalidRatio(ratio));
+        }
+
+        if levels.len() >= max_levels {
+            return Err(MathError::TooManyLevels(max_levels));
+        }
+
+        levels.push(level);
+        last = level;
+    }
+
+    Ok(levels)
+}
+
+// Running mean, so large inputs never build up an overflowing sum
+pub fn mean(values: &[Decimal]) -> Option<Decimal> {
+    if values.is_empty() {
+        return None;
+    }
+
+    rescaled(values, |values| {
+        let mut mean = Decimal::ZERO;
+        for (i, value) in values.iter().enumerate() {
+            let delta = value.checked_sub(mean)?;
+            mean = mean.checked_add(delta.checked_div(Decimal::from(i + 1))?)?;
+        }
+
+        Some(mean)
+    })
+}
+
+// Population standard deviation, accumulated with Welford's method
+pub fn stddev(values: &[Decimal]) -> Option<Decimal> {
+    if values.is_empty() {
+        return None;
+    }
+
+    rescaled(values, |values| {
+        let mut mean = Decimal::ZERO;
+        let mut squares = Decimal::ZERO;
+        for (i, value) in values.iter().enumerate() {
+            let delta = value.checked_sub(mean)?;
+            mean = mean.checked_add(delta.checked_div(Decimal::from(i + 1))?)?;
+            squares = squares.checked_add(delta.checked_mul(value.checked_sub(mean)?)?)?;
+        }
+
+        squares.checked_div(Decimal::from(values.len()))?.sqrt()
+    })
+}
+
+// Runs a statistic that scales linearly with its inputs, and when it overflows runs it
+// again on the inputs divided by a power of ten at least their largest magnitude
+fn rescaled(
+    values: &[Decimal],
+    statistic: impl Fn(&[Decimal]) -> Option<Decimal>,
+) -> Option<Decimal> {
+    if let Some(result) = statistic(values) {
+        return Some(result);
+    }
+
+    let largest = values.iter().map(|value| value.abs()).max()?;
+    let mut factor = Decimal::ONE;
+    while factor < largest {
+        match factor.checked_mul(Decimal::TEN) {
+            Some(next) => factor = next,
+            None => break,
+        }
+    }
+
+    let scaled: Vec<Decimal> = values.iter().map(|value| value / factor).collect();
+    statistic(&scaled)?.checked_mul(factor)
+}
+
+pub fn minmax(values: &[Decimal]) -> Option<Range<Decimal>> {
+    let min = values.iter().min()?;
+    let max = values.iter().max()?;
+
+    Some(Range(*min, *max))
+}
+
+// Largest peak-to-trough decline as a fraction of the peak, with the peak and
+// trough indices. Rising or flat series give a zero drawdown at index 0, and
+// non-positive peaks are skipped since the fraction is undefined for them.
+pub fn max_drawdown(series: &[Decimal]) -> Option<(Decimal, usize, usize)> {
+    if series.len() < 2 {
+        return None;
+    }
+
+    let mut peak = 0;
+    let mut drawdown = (Decimal::ZERO, 0, 0);
+    for (i, value) in series.iter().enumerate() {
+        if value > &series[peak] {
+            peak = i;
+            continue;
+        }
+
+        if series[peak] <= Decimal::ZERO {
+            continue;
+        }
+
+        let decline = (series[peak] - value) / series[peak];
+        if decline > drawdown.0 {
+            drawdown = (decline, peak, i);
+        }
+    }
+
+    Some(drawdown)
+}
+
+// Floors onto a multiple of `step` so an order never exceeds the available quantity
+pub fn round_to_step(quantity: Decimal, step: Decimal) -> Result<Decimal, MathError> {
+    if step <= Decimal::ZERO {
+        return Err(MathError::InvalidStep(step));
+    }
+
+    Ok((quantity / step).floor() * step)
+}
+
+// The helpers below do plain Decimal arithmetic without any rounding,
+// callers truncate the results to the scale they need
+
+// Fractional change from `from` to `to`, `None` when `from` is zero
+pub fn percent_change(from: &Decimal, to: &Decimal) -> Option<Decimal> {
+    if from.is_zero() {
+        return None;
+    }
+
+    Some((to - from) / from)
+}
+
+// Value `t` of the way from `a` to `b`, with `t` clamped to 0..=1
+pub fn lerp(a: &Decimal, b: &Decimal, t: &Decimal) -> Decimal {
+    let t = t.clamp(&Decimal::ZERO, &Decimal::ONE);
+    a + (b - a) * t
+}
+
+// Fraction of the way `value` sits from `a` to `b`, unclamped so values past
+// either end give results outside 0..=1; `None` when `a == b`
+pub fn inverse_lerp(a: &Decimal, b: &Decimal, value: &Decimal) -> Option<Decimal> {
+    if a == b {
+        return None;
+    }
+
+    Some((value - a) / (b - a))
+}
+
+// Moves `value` by `bps` basis points, e.g. 100 bps is +1%
+pub fn apply_bps(value: &Decimal, bps: &Decimal) -> Decimal {
+    value * (Decimal::ONE + bps / BPS)
+}
+
+// Change from `a` to `b` in basis points, `None` when `a` is zero
+pub fn bps_between(a: &Decimal, b: &Decimal) -> Option<Decimal> {
+    percent_change(a, b).map(|change| change * BPS)
+}
+
+const BPS: Decimal = Decimal::from_parts(10000, 0, 0, false, 0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Bound {
+    #[default]
+    #[serde(rename = "INCLUSIVE")]
+    Inclusive,
+
+    #[serde(rename = "EXCLUSIVE")]
+    Exclusive,
+}
+
+impl Bound {
+    pub fn is_inclusive(&self) -> bool {
+        matches!(self, Self::Inclusive)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeError {
+    Degenerate(Decimal),
+    NonPositiveMin(Decimal),
+    NegativeMin(Decimal),
+    NegativeMax(Decimal),
+    UnitRatio,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Degenerate(value) => write!(f, "range bounds are both {}", value),
+            Self::NonPositiveMin(value) => write!(f, "range min {} is not positive", value),
+            Self::NegativeMin(value) => write!(f, "range min {} is negative", value),
+            Self::NegativeMax(value) => write!(f, "range max {} is negative", value),
+            Self::UnitRatio => write!(f, "range ratio rounds to one"),
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MathError {
+    InvalidTick(Decimal),
+    InvalidStep(Decimal),
+    InvalidRatio(Decimal),
+    NonPositiveStart(Decimal),
+    TooManyLevels(usize),
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTick(value) => write!(f, "tick size {} is not positive", value),
+            Self::InvalidStep(value) => write!(f, "step size {} is not positive", value),
+            Self::InvalidRatio(value) => write!(f, "ratio {} does not increase levels", value),
+            Self::NonPositiveStart(value) => write!(f, "start level {} is not positive", value),
+            Self::TooManyLevels(value) => write!(f, "more than {} levels", value),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseRangeError {
+    Separator,
+    Start(rust_decimal::Error),
+    End(rust_decimal::Error),
+}
+
+impl fmt::Display for ParseRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Separator => write!(f, "range is missing the `..` separator"),
+            Self::Start(e) => write!(f, "invalid range start: {}", e),
+            Self::End(e) => write!(f, "invalid range end: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseRangeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(
+            Range::new(dec("205"), dec("200")),
+            Range(dec("200"), dec("205"))
+        );
+        assert_eq!(
+            Range::new(dec("200"), dec("205")),
+            Range(dec("200"), dec("205"))
+        );
+        assert_eq!(Range::new(dec("1"), dec("1")), Range(dec("1"), dec("1")));
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(
+            Range::try_new(dec("205"), dec("200"), false),
+            Ok(Range(dec("200"), dec("205")))
+        );
+        assert_eq!(
+            Range::try_new(dec("1"), dec("1"), false),
+            Err(RangeError::Degenerate(dec("1")))
+        );
+        assert_eq!(
+            Range::try_new(dec("1"), dec("1"), true),
+            Ok(Range(dec("1"), dec("1")))
+        );
+    }
+
+    #[test]
+    fn test_is_within_bounds() {
+        let range = Range(dec("50"), dec("100"));
+        assert!(range.is_within(&dec("50")));
+        assert!(range.is_within(&dec("100")));
+
+        assert!(range.is_within_bounds(&dec("50"), Bound::Inclusive, Bound::Exclusive));
+        assert!(!range.is_within_bounds(&dec("100"), Bound::Inclusive, Bound::Exclusive));
+        assert!(!range.is_within_bounds(&dec("50"), Bound::Exclusive, Bound::Inclusive));
+        assert!(range.is_within_bounds(&dec("100"), Bound::Exclusive, Bound::Inclusive));
+        assert!(range.is_within_bounds(&dec("75"), Bound::Exclusive, Bound::Exclusive));
+    }
+
+    #[test]
+    fn test_price_range() {
+        let range = PriceRange::new(dec("100"), dec("0")).unwrap();
+        assert_eq!(range.into_inner(), Range(dec("0"), dec("100")));
+
+        assert_eq!(
+            Range(dec("-5"), dec("10")).try_into_price_range(),
+            Err(RangeError::NegativeMin(dec("-5")))
+        );
+        assert_eq!(
+            PriceRange::try_from(Range(dec("-5"), dec("-1"))),
+            Err(RangeError::NegativeMin(dec("-5")))
+        );
+        assert_eq!(
+            RangeError::NegativeMin(dec("-5")).to_string(),
+            "range min -5 is negative"
+        );
+        assert!(PriceRange::new(dec("-0"), dec("1")).is_ok());
+    }
+
+    #[test]
+    fn test_is_degenerate() {
+        assert!(Range(dec("1.0"), dec("1")).is_degenerate());
+        assert!(!Range(dec("1.000001"), dec("1")).is_degenerate());
+
+        let range = Range(dec("1.01"), dec("1"));
+        assert!(range.width_at_least(dec("0.01")));
+        assert!(!range.width_at_least(dec("0.011")));
+    }
+
+    #[test]
+    fn test_midpoint_and_span() {
+        let range = Range(dec("100"), dec("50"));
+        assert_eq!(range.span(), dec("50"));
+        assert_eq!(range.midpoint(), dec("75"));
+
+        let range = Range(dec("50"), dec("58.333333"));
+        assert_eq!(range.midpoint(), dec("54.1666665"));
+        assert_eq!(range.midpoint_with_scale(6), dec("54.166666"));
+
+        // Pseudo random pairs, including negatives and mixed scales
+        let mut seed: i64 = 7;
+        for _ in 0..1000 {
+            seed = (seed * 1103515245 + 12345) % 2147483648;
+            let a = Decimal::new(seed % 1000000 - 500000, (seed % 7) as u32);
+            seed = (seed * 1103515245 + 12345) % 2147483648;
+            let b = Decimal::new(seed % 1000000 - 500000, (seed % 5) as u32);
+
+            let range = Range(a, b);
+            assert!(range.span() >= Decimal::ZERO);
+            assert!(range.min() <= &range.midpoint());
+            assert!(range.midpoint() <= *range.max());
+        }
+    }
+
+    #[test]
+    fn test_position_of() {
+        let range = Range(dec("100"), dec("50"));
+        assert_eq!(range.position_of(&dec("50")), Some(dec("0")));
+        assert_eq!(range.position_of(&dec("100")), Some(dec("1")));
+        assert_eq!(range.position_of(&dec("62.5")), Some(dec("0.25")));
+        assert_eq!(range.position_of(&dec("49.99")), None);
+        assert_eq!(range.position_of(&dec("100.01")), None);
+
+        let range = Range(dec("0"), dec("3"));
+        assert_eq!(
+            range.position_of_with_scale(&dec("1"), 4),
+            Some(dec("0.3333"))
+        );
+
+        let range = Range(dec("7"), dec("7"));
+        assert_eq!(range.position_of(&dec("7")), Some(dec("0")));
+        assert_eq!(range.position_of(&dec("7.1")), None);
+    }
+
+    #[test]
+    fn test_value_at() {
+        let range = Range(dec("100"), dec("50"));
+        assert_eq!(range.value_at(&dec("0")), dec("50"));
+        assert_eq!(range.value_at(&dec("1")), dec("100"));
+        assert_eq!(range.value_at(&dec("0.25")), dec("62.5"));
+        assert_eq!(range.value_at(&dec("1.5")), dec("100"));
+        assert_eq!(range.value_at(&dec("-1")), dec("50"));
+        assert_eq!(
+            range.value_at(&range.position_of(&dec("83.2")).unwrap()),
+            dec("83.2")
+        );
+    }
+
+    #[test]
+    fn test_iter_step() {
+        let range = Range(dec("2"), dec("1"));
+        let values: Vec<Decimal> = range.iter_step(dec("0.5")).collect();
+        assert_eq!(values, vec![dec("1"), dec("1.5"), dec("2")]);
+
+        let values: Vec<Decimal> = range.iter_step(dec("0.3")).collect();
+        assert_eq!(values, vec![dec("1"), dec("1.3"), dec("1.6"), dec("1.9")]);
+
+        assert_eq!(range.iter_step(dec("0")).count(), 0);
+        assert_eq!(range.iter_step(dec("-1")).count(), 0);
+        assert_eq!(
+            range.iter_step(dec("5")).collect::<Vec<_>>(),
+            vec![dec("1")]
+        );
+    }
+
+    #[test]
+    fn test_iter_step_geometric() {
+        let range = Range(dec("100"), dec("130"));
+        let values: Vec<Decimal> = range.iter_step_geometric(dec("1.1")).collect();
+        assert_eq!(values, vec![dec("100"), dec("110.0"), dec("121.00")]);
+
+        assert_eq!(range.iter_step_geometric(dec("1")).count(), 0);
+        assert_eq!(range.iter_step_geometric(dec("0.9")).count(), 0);
+
+        let range = Range(dec("0"), dec("130"));
+        assert_eq!(range.iter_step_geometric(dec("1.1")).count(), 0);
+    }
+
+    #[test]
+    fn test_shift_and_scale() {
+        let mut range = Range(dec("100"), dec("50"));
+        assert_eq!(range.shift(dec("10")), Range(dec("60"), dec("110")));
+        assert_eq!(range.shift(dec("-60")), Range(dec("0"), dec("40")));
+        assert_eq!(range.shift(dec("-200")), Range(dec("0"), dec("0")));
+
+        assert_eq!(range.scale(dec("1.5")), Range(dec("75.0"), dec("150.0")));
+        assert_eq!(range.scale(dec("0")), Range(dec("0"), dec("0")));
+        assert_eq!(range.scale(dec("-1")), Range(dec("0"), dec("0")));
+
+        range.shift_mut(dec("-25"));
+        assert_eq!(range, Range(dec("25"), dec("75")));
+        range.scale_mut(dec("2"));
+        assert_eq!(range, Range(dec("50"), dec("150")));
+    }
+
+    #[test]
+    fn test_expand_percent() {
+        let range = Range(dec("100"), dec("50"));
+        let expanded = range.expand_percent(dec("0.1"));
+        assert_eq!(expanded, Range(dec("47.5"), dec("102.5")));
+        assert_eq!(expanded.span(), range.span() * dec("1.1"));
+        assert_eq!(expanded.midpoint(), range.midpoint());
+
+        assert_eq!(
+            range.expand_percent(dec("-0.5")),
+            Range(dec("62.5"), dec("87.5"))
+        );
+        assert_eq!(range.expand_percent(dec("-3")), Range(dec("75"), dec("75")));
+        assert_eq!(range.expand_percent(dec("3")), Range(dec("0"), dec("175")));
+    }
+
+    #[test]
+    fn test_clamp() {
+        let range = Range(dec("100"), dec("50"));
+        assert_eq!(range.clamp(&dec("10")), dec("50"));
+        assert_eq!(range.clamp(&dec("50")), dec("50"));
+        assert_eq!(range.clamp(&dec("75")), dec("75"));
+        assert_eq!(range.clamp(&dec("100")), dec("100"));
+        assert_eq!(range.clamp(&dec("100.01")), dec("100"));
+
+        let mut price = dec("120");
+        range.clamp_in_place(&mut price);
+        assert_eq!(price, dec("100"));
+    }
+
+    #[test]
+    fn test_contains_range() {
+        let range = Range(dec("50"), dec("100"));
+        assert!(range.contains_range(&Range(dec("100"), dec("50"))));
+        assert!(range.contains_range(&Range(dec("60"), dec("70"))));
+        assert!(range.contains_range(&Range(dec("50"), dec("50"))));
+        assert!(!range.contains_range(&Range(dec("40"), dec("70"))));
+        assert!(!range.contains_range(&Range(dec("90"), dec("110"))));
+        assert!(!range.contains_range(&Range(dec("0"), dec("200"))));
+    }
+
+    #[test]
+    fn test_generic_range() {
+        let range = Range(10usize, 2);
+        assert_eq!(range.min(), &2);
+        assert_eq!(range.max(), &10);
+        assert!(range.is_within(&2));
+        assert!(range.is_within(&10));
+        assert!(!range.is_within(&11));
+        assert!(!range.is_within_bounds(&10, Bound::Inclusive, Bound::Exclusive));
+
+        let range: Range<u128> = Range::new(1716000000000, 1715000000000);
+        assert_eq!(range, Range(1715000000000, 1716000000000));
+        assert!(range.is_within(&1715500000000));
+        assert!(!range.is_within(&1714999999999));
+    }
+
+    #[test]
+    fn test_intersect() {
+        // Disjoint
+        let a = Range(dec("10"), dec("20"));
+        let b = Range(dec("30"), dec("40"));
+        assert!(!a.overlaps(&b));
+        assert_eq!(a.intersect(&b), None);
+
+        // Touching at an endpoint, with reversed fields
+        let b = Range(dec("30"), dec("20"));
+        assert!(a.overlaps(&b));
+        assert_eq!(a.intersect(&b), Some(Range(dec("20"), dec("20"))));
+
+        // Containment
+        let b = Range(dec("18"), dec("12"));
+        assert!(a.overlaps(&b));
+        assert_eq!(a.intersect(&b), Some(Range(dec("12"), dec("18"))));
+        assert_eq!(b.intersect(&a), Some(Range(dec("12"), dec("18"))));
+
+        // Partial overlap
+        let b = Range(dec("15"), dec("25"));
+        assert_eq!(a.intersect(&b), Some(Range(dec("15"), dec("20"))));
+
+        // Single point
+        let b = Range(dec("15"), dec("15"));
+        assert_eq!(a.intersect(&b), Some(Range(dec("15"), dec("15"))));
+        let b = Range(dec("25"), dec("25"));
+        assert_eq!(a.intersect(&b), None);
+    }
+
+    #[test]
+    fn test_linspace() {
+        let range = Range(dec("10"), dec("0"));
+        assert_eq!(range.linspace(0, 2), vec![]);
+        assert_eq!(range.linspace(1, 2), vec![dec("0")]);
+        assert_eq!(range.linspace(2, 2), vec![dec("0"), dec("10")]);
+        assert_eq!(
+            range.linspace(5, 2),
+            vec![dec("0"), dec("2.5"), dec("5"), dec("7.5"), dec("10")]
+        );
+        assert_eq!(
+            range.linspace(4, 2),
+            vec![dec("0"), dec("3.33"), dec("6.66"), dec("10")]
+        );
+
+        let range = Range(dec("7"), dec("7"));
+        assert_eq!(range.linspace(3, 2), vec![dec("7"), dec("7"), dec("7")]);
+    }
+
+    #[test]
+    fn test_split_evenly() {
+        let range = Range(dec("100"), dec("50"));
+        assert_eq!(range.split_evenly(0, 6), vec![]);
+        assert_eq!(range.split_evenly(1, 6), vec![Range(dec("50"), dec("100"))]);
+        assert_eq!(
+            range.split_evenly(3, 6),
+            vec![
+                Range(dec("50"), dec("66.666666")),
+                Range(dec("66.666666"), dec("83.333332")),
+                Range(dec("83.333332"), dec("100")),
+            ]
+        );
+
+        let range = Range(dec("1.00001"), dec("1.00002"));
+        assert_eq!(range.split_evenly(2, 4), vec![]);
+        assert_eq!(range.split_evenly(2, 6).len(), 2);
+    }
+
+    #[test]
+    fn test_split_geometric() {
+        let range = Range(dec("100"), dec("400"));
+        assert_eq!(
+            range.split_geometric(2, 6),
+            Ok(vec![dec("100"), dec("200"), dec("400")])
+        );
+        assert_eq!(
+            range.split_geometric_ranges(2, 6),
+            Ok(vec![
+                Range(dec("100"), dec("200")),
+                Range(dec("200"), dec("400"))
+            ])
+        );
+
+        let range = Range(dec("50"), dec("60"));
+        assert_eq!(
+            range.split_geometric(3, 6),
+            Ok(vec![dec("50"), dec("53.1329"), dec("56.462101"), dec("60")])
+        );
+
+        assert_eq!(
+            Range(dec("0"), dec("60")).split_geometric(3, 6),
+            Err(RangeError::NonPositiveMin(dec("0")))
+        );
+        assert_eq!(
+            Range(dec("50"), dec("50.001")).split_geometric(3, 2),
+            Err(RangeError::UnitRatio)
+        );
+    }
+
+    #[test]
+    fn test_display_and_from_str() {
+        let range = Range(dec("100.50"), dec("50"));
+        assert_eq!(range.to_string(), "50..100.50");
+        assert_eq!(
+            Range::from_str(&range.to_string()),
+            Ok(Range(dec("50"), dec("100.50")))
+        );
+
+        assert_eq!(
+            Range::from_str(" 50 .. 100 "),
+            Ok(Range(dec("50"), dec("100")))
+        );
+        assert_eq!(Range::from_str("100..50"), Ok(Range(dec("50"), dec("100"))));
+        assert_eq!(
+            Range::from_str("-5..0.001"),
+            Ok(Range(dec("-5"), dec("0.001")))
+        );
+
+        assert_eq!(
+            Range::<Decimal>::from_str("50-100"),
+            Err(ParseRangeError::Separator)
+        );
+        assert!(matches!(
+            Range::<Decimal>::from_str("abc..100"),
+            Err(ParseRangeError::Start(_))
+        ));
+        assert!(matches!(
+            Range::<Decimal>::from_str("50.."),
+            Err(ParseRangeError::End(_))
+        ));
+    }
+
+    #[test]
+    fn test_round_to_tick() {
+        use RoundingMode::*;
+
+        let price = dec("56.341251506596");
+        assert_eq!(round_to_tick(price, dec("0.01"), Floor), Ok(dec("56.34")));
+        assert_eq!(round_to_tick(price, dec("0.01"), Ceil), Ok(dec("56.35")));
+        assert_eq!(round_to_tick(price, dec("0.01"), Nearest), Ok(dec("56.34")));
+
+        assert_eq!(round_to_tick(price, dec("0.5"), Floor), Ok(dec("56.0")));
+        assert_eq!(round_to_tick(price, dec("0.5"), Ceil), Ok(dec("56.5")));
+        assert_eq!(round_to_tick(price, dec("0.5"), Nearest), Ok(dec("56.5")));
+
+        assert_eq!(round_to_tick(price, dec("5"), Floor), Ok(dec("55")));
+        assert_eq!(round_to_tick(price, dec("5"), Ceil), Ok(dec("60")));
+        assert_eq!(round_to_tick(price, dec("5"), Nearest), Ok(dec("55")));
+        assert_eq!(round_to_tick(dec("57.5"), dec("5"), Nearest), Ok(dec("60")));
+
+        // Already on a tick
+        for mode in [Floor, Ceil, Nearest] {
+            assert_eq!(
+                round_to_tick(dec("56.5"), dec("0.5"), mode),
+                Ok(dec("56.5"))
+            );
+            assert_eq!(round_to_tick(dec("55"), dec("5"), mode), Ok(dec("55")));
+        }
+
+        assert_eq!(
+            round_to_tick(price, dec("0"), Floor),
+            Err(MathError::InvalidTick(dec("0")))
+        );
+        assert_eq!(
+            round_to_tick(price, dec("-0.01"), Floor),
+            Err(MathError::InvalidTick(dec("-0.01")))
+        );
+    }
+
+    #[test]
+    fn test_compound_levels() {
+        assert_eq!(
+            compound_levels(dec("100"), dec("1.05"), dec("120"), 100, 12),
+            Ok(vec![dec("100"), dec("105"), dec("110.25"), dec("115.7625")])
+        );
+        assert_eq!(
+            compound_levels(dec("100"), dec("1.05"), dec("100"), 100, 12),
+            Ok(vec![dec("100")])
+        );
+
+        assert_eq!(
+            compound_levels(dec("100"), dec("1"), dec("120"), 100, 12),
+            Err(MathError::InvalidRatio(dec("1")))
+        );
+        assert_eq!(
+            compound_levels(dec("1"), dec("1.0000000000001"), dec("2"), 100, 12),
+            Err(MathError::InvalidRatio(dec("1.0000000000001")))
+        );
+        assert_eq!(
+            compound_levels(dec("0"), dec("1.05"), dec("120"), 100, 12),
+            Err(MathError::NonPositiveStart(dec("0")))
+        );
+        assert_eq!(
+            compound_levels(dec("100"), dec("1.05"), dec("120"), 2, 12),
+            Err(MathError::TooManyLevels(2))
+        );
+    }
+
+    #[test]
+    fn test_statistics() {
+        assert_eq!(mean(&[]), None);
+        assert_eq!(stddev(&[]), None);
+        assert_eq!(minmax(&[]), None);
+
+        let values = [
+            dec("2"),
+            dec("4"),
+            dec("4"),
+            dec("4"),
+            dec("5"),
+            dec("5"),
+            dec("7"),
+            dec("9"),
+        ];
+        assert_eq!(mean(&values), Some(dec("5")));
+        assert_eq!(stddev(&values), Some(dec("2")));
+        assert_eq!(minmax(&values), Some(Range(dec("2"), dec("9"))));
+
+        assert_eq!(mean(&[dec("1.5")]), Some(dec("1.5")));
+        assert_eq!(stddev(&[dec("1.5")]), Some(dec("0")));
+
+        // A plain sum of these would overflow
+        let values = [Decimal::MAX, Decimal::MAX, Decimal::MAX];
+        assert_eq!(mean(&values), Some(Decimal::MAX));
+        assert_eq!(stddev(&values), Some(dec("0")));
+
+        // The squared deviations, or the deviations themselves, overflow without rescaling
+        let values = [dec("0"), dec("1000000000000000")];
+        assert_eq!(mean(&values), Some(dec("500000000000000")));
+        assert_eq!(stddev(&values), Some(dec("500000000000000")));
+
+        let values = [Decimal::MAX, -Decimal::MAX];
+        assert_eq!(mean(&values), Some(dec("0")));
+        assert_eq!(stddev(&values), Some(Decimal::MAX));
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        assert_eq!(max_drawdown(&[]), None);
+        assert_eq!(max_drawdown(&[dec("100")]), None);
+
+        let series = [
+            dec("100"),
+            dec("120"),
+            dec("90"),
+            dec("110"),
+            dec("60"),
+            dec("130"),
+        ];
+        assert_eq!(max_drawdown(&series), Some((dec("0.5"), 1, 4)));
+
+        let series = [dec("100"), dec("80"), dec("150"), dec("135")];
+        assert_eq!(max_drawdown(&series), Some((dec("0.2"), 0, 1)));
+
+        let series = [dec("1"), dec("2"), dec("3")];
+        assert_eq!(max_drawdown(&series), Some((dec("0"), 0, 0)));
+
+        let series = [dec("5"), dec("5"), dec("5")];
+        assert_eq!(max_drawdown(&series), Some((dec("0"), 0, 0)));
+    }
+
+    #[test]
+    fn test_round_to_step() {
+        let step = dec("0.00001");
+        assert_eq!(round_to_step(dec("0.09841489"), step), Ok(dec("0.09841")));
+        assert_eq!(round_to_step(dec("0.09841"), step), Ok(dec("0.09841")));
+        assert_eq!(round_to_step(dec("0.000009"), step), Ok(dec("0")));
+        assert_eq!(round_to_step(dec("13.375"), dec("0.5")), Ok(dec("13.0")));
+        assert_eq!(
+            round_to_step(dec("1"), dec("0")),
+            Err(MathError::InvalidStep(dec("0")))
+        );
+    }
+
+    #[test]
+    fn test_percent_change() {
+        assert_eq!(percent_change(&dec("50"), &dec("55")), Some(dec("0.1")));
+        assert_eq!(percent_change(&dec("50"), &dec("40")), Some(dec("-0.2")));
+        assert_eq!(percent_change(&dec("50"), &dec("50")), Some(dec("0")));
+        assert_eq!(percent_change(&dec("0"), &dec("50")), None);
+    }
+
+    #[test]
+    fn test_lerp() {
+        let (a, b) = (dec("80"), dec("200"));
+        assert_eq!(lerp(&a, &b, &dec("0")), dec("80"));
+        assert_eq!(lerp(&a, &b, &dec("1")), dec("200"));
+        assert_eq!(lerp(&a, &b, &dec("0.3")), dec("116.0"));
+        assert_eq!(lerp(&a, &b, &dec("2")), dec("200"));
+        assert_eq!(lerp(&b, &a, &dec("0.3")), dec("164.0"));
+        assert_eq!(lerp(&a, &a, &dec("0.3")), dec("80"));
+
+        assert_eq!(inverse_lerp(&a, &b, &dec("80")), Some(dec("0")));
+        assert_eq!(inverse_lerp(&a, &b, &dec("200")), Some(dec("1")));
+        assert_eq!(inverse_lerp(&a, &b, &dec("116")), Some(dec("0.3")));
+        assert_eq!(inverse_lerp(&b, &a, &dec("164")), Some(dec("0.3")));
+        assert_eq!(inverse_lerp(&a, &b, &dec("320")), Some(dec("2")));
+        assert_eq!(inverse_lerp(&a, &a, &dec("80")), None);
+    }
+
+    #[test]
+    fn test_bps() {
+        assert_eq!(apply_bps(&dec("100"), &dec("100")), dec("101"));
+        assert_eq!(apply_bps(&dec("100"), &dec("-25")), dec("99.75"));
+        assert_eq!(apply_bps(&dec("100"), &dec("0")), dec("100"));
+
+        assert_eq!(bps_between(&dec("100"), &dec("101")), Some(dec("100")));
+        assert_eq!(bps_between(&dec("100"), &dec("99.75")), Some(dec("-25")));
+        assert_eq!(bps_between(&dec("0"), &dec("1")), None);
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert_eq!(Range(dec("2"), dec("1")), Range(dec("1"), dec("2")));
+        assert_ne!(Range(dec("1"), dec("3")), Range(dec("1"), dec("2")));
+        assert!(Range(dec("1"), dec("2")) < Range(dec("1"), dec("3")));
+        assert!(Range(dec("3"), dec("1")) < Range(dec("2"), dec("2")));
+
+        let mut ranges = vec![
+            Range(dec("60"), dec("70")),
+            Range(dec("20"), dec("10")),
+            Range(dec("10"), dec("15")),
+            Range(dec("40"), dec("50")),
+        ];
+        sort_ranges(&mut ranges);
+
+        assert_eq!(
+            ranges,
+            vec![
+                Range(dec("10"), dec("15")),
+                Range(dec("10"), dec("20")),
+                Range(dec("40"), dec("50")),
+                Range(dec("60"), dec("70")),
+            ]
+        );
+        assert_eq!(ranges[1].0, dec("10"));
+        assert_eq!(
+            serde_json::to_string(&ranges).unwrap(),
+            r#"[["10","15"],["10","20"],["40","50"],["60","70"]]"#
+        );
+
+        assert!(!is_sorted_disjoint(&ranges));
+        assert!(is_sorted_disjoint(&ranges[1..]));
+        assert!(is_sorted_disjoint(&[]));
+        assert!(is_sorted_disjoint(&[
+            Range(dec("10"), dec("20")),
+            Range(dec("30"), dec("20")),
+        ]));
+        assert!(!is_sorted_disjoint(&[
+            Range(dec("40"), dec("50")),
+            Range(dec("10"), dec("20")),
+        ]));
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashSet;
+
+        let mut set = HashSet::new();
+        assert!(set.insert(Range(dec("1.10"), dec("2"))));
+        assert!(!set.insert(Range(dec("1.1000"), dec("2.0"))));
+        assert!(!set.insert(Range(dec("2.00"), dec("1.1"))));
+        assert!(set.insert(Range(dec("1.1"), dec("2.01"))));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        assert_eq!(merge_ranges(vec![]), vec![]);
+        assert_eq!(
+            merge_ranges(vec![Range(dec("20"), dec("10"))]),
+            vec![Range(dec("10"), dec("20"))]
+        );
+
+        let ranges = vec![
+            Range(dec("50"), dec("40")),
+            Range(dec("10"), dec("20")),
+            Range(dec("15"), dec("18")),
+            Range(dec("20"), dec("30")),
+            Range(dec("10"), dec("20")),
+            Range(dec("60"), dec("70")),
+        ];
+        assert_eq!(
+            merge_ranges(ranges),
+            vec![
+                Range(dec("10"), dec("30")),
+                Range(dec("40"), dec("50")),
+                Range(dec("60"), dec("70")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_range_struct() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            #[serde(with = "range_struct")]
+            range: Range<Decimal>,
+        }
+
+        let config = Config {
+            range: Range(dec("100"), dec("50")),
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(json, r#"{"range":{"min":"50","max":"100"}}"#);
+
+        let config: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(config.range, Range(dec("50"), dec("100")));
+
+        let config: Config = serde_json::from_str(r#"{"range":["100","50"]}"#).unwrap();
+        assert_eq!(config.range, Range(dec("50"), dec("100")));
+
+        // The plain impl reads the object form too
+        let range: Range<Decimal> = serde_json::from_str(r#"{"min":"100","max":"50"}"#).unwrap();
+        assert_eq!(range, Range(dec("50"), dec("100")));
+        assert_eq!(serde_json::to_string(&range).unwrap(), r#"["50","100"]"#);
+    }
+
+    #[test]
+    fn test_deserialize_normalizes() {
+        let range: Range<Decimal> = serde_json::from_str(r#"["205", "200"]"#).unwrap();
+        assert_eq!(range, Range(dec("200"), dec("205")));
+
+        let json = serde_json::to_string(&range).unwrap();
+        assert_eq!(json, r#"["200","205"]"#);
     }
 }
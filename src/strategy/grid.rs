@@ -1,53 +1,83 @@
 use serde::{Deserialize, Serialize};
 
-use crate::math::Range;
+use crate::math::{Bound, PriceRange, Range};
 use crate::types::{Decimal, Price, QuoteQuantity};
 
-use super::{Position, Strategy};
+use super::{check_contained, level_label, snap_to_tick, Position, Strategy, ValidateError};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Grid {
     pub investment: QuoteQuantity,
     pub range: Range<Price>,
     pub copies: usize,
+
+    #[serde(default)]
+    pub half_open: bool,
+
+    #[serde(default)]
+    pub tick_size: Option<Price>,
 }
 
 impl Grid {
-    pub fn new(investment: QuoteQuantity, range: Range<Price>, copies: usize) -> Self {
+    pub fn new(investment: QuoteQuantity, range: PriceRange, copies: usize) -> Self {
         Self {
             investment,
-            range,
+            range: range.into(),
             copies,
+            half_open: false,
+            tick_size: None,
         }
     }
+
+    pub fn validate(&self) -> Result<(), ValidateError> {
+        let positions = self.assign_position();
+        let ranges = positions.iter().enumerate().flat_map(|(index, position)| {
+            let ranges = position.buying_prices.iter();
+            ranges
+                .chain(position.selling_prices.iter())
+                .map(move |range| (index, range))
+        });
+
+        check_contained(&self.range, ranges)
+    }
 }
 
 impl Strategy for Grid {
     fn assign_position(&self) -> Vec<Position> {
         let mut result = Vec::with_capacity(self.copies);
-        let copies = Decimal::from(self.copies);
         let price_highest = self.range.max();
-        let price_lowest = self.range.min();
+        let levels = self.range.split_evenly(self.copies + 1, 6);
 
-        let interval = (price_highest - price_lowest) / (copies + Decimal::ONE);
-        let interval_quote_quantity = self.investment / copies;
-
-        let interval = interval.trunc_with_scale(6);
+        let interval_quote_quantity = self.investment / Decimal::from(self.copies);
         let interval_quote_quantity = interval_quote_quantity.trunc_with_scale(6);
 
-        for i in 0..self.copies {
-            let buying = price_lowest + interval * Decimal::from(i);
-            let selling = price_lowest + interval * Decimal::from(i + 2);
-            let position = Position {
-                buying_prices: vec![Range(buying, buying + (interval / Decimal::TWO))],
-                selling_prices: vec![Range(
-                    selling - (interval / Decimal::TWO),
-                    price_highest.clone(),
-                )],
+        for (i, level) in levels.iter().take(self.copies).enumerate() {
+            let half = level.span() / Decimal::TWO;
+            let selling = levels[i + 1].0 + half;
+            let buying = Range::new(level.0, level.0 + half);
+            let selling = Range::new(selling, *price_highest);
+            let mut position = Position {
+                buying_prices: vec![snap_to_tick(buying, self.tick_size)],
+                selling_prices: vec![snap_to_tick(selling, self.tick_size)],
                 base_quantity: Decimal::ZERO,
                 quote_quantity: interval_quote_quantity,
+                buying_upper_bound: match self.half_open {
+                    true => Bound::Exclusive,
+                    false => Bound::Inclusive,
+                },
+                ..Default::default()
             };
 
+            // Truncation can collapse a band onto a single price, which never trades sensibly
+            if position.has_degenerate_prices() {
+                continue;
+            }
+
+            debug_assert_eq!(position.validate(true), Ok(()));
+
+            position.id = Some(result.len() as u64);
+            position.label = Some(level_label(&position.buying_prices[0]));
+
             result.push(position)
         }
 
@@ -64,12 +94,18 @@ mod tests_grid {
         Decimal::from_str(value).unwrap()
     }
 
+    fn price_range(a: &str, b: &str) -> PriceRange {
+        PriceRange::new(dec(a), dec(b)).unwrap()
+    }
+
     #[test]
     fn test_trap() {
         let grid = Grid {
             investment: dec("30"),
             range: Range(dec("50"), dec("100")),
             copies: 1,
+            half_open: false,
+            tick_size: None,
         };
 
         assert_eq!(
@@ -78,7 +114,10 @@ mod tests_grid {
                 buying_prices: vec![Range(dec("50"), dec("62.5"))],
                 selling_prices: vec![Range(dec("87.5"), dec("100"))],
                 base_quantity: dec("0"),
-                quote_quantity: dec("30.0")
+                quote_quantity: dec("30.0"),
+                id: Some(0),
+                label: Some(String::from("50..62.5")),
+                ..Default::default()
             },]
         );
 
@@ -86,6 +125,8 @@ mod tests_grid {
             investment: dec("30"),
             range: Range(dec("50"), dec("100")),
             copies: 2,
+            half_open: false,
+            tick_size: None,
         };
 
         assert_eq!(
@@ -95,13 +136,19 @@ mod tests_grid {
                     buying_prices: vec![Range(dec("50"), dec("58.333333"))],
                     selling_prices: vec![Range(dec("74.999999"), dec("100"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("15.0")
+                    quote_quantity: dec("15.0"),
+                    id: Some(0),
+                    label: Some(String::from("50..58.333333")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("66.666666"), dec("74.999999"))],
                     selling_prices: vec![Range(dec("91.666665"), dec("100"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("15.0")
+                    quote_quantity: dec("15.0"),
+                    id: Some(1),
+                    label: Some(String::from("66.666666..74.999999")),
+                    ..Default::default()
                 },
             ]
         );
@@ -110,6 +157,8 @@ mod tests_grid {
             investment: dec("30"),
             range: Range(dec("50"), dec("100")),
             copies: 3,
+            half_open: false,
+            tick_size: None,
         };
 
         assert_eq!(
@@ -119,21 +168,94 @@ mod tests_grid {
                     buying_prices: vec![Range(dec("50"), dec("56.250000"))],
                     selling_prices: vec![Range(dec("68.750000"), dec("100"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("10.0")
+                    quote_quantity: dec("10.0"),
+                    id: Some(0),
+                    label: Some(String::from("50..56.25")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("62.500000"), dec("68.750000"))],
                     selling_prices: vec![Range(dec("81.250000"), dec("100"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("10.0")
+                    quote_quantity: dec("10.0"),
+                    id: Some(1),
+                    label: Some(String::from("62.5..68.75")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("75.000000"), dec("81.250000"))],
                     selling_prices: vec![Range(dec("93.750000"), dec("100"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("10.0")
+                    quote_quantity: dec("10.0"),
+                    id: Some(2),
+                    label: Some(String::from("75..81.25")),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_half_open() {
+        let mut grid = Grid::new(dec("30"), price_range("50", "100"), 1);
+        grid.half_open = true;
+
+        let positions = grid.assign_position();
+        assert_eq!(positions[0].buying_upper_bound, Bound::Exclusive);
+        assert!(positions[0].is_within_buying_price(&dec("50")));
+        assert!(!positions[0].is_within_buying_price(&dec("62.5")));
+    }
+
+    #[test]
+    fn test_narrow_range() {
+        // The interval truncates to zero at 6 decimals
+        let grid = Grid::new(dec("30"), price_range("50", "50.000001"), 2);
+        assert_eq!(grid.assign_position(), vec![]);
+
+        for copies in 1..5 {
+            let grid = Grid::new(dec("30"), price_range("50", "50.000005"), copies);
+            for position in grid.assign_position() {
+                assert!(!position.has_degenerate_prices());
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        for copies in 1..10 {
+            let grid = Grid::new(dec("30"), price_range("100", "50"), copies);
+            assert_eq!(grid.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_tick_size() {
+        let mut grid = Grid::new(dec("30"), price_range("50", "100"), 2);
+        grid.tick_size = Some(dec("0.5"));
+
+        assert_eq!(
+            grid.assign_position(),
+            vec![
+                Position {
+                    buying_prices: vec![Range(dec("50"), dec("58.0"))],
+                    selling_prices: vec![Range(dec("75.0"), dec("100"))],
+                    base_quantity: dec("0"),
+                    quote_quantity: dec("15.0"),
+                    id: Some(0),
+                    label: Some(String::from("50..58")),
+                    ..Default::default()
+                },
+                Position {
+                    buying_prices: vec![Range(dec("67.0"), dec("74.5"))],
+                    selling_prices: vec![Range(dec("92.0"), dec("100"))],
+                    base_quantity: dec("0"),
+                    quote_quantity: dec("15.0"),
+                    id: Some(1),
+                    label: Some(String::from("67..74.5")),
+                    ..Default::default()
                 },
             ]
         );
+        assert_eq!(grid.validate(), Ok(()));
     }
 }
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-use crate::math::Range;
+use crate::math::{self, Bound, MathError, PriceRange, Range, RoundingMode};
 use crate::types::{Decimal, Price, QuoteQuantity};
 
-use super::{Position, Strategy};
+use super::{check_contained, level_label, snap_to_tick, Position, Strategy, ValidateError};
+
+// Guards against tiny percents producing an unbounded number of levels
+const MAX_LEVELS: usize = 10000;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GridPercent {
@@ -11,41 +14,59 @@ pub struct GridPercent {
     pub range: Range<Price>,
     pub percent: Decimal,
     pub percent_lost: Decimal,
+
+    #[serde(default)]
+    pub half_open: bool,
+
+    #[serde(default)]
+    pub tick_size: Option<Price>,
 }
 
 impl GridPercent {
     pub fn new(
         investment: QuoteQuantity,
-        range: Range<Price>,
+        range: PriceRange,
         percent: Decimal,
         percent_lost: Decimal,
     ) -> Self {
         Self {
             investment,
-            range,
+            range: range.into(),
             percent,
             percent_lost,
+            half_open: false,
+            tick_size: None,
         }
     }
+
+    // The stop-loss price sits below the band on purpose and is not checked
+    pub fn validate(&self) -> Result<(), ValidateError> {
+        let positions = self.assign_position();
+        let ranges = positions.iter().enumerate().flat_map(|(index, position)| {
+            let ranges = position.buying_prices.iter();
+            ranges
+                .chain(position.selling_prices.iter())
+                .map(move |range| (index, range))
+        });
+
+        check_contained(&self.range, ranges)
+    }
 }
 
-impl Strategy for GridPercent {
-    fn assign_position(&self) -> Vec<Position> {
-        let initial_price = self.range.min().clone();
-        let termination_price = self.range.max().clone();
+impl GridPercent {
+    pub fn try_assign_position(&self) -> Result<Vec<Position>, MathError> {
+        let initial_price = *self.range.min();
+        let termination_price = *self.range.max();
         let percentage_increase = Decimal::ONE + self.percent;
         let percentage_lost = Decimal::ONE - self.percent_lost;
 
-        let mut prices = vec![initial_price];
-        loop {
-            let new_price = prices.last().unwrap() * percentage_increase;
-            let new_price = new_price.trunc_with_scale(12);
-            if new_price >= termination_price {
-                break;
-            }
-
-            prices.push(new_price);
-        }
+        let prices = math::compound_levels(
+            initial_price,
+            percentage_increase,
+            termination_price,
+            MAX_LEVELS,
+            12,
+        )?;
 
         let mut positions = Vec::with_capacity(prices.len());
         let mut num = 0;
@@ -54,45 +75,73 @@ impl Strategy for GridPercent {
 
             let buy_0 = match prices.get(index) {
                 Some(v) => *v,
-                None => return positions,
+                None => return Ok(positions),
             };
 
             let buy_1 = match prices.get(index + 1) {
                 Some(v) => *v,
-                None => return positions,
+                None => return Ok(positions),
             };
 
             let sell_0 = match prices.get(index + 2) {
                 Some(v) => *v,
-                None => return positions,
+                None => return Ok(positions),
             };
 
-            if let None = prices.get(index + 3) {
-                return positions;
+            if prices.get(index + 3).is_none() {
+                return Ok(positions);
             }
 
-            let selling_prices = {
-                if Decimal::ZERO < percentage_lost && percentage_lost < Decimal::ONE {
-                    vec![
-                        Range(sell_0, termination_price.clone()),
-                        Range(Decimal::ZERO, sell_0 * percentage_lost),
-                    ]
-                } else {
-                    vec![Range(sell_0, termination_price.clone())]
+            // The stop is floored onto the tick like the top of a selling range
+            let stop_loss = match Decimal::ZERO < percentage_lost && percentage_lost < Decimal::ONE
+            {
+                true => {
+                    let stop = sell_0 * percentage_lost;
+                    match self.tick_size {
+                        Some(tick) => math::round_to_tick(stop, tick, RoundingMode::Floor).ok(),
+                        None => Some(stop),
+                    }
                 }
+                false => None,
             };
 
-            positions.push(Position {
-                buying_prices: vec![Range(buy_0, buy_1)],
-                selling_prices: selling_prices,
+            let mut position = Position {
+                buying_prices: vec![snap_to_tick(Range::new(buy_0, buy_1), self.tick_size)],
+                selling_prices: vec![snap_to_tick(
+                    Range::new(sell_0, termination_price),
+                    self.tick_size,
+                )],
+                stop_loss,
                 base_quantity: Decimal::ZERO,
-                quote_quantity: self.investment.clone(),
-            });
+                quote_quantity: self.investment,
+                buying_upper_bound: match self.half_open {
+                    true => Bound::Exclusive,
+                    false => Bound::Inclusive,
+                },
+                ..Default::default()
+            };
 
             num += 3;
+            if position.has_degenerate_prices() {
+                continue;
+            }
+
+            debug_assert_eq!(position.validate(true), Ok(()));
+
+            position.id = Some(positions.len() as u64);
+            position.label = Some(level_label(&position.buying_prices[0]));
+
+            positions.push(position);
         }
 
-        positions
+        Ok(positions)
+    }
+}
+
+impl Strategy for GridPercent {
+    // Yields no positions when the levels cannot be generated, see `try_assign_position`
+    fn assign_position(&self) -> Vec<Position> {
+        self.try_assign_position().unwrap_or_default()
     }
 }
 
@@ -105,14 +154,13 @@ mod tests {
         Decimal::from_str(value).unwrap()
     }
 
+    fn price_range(a: &str, b: &str) -> PriceRange {
+        PriceRange::new(dec(a), dec(b)).unwrap()
+    }
+
     #[test]
     fn test_positions() {
-        let grid = GridPercent::new(
-            dec("100"),
-            Range(dec("50"), dec("60")),
-            dec("0.01"),
-            dec("0"),
-        );
+        let grid = GridPercent::new(dec("100"), price_range("50", "60"), dec("0.01"), dec("0"));
         let positions = grid.assign_position();
 
         assert_eq!(
@@ -122,35 +170,42 @@ mod tests {
                     buying_prices: vec![Range(dec("50"), dec("50.5"))],
                     selling_prices: vec![Range(dec("51.005"), dec("60"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(0),
+                    label: Some(String::from("50..50.5")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("52.0302005"), dec("52.550502505"))],
                     selling_prices: vec![Range(dec("53.07600753005"), dec("60"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(1),
+                    label: Some(String::from("52.0302005..52.550502505")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("54.142835281403"), dec("54.684263634217"))],
                     selling_prices: vec![Range(dec("55.231106270559"), dec("60"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(2),
+                    label: Some(String::from("54.142835281403..54.684263634217")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("56.341251506596"), dec("56.904664021661"))],
                     selling_prices: vec![Range(dec("57.473710661877"), dec("60"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(3),
+                    label: Some(String::from("56.341251506596..56.904664021661")),
+                    ..Default::default()
                 }
             ]
         );
 
-        let grid = GridPercent::new(
-            dec("100"),
-            Range(dec("100"), dec("200")),
-            dec("0.05"),
-            dec("0"),
-        );
+        let grid = GridPercent::new(dec("100"), price_range("100", "200"), dec("0.05"), dec("0"));
         let positions = grid.assign_position();
 
         assert_eq!(
@@ -160,19 +215,28 @@ mod tests {
                     buying_prices: vec![Range(dec("100"), dec("105"))],
                     selling_prices: vec![Range(dec("110.25"), dec("200"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(0),
+                    label: Some(String::from("100..105")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("121.550625"), dec("127.62815625"))],
                     selling_prices: vec![Range(dec("134.0095640625"), dec("200"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(1),
+                    label: Some(String::from("121.550625..127.62815625")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("147.745544378906"), dec("155.132821597851"))],
                     selling_prices: vec![Range(dec("162.889462677743"), dec("200"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(2),
+                    label: Some(String::from("147.745544378906..155.132821597851")),
+                    ..Default::default()
                 }
             ]
         );
@@ -182,7 +246,7 @@ mod tests {
     fn test_positions_stop_lost() {
         let grid = GridPercent::new(
             dec("100"),
-            Range(dec("100"), dec("200")),
+            price_range("100", "200"),
             dec("0.05"),
             dec("0.1"),
         );
@@ -193,32 +257,127 @@ mod tests {
             vec![
                 Position {
                     buying_prices: vec![Range(dec("100"), dec("105"))],
-                    selling_prices: vec![
-                        Range(dec("110.25"), dec("200")),
-                        Range(dec("0"), dec("99.225"))
-                    ],
+                    selling_prices: vec![Range(dec("110.25"), dec("200"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(0),
+                    label: Some(String::from("100..105")),
+                    stop_loss: Some(dec("99.225")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("121.550625"), dec("127.62815625"))],
-                    selling_prices: vec![
-                        Range(dec("134.0095640625"), dec("200")),
-                        Range(dec("0"), dec("120.60860765625"))
-                    ],
+                    selling_prices: vec![Range(dec("134.0095640625"), dec("200"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(1),
+                    label: Some(String::from("121.550625..127.62815625")),
+                    stop_loss: Some(dec("120.60860765625")),
+                    ..Default::default()
                 },
                 Position {
                     buying_prices: vec![Range(dec("147.745544378906"), dec("155.132821597851"))],
-                    selling_prices: vec![
-                        Range(dec("162.889462677743"), dec("200")),
-                        Range(dec("0"), dec("146.6005164099687"))
-                    ],
+                    selling_prices: vec![Range(dec("162.889462677743"), dec("200"))],
                     base_quantity: dec("0"),
-                    quote_quantity: dec("100")
+                    quote_quantity: dec("100"),
+                    id: Some(2),
+                    label: Some(String::from("147.745544378906..155.132821597851")),
+                    stop_loss: Some(dec("146.6005164099687")),
+                    ..Default::default()
                 }
             ]
         );
     }
+
+    #[test]
+    fn test_positions_half_open() {
+        let mut grid =
+            GridPercent::new(dec("100"), price_range("100", "200"), dec("0.05"), dec("0"));
+        grid.half_open = true;
+
+        let positions = grid.assign_position();
+        assert_eq!(positions.len(), 3);
+        for position in positions.iter() {
+            assert_eq!(position.buying_upper_bound, Bound::Exclusive);
+        }
+
+        assert!(positions[0].is_within_buying_price(&dec("100")));
+        assert!(!positions[0].is_within_buying_price(&dec("105")));
+    }
+
+    #[test]
+    fn test_validate() {
+        let grid = GridPercent::new(
+            dec("100"),
+            price_range("100", "200"),
+            dec("0.05"),
+            dec("0.1"),
+        );
+        assert_eq!(grid.validate(), Ok(()));
+
+        let grid = GridPercent::new(dec("100"), price_range("50", "60"), dec("0.01"), dec("0"));
+        assert_eq!(grid.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_positions_collapsed_percent() {
+        let grid = GridPercent::new(
+            dec("100"),
+            price_range("50", "60"),
+            dec("0.00000000000001"),
+            dec("0"),
+        );
+        assert_eq!(grid.assign_position(), vec![]);
+        assert_eq!(
+            grid.try_assign_position(),
+            Err(MathError::InvalidRatio(dec("1.00000000000001")))
+        );
+    }
+
+    #[test]
+    fn test_positions_zero_percent() {
+        let grid = GridPercent::new(dec("100"), price_range("50", "60"), dec("0"), dec("0"));
+        assert_eq!(
+            grid.try_assign_position(),
+            Err(MathError::InvalidRatio(dec("1")))
+        );
+        assert_eq!(grid.assign_position(), vec![]);
+
+        let grid = GridPercent::new(
+            dec("100"),
+            price_range("50", "60"),
+            dec("0.000001"),
+            dec("0"),
+        );
+        assert_eq!(
+            grid.try_assign_position(),
+            Err(MathError::TooManyLevels(MAX_LEVELS))
+        );
+    }
+
+    #[test]
+    fn test_positions_tick_size() {
+        let mut grid = GridPercent::new(
+            dec("100"),
+            price_range("100", "200"),
+            dec("0.05"),
+            dec("0.1"),
+        );
+        grid.tick_size = Some(dec("0.01"));
+
+        let positions = grid.assign_position();
+        assert_eq!(
+            positions[1],
+            Position {
+                buying_prices: vec![Range(dec("121.56"), dec("127.62"))],
+                selling_prices: vec![Range(dec("134.01"), dec("200"))],
+                base_quantity: dec("0"),
+                quote_quantity: dec("100"),
+                id: Some(1),
+                label: Some(String::from("121.56..127.62")),
+                stop_loss: Some(dec("120.60")),
+                ..Default::default()
+            }
+        );
+    }
 }
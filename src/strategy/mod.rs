@@ -1,7 +1,127 @@
 pub mod grid;
 pub mod grid_percent;
 
+use std::fmt;
+
+use crate::math::{self, Range, RoundingMode};
 use crate::trade::position::Position;
+use crate::types::Price;
+
 pub trait Strategy {
     fn assign_position(&self) -> Vec<Position>;
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidateError {
+    pub range: Range<Price>,
+    pub violations: Vec<(usize, Range<Price>)>, // Position index and the offending range
+}
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "price ranges outside {}:", self.range)?;
+
+        for (index, range) in self.violations.iter() {
+            write!(f, " position {} {}", index, range)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidateError {}
+
+// A position label from its buying band, printed without trailing zeros
+fn level_label(range: &Range<Price>) -> String {
+    format!("{}..{}", range.min().normalize(), range.max().normalize())
+}
+
+fn check_contained<'a>(
+    range: &Range<Price>,
+    ranges: impl Iterator<Item = (usize, &'a Range<Price>)>,
+) -> Result<(), ValidateError> {
+    let violations: Vec<(usize, Range<Price>)> = ranges
+        .filter(|(_, other)| !range.contains_range(other))
+        .map(|(index, other)| (index, other.clone()))
+        .collect();
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    Err(ValidateError {
+        range: range.clone(),
+        violations,
+    })
+}
+
+// Snaps a range inwards onto tick multiples. When no tick fits inside, the range
+// collapses onto a single price so the degenerate check drops its position.
+// An invalid tick size leaves the range untouched.
+fn snap_to_tick(range: Range<Price>, tick_size: Option<Price>) -> Range<Price> {
+    let Some(tick) = tick_size else {
+        return range;
+    };
+
+    let min = math::round_to_tick(*range.min(), tick, RoundingMode::Ceil);
+    let max = math::round_to_tick(*range.max(), tick, RoundingMode::Floor);
+    match (min, max) {
+        (Ok(min), Ok(max)) if min <= max => Range(min, max),
+        (Ok(min), Ok(_)) => Range(min, min),
+        _ => range,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Decimal;
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_check_contained() {
+        let range = Range(dec("50"), dec("100"));
+        let inside = Range(dec("50"), dec("60"));
+        let partial = Range(dec("90"), dec("110"));
+        let outside = Range(dec("0"), dec("10"));
+
+        assert_eq!(
+            check_contained(&range, vec![(0, &inside)].into_iter()),
+            Ok(())
+        );
+
+        let error = check_contained(
+            &range,
+            vec![(0, &inside), (1, &partial), (2, &outside)].into_iter(),
+        )
+        .unwrap_err();
+        assert_eq!(error.violations, vec![(1, partial), (2, outside)]);
+        assert_eq!(
+            error.to_string(),
+            "price ranges outside 50..100: position 1 90..110 position 2 0..10"
+        );
+    }
+
+    #[test]
+    fn test_snap_to_tick() {
+        let range = Range(dec("52.0302005"), dec("52.550502505"));
+        assert_eq!(snap_to_tick(range.clone(), None), range);
+        assert_eq!(
+            snap_to_tick(range.clone(), Some(dec("0.01"))),
+            Range(dec("52.04"), dec("52.55"))
+        );
+        assert_eq!(
+            snap_to_tick(range.clone(), Some(dec("0.5"))),
+            Range(dec("52.5"), dec("52.5"))
+        );
+        assert_eq!(
+            snap_to_tick(range.clone(), Some(dec("5"))),
+            Range(dec("55"), dec("55"))
+        );
+        assert_eq!(snap_to_tick(range.clone(), Some(dec("0"))), range);
+    }
+}
@@ -1,9 +1,106 @@
+use std::fmt;
 use std::time::Duration;
 
-pub fn timestamp() -> Duration {
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+// Milliseconds since the UNIX epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    pub fn as_millis(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for Timestamp {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+// Values past `u64::MAX` saturate, they are far beyond any real clock
+impl From<u128> for Timestamp {
+    fn from(value: u128) -> Self {
+        Self(u64::try_from(value).unwrap_or(u64::MAX))
+    }
+}
+
+impl From<Duration> for Timestamp {
+    fn from(value: Duration) -> Self {
+        value.as_millis().into()
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Accepts the numbers written by the former `u128` field as well as decimal strings
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+struct TimestampVisitor;
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a non-negative integer timestamp as a number or a string")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Timestamp, E> {
+        Ok(value.into())
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Timestamp, E> {
+        Ok(value.into())
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Timestamp, E> {
+        match u64::try_from(value) {
+            Ok(value) => Ok(value.into()),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Signed(value), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Timestamp, E> {
+        match value.parse::<u128>() {
+            Ok(value) => Ok(value.into()),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+pub fn timestamp() -> Timestamp {
     use std::time::SystemTime;
     let earlier = SystemTime::UNIX_EPOCH;
     let msg = "SystemTime before UNIX EPOCH!";
 
-    SystemTime::now().duration_since(earlier).expect(msg)
+    SystemTime::now().duration_since(earlier).expect(msg).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(Timestamp::from(1700000000000u128), Timestamp(1700000000000));
+        assert_eq!(Timestamp::from(u128::MAX), Timestamp(u64::MAX));
+        assert!(Timestamp(1) < Timestamp(2));
+        assert!(super::timestamp() > Timestamp(1700000000000));
+
+        let value: Timestamp = serde_json::from_str("1700000000000").unwrap();
+        assert_eq!(value.as_millis(), 1700000000000);
+        let value: Timestamp = serde_json::from_str("\"1700000000000\"").unwrap();
+        assert_eq!(value.as_millis(), 1700000000000);
+        assert_eq!(serde_json::to_string(&Timestamp(5)).unwrap(), "5");
+    }
 }
@@ -0,0 +1,223 @@
+// Binance spot REST payloads, decimals are parsed from their JSON strings and never as floats
+
+use serde::{Deserialize, Serialize};
+
+use crate::time;
+use crate::types::{BaseQuantity, Price, Quantity, QuoteQuantity, Timestamp};
+
+use super::{Trade, TradeSide};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceFill {
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Price,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub qty: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub commission: Quantity,
+    pub commission_asset: String,
+    #[serde(default)]
+    pub trade_id: Option<u64>,
+}
+
+// The FULL order response, only the fields needed to build trades
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceOrderResponse {
+    pub symbol: String,
+    pub order_id: u64,
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    pub transact_time: u64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub executed_qty: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub cummulative_quote_qty: QuoteQuantity,
+    pub status: String,
+    pub side: TradeSide,
+    #[serde(default)]
+    pub fills: Vec<BinanceFill>,
+}
+
+impl Trade {
+    // The amounts that actually changed hands. A commission in the base or quote asset of
+    // `symbol` is netted into that side, `costs` then implies it from the price.
+    // Only a commission in a third asset, such as BNB, is kept as `fee`.
+    // Stamped with the current time, a bare fill carries none.
+    pub fn from_binance_fill(side: TradeSide, symbol: &str, fill: &BinanceFill) -> Trade {
+        let mut base_quantity = fill.qty;
+        let mut quote_quantity = fill.price * fill.qty;
+        let (mut fee, mut fee_asset) = (None, None);
+
+        let asset = fill.commission_asset.as_str();
+        let commission = fill.commission;
+        match side {
+            _ if commission.is_zero() => {}
+            TradeSide::Buy if symbol.ends_with(asset) => quote_quantity += commission,
+            TradeSide::Sell if symbol.ends_with(asset) => quote_quantity -= commission,
+            TradeSide::Buy if symbol.starts_with(asset) => base_quantity -= commission,
+            TradeSide::Sell if symbol.starts_with(asset) => base_quantity += commission,
+            _ => {
+                fee = Some(commission);
+                fee_asset = Some(fill.commission_asset.clone());
+            }
+        }
+
+        let mut trade = Trade::new(
+            side,
+            fill.price,
+            base_quantity,
+            quote_quantity,
+            time::timestamp(),
+        );
+        trade.fee = fee;
+        trade.fee_asset = fee_asset;
+        trade.symbol = Some(symbol.to_string());
+        trade.id = fill.trade_id.map(|id| id.to_string());
+        trade
+    }
+
+    // One trade per fill
+    pub fn from_binance_order(order: &BinanceOrderResponse) -> Vec<Trade> {
+        order
+            .fills
+            .iter()
+            .map(|fill| {
+                let mut trade = Trade::from_binance_fill(order.side, &order.symbol, fill);
+                trade.timestamp = Timestamp(order.transact_time);
+                trade.order_id = Some(order.order_id.to_string());
+                trade
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trade::{Trade, TradeSide};
+    use crate::types::{Decimal, Timestamp};
+
+    use super::{BinanceFill, BinanceOrderResponse};
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    const BUY_ORDER: &str = r#"{
+        "symbol": "BTCUSDT",
+        "orderId": 28,
+        "orderListId": -1,
+        "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP",
+        "transactTime": 1507725176595,
+        "price": "0.00000000",
+        "origQty": "0.00200000",
+        "executedQty": "0.00200000",
+        "cummulativeQuoteQty": "135.99180000",
+        "status": "FILLED",
+        "timeInForce": "GTC",
+        "type": "MARKET",
+        "side": "BUY",
+        "fills": [
+            {
+                "price": "67995.90000000",
+                "qty": "0.00150000",
+                "commission": "0.00000150",
+                "commissionAsset": "BTC",
+                "tradeId": 56
+            },
+            {
+                "price": "67995.90000000",
+                "qty": "0.00050000",
+                "commission": "0.00000050",
+                "commissionAsset": "BTC",
+                "tradeId": 57
+            }
+        ]
+    }"#;
+
+    const SELL_ORDER: &str = r#"{
+        "symbol": "ETHUSDT",
+        "orderId": 3415,
+        "orderListId": -1,
+        "clientOrderId": "web_3a6f1e",
+        "transactTime": 1716000000000,
+        "price": "0.00000000",
+        "origQty": "0.10000000",
+        "executedQty": "0.10000000",
+        "cummulativeQuoteQty": "301.23000000",
+        "status": "FILLED",
+        "timeInForce": "GTC",
+        "type": "MARKET",
+        "side": "SELL",
+        "fills": [
+            {
+                "price": "3012.30000000",
+                "qty": "0.10000000",
+                "commission": "0.30123000",
+                "commissionAsset": "USDT",
+                "tradeId": 901
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_from_binance_order() {
+        let order: BinanceOrderResponse = serde_json::from_str(BUY_ORDER).unwrap();
+        assert_eq!(order.executed_qty, dec("0.002"));
+
+        let trades = Trade::from_binance_order(&order);
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].side, TradeSide::Buy);
+        assert_eq!(trades[0].price, dec("67995.9"));
+        assert_eq!(trades[0].base_quantity, dec("0.0014985"));
+        assert_eq!(trades[0].quote_quantity, dec("101.99385"));
+        assert_eq!(trades[0].timestamp, Timestamp(1507725176595));
+        assert_eq!(trades[0].fee, None);
+        assert_eq!(trades[0].fee_asset, None);
+        assert_eq!(trades[0].costs(), dec("0.10199385"));
+        assert_eq!(trades[0].symbol.as_deref(), Some("BTCUSDT"));
+        assert_eq!(trades[0].order_id.as_deref(), Some("28"));
+        assert_eq!(trades[1].id.as_deref(), Some("57"));
+
+        let base: Decimal = trades.iter().map(|trade| trade.base_quantity).sum();
+        assert_eq!(base, dec("0.001998"));
+        let quote: Decimal = trades.iter().map(|trade| trade.quote_quantity).sum();
+        assert_eq!(quote, order.cummulative_quote_qty);
+
+        let order: BinanceOrderResponse = serde_json::from_str(SELL_ORDER).unwrap();
+        let trades = Trade::from_binance_order(&order);
+        assert_eq!(trades[0].side, TradeSide::Sell);
+        assert_eq!(trades[0].base_quantity, dec("0.1"));
+        assert_eq!(trades[0].quote_quantity, dec("300.92877"));
+        assert_eq!(trades[0].fee, None);
+        assert_eq!(trades[0].costs(), dec("0.30123"));
+    }
+
+    #[test]
+    fn test_from_binance_fill() {
+        let json = r#"{"price":"0.00001234","qty":"1000.00000000","commission":"0.00075000","commissionAsset":"BNB"}"#;
+        let fill: BinanceFill = serde_json::from_str(json).unwrap();
+
+        let trade = Trade::from_binance_fill(TradeSide::Sell, "SHIBUSDT", &fill);
+        assert_eq!(trade.base_quantity, dec("1000"));
+        assert_eq!(trade.quote_quantity, dec("0.01234"));
+        assert_eq!(trade.fee, Some(dec("0.00075")));
+        assert_eq!(trade.fee_asset.as_deref(), Some("BNB"));
+        assert_eq!(trade.symbol.as_deref(), Some("SHIBUSDT"));
+        assert_eq!(trade.id, None);
+
+        // Selling with a base commission gives up more than was filled
+        let trade = Trade::from_binance_fill(TradeSide::Sell, "BNBUSDT", &fill);
+        assert_eq!(trade.base_quantity, dec("1000.00075"));
+        assert_eq!(trade.fee, None);
+
+        let trade = Trade::from_binance_fill(TradeSide::Buy, "ETHBNB", &fill);
+        assert_eq!(trade.quote_quantity, dec("0.01309"));
+        assert_eq!(trade.costs(), dec("0.00075"));
+
+        let json = r#"{"price":0.1,"qty":"1","commission":"0","commissionAsset":"BNB"}"#;
+        assert!(serde_json::from_str::<BinanceFill>(json).is_err());
+    }
+}
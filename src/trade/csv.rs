@@ -0,0 +1,462 @@
+// Flat CSV form of trades for spreadsheets, optional fields are written as empty cells
+
+use std::fmt;
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{BaseQuantity, Decimal, Price, Quantity, QuoteQuantity, Timestamp};
+
+use super::evaluate::Evaluate;
+use super::{Trade, TradeKind, TradeSide};
+
+// Decimals go through strings, csv would otherwise load them as floats. The last three
+// columns default to empty, so files written before them still load.
+#[derive(Serialize, Deserialize)]
+struct TradeRow {
+    side: TradeSide,
+    #[serde(with = "rust_decimal::serde::str")]
+    price: Price,
+    #[serde(with = "rust_decimal::serde::str")]
+    base_quantity: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    quote_quantity: QuoteQuantity,
+    timestamp: Timestamp,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    fee: Option<Quantity>,
+    fee_asset: Option<String>,
+    symbol: Option<String>,
+    id: Option<String>,
+    order_id: Option<String>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    requested_price: Option<Price>,
+    #[serde(default)]
+    position_id: Option<u64>,
+    #[serde(default)]
+    kind: Option<TradeKind>,
+}
+
+impl From<&Trade> for TradeRow {
+    fn from(trade: &Trade) -> Self {
+        Self {
+            side: trade.side,
+            price: trade.price,
+            base_quantity: trade.base_quantity,
+            quote_quantity: trade.quote_quantity,
+            timestamp: trade.timestamp,
+            fee: trade.fee,
+            fee_asset: trade.fee_asset.clone(),
+            symbol: trade.symbol.clone(),
+            id: trade.id.clone(),
+            order_id: trade.order_id.clone(),
+            requested_price: trade.requested_price,
+            position_id: trade.position_id,
+            kind: trade.kind,
+        }
+    }
+}
+
+impl From<TradeRow> for Trade {
+    fn from(row: TradeRow) -> Self {
+        let mut trade = Trade::new(
+            row.side,
+            row.price,
+            row.base_quantity,
+            row.quote_quantity,
+            row.timestamp,
+        );
+        trade.fee = row.fee;
+        trade.fee_asset = row.fee_asset;
+        trade.symbol = row.symbol;
+        trade.id = row.id;
+        trade.order_id = row.order_id;
+        trade.requested_price = row.requested_price;
+        trade.position_id = row.position_id;
+        trade.kind = row.kind;
+        trade
+    }
+}
+
+pub fn write_trades<W: Write>(writer: W, trades: &[Trade]) -> Result<(), CsvError> {
+    let mut writer = ::csv::Writer::from_writer(writer);
+    for trade in trades.iter() {
+        writer.serialize(TradeRow::from(trade))?;
+    }
+
+    writer.flush().map_err(|e| CsvError(e.into()))
+}
+
+pub fn read_trades<R: Read>(reader: R) -> Result<Vec<Trade>, CsvError> {
+    let mut reader = ::csv::Reader::from_reader(reader);
+    let mut trades = Vec::new();
+    for row in reader.deserialize::<TradeRow>() {
+        trades.push(row?.into());
+    }
+
+    Ok(trades)
+}
+
+// One flat line per report in a stable column order, `other_fees` has no column
+#[derive(Serialize, Deserialize)]
+struct EvaluateRow {
+    #[serde(with = "rust_decimal::serde::str")]
+    volume_base_quantity: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    volume_quote_quantity: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    leave_base_quantity: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    leave_quote_quantity: QuoteQuantity,
+    buy_count: usize,
+    sell_count: usize,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    max_price: Option<Price>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    min_price: Option<Price>,
+    #[serde(with = "rust_decimal::serde::str")]
+    costs: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    buy_base_volume: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    buy_quote_volume: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    sell_base_volume: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    sell_quote_volume: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    max_single_quote: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    max_single_base: BaseQuantity,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    avg_quote_per_trade: Option<QuoteQuantity>,
+    #[serde(with = "rust_decimal::serde::str")]
+    rebates: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    avg_buy_price: Option<Price>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    avg_sell_price: Option<Price>,
+    #[serde(with = "rust_decimal::serde::str")]
+    slippage: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    avg_slippage: Option<QuoteQuantity>,
+    inconsistent_count: usize,
+    #[serde(with = "rust_decimal::serde::str")]
+    fees: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    max_drawdown: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    max_drawdown_pct: Decimal,
+    round_trips: usize,
+    winning_round_trips: usize,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    win_rate: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str")]
+    gross_profit: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str")]
+    gross_loss: QuoteQuantity,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    profit_factor: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::str_option")]
+    break_even_price: Option<Price>,
+    avg_holding_millis: Option<u64>,
+    max_holding_millis: u64,
+    time_in_market_millis: u64,
+    max_gap_millis: Option<u64>,
+    first_timestamp: Option<Timestamp>,
+    last_timestamp: Option<Timestamp>,
+}
+
+impl From<&Evaluate> for EvaluateRow {
+    fn from(report: &Evaluate) -> Self {
+        Self {
+            volume_base_quantity: report.volume_base_quantity,
+            volume_quote_quantity: report.volume_quote_quantity,
+            leave_base_quantity: report.leave_base_quantity,
+            leave_quote_quantity: report.leave_quote_quantity,
+            buy_count: report.buy_count,
+            sell_count: report.sell_count,
+            max_price: report.max_price,
+            min_price: report.min_price,
+            costs: report.costs,
+            buy_base_volume: report.buy_base_volume,
+            buy_quote_volume: report.buy_quote_volume,
+            sell_base_volume: report.sell_base_volume,
+            sell_quote_volume: report.sell_quote_volume,
+            max_single_quote: report.max_single_quote,
+            max_single_base: report.max_single_base,
+            avg_quote_per_trade: report.avg_quote_per_trade,
+            rebates: report.rebates,
+            avg_buy_price: report.avg_buy_price,
+            avg_sell_price: report.avg_sell_price,
+            slippage: report.slippage,
+            avg_slippage: report.avg_slippage,
+            inconsistent_count: report.inconsistent_count,
+            fees: report.fees,
+            max_drawdown: report.max_drawdown,
+            max_drawdown_pct: report.max_drawdown_pct,
+            round_trips: report.round_trips,
+            winning_round_trips: report.winning_round_trips,
+            win_rate: report.win_rate,
+            gross_profit: report.gross_profit,
+            gross_loss: report.gross_loss,
+            profit_factor: report.profit_factor,
+            break_even_price: report.break_even_price,
+            avg_holding_millis: report.avg_holding_millis,
+            max_holding_millis: report.max_holding_millis,
+            time_in_market_millis: report.time_in_market_millis,
+            max_gap_millis: report.max_gap_millis,
+            first_timestamp: report.first_timestamp,
+            last_timestamp: report.last_timestamp,
+        }
+    }
+}
+
+impl From<EvaluateRow> for Evaluate {
+    fn from(row: EvaluateRow) -> Self {
+        Self {
+            volume_base_quantity: row.volume_base_quantity,
+            volume_quote_quantity: row.volume_quote_quantity,
+            leave_base_quantity: row.leave_base_quantity,
+            leave_quote_quantity: row.leave_quote_quantity,
+            buy_count: row.buy_count,
+            sell_count: row.sell_count,
+            max_price: row.max_price,
+            min_price: row.min_price,
+            costs: row.costs,
+            buy_base_volume: row.buy_base_volume,
+            buy_quote_volume: row.buy_quote_volume,
+            sell_base_volume: row.sell_base_volume,
+            sell_quote_volume: row.sell_quote_volume,
+            max_single_quote: row.max_single_quote,
+            max_single_base: row.max_single_base,
+            avg_quote_per_trade: row.avg_quote_per_trade,
+            rebates: row.rebates,
+            avg_buy_price: row.avg_buy_price,
+            avg_sell_price: row.avg_sell_price,
+            slippage: row.slippage,
+            avg_slippage: row.avg_slippage,
+            inconsistent_count: row.inconsistent_count,
+            fees: row.fees,
+            max_drawdown: row.max_drawdown,
+            max_drawdown_pct: row.max_drawdown_pct,
+            round_trips: row.round_trips,
+            winning_round_trips: row.winning_round_trips,
+            win_rate: row.win_rate,
+            gross_profit: row.gross_profit,
+            gross_loss: row.gross_loss,
+            profit_factor: row.profit_factor,
+            break_even_price: row.break_even_price,
+            avg_holding_millis: row.avg_holding_millis,
+            max_holding_millis: row.max_holding_millis,
+            time_in_market_millis: row.time_in_market_millis,
+            max_gap_millis: row.max_gap_millis,
+            first_timestamp: row.first_timestamp,
+            last_timestamp: row.last_timestamp,
+            running: None,
+            ..Default::default()
+        }
+    }
+}
+
+impl Evaluate {
+    pub fn csv_header() -> String {
+        Self::default().csv_lines().0
+    }
+
+    pub fn to_csv_row(&self) -> String {
+        self.csv_lines().1
+    }
+
+    // The header and the row of this report, without line endings
+    fn csv_lines(&self) -> (String, String) {
+        let mut writer = ::csv::Writer::from_writer(Vec::new());
+        writer
+            .serialize(EvaluateRow::from(self))
+            .expect("a flat row of plain values");
+        let bytes = writer.into_inner().expect("writing to memory");
+        let text = String::from_utf8(bytes).expect("csv of utf-8 values");
+
+        let mut lines = text.lines().map(String::from);
+        let header = lines.next().unwrap_or_default();
+        let row = lines.next().unwrap_or_default();
+        (header, row)
+    }
+}
+
+pub fn write_reports<W: Write>(writer: W, reports: &[Evaluate]) -> Result<(), CsvError> {
+    let mut writer = ::csv::Writer::from_writer(writer);
+    for report in reports.iter() {
+        writer.serialize(EvaluateRow::from(report))?;
+    }
+
+    writer.flush().map_err(|e| CsvError(e.into()))
+}
+
+pub fn read_reports<R: Read>(reader: R) -> Result<Vec<Evaluate>, CsvError> {
+    let mut reader = ::csv::Reader::from_reader(reader);
+    let mut reports = Vec::new();
+    for row in reader.deserialize::<EvaluateRow>() {
+        reports.push(row?.into());
+    }
+
+    Ok(reports)
+}
+
+#[derive(Debug)]
+pub struct CsvError(pub ::csv::Error);
+
+impl From<::csv::Error> for CsvError {
+    fn from(value: ::csv::Error) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "trade csv: {}", self.0)
+    }
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trade::{Trade, TradeKind, TradeSide};
+    use crate::types::{Decimal, Timestamp};
+
+    use crate::trade::evaluate::Evaluate;
+
+    use super::{read_reports, read_trades, write_reports, write_trades};
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("0.00000001"),
+                dec("100000000"),
+                dec("1.0"),
+                Timestamp(1700000000000),
+            ),
+            Trade::builder()
+                .side(TradeSide::Sell)
+                .price(dec("200"))
+                .base_quantity(dec("0.4"))
+                .quote_quantity(dec("79.92"))
+                .timestamp(Timestamp(1700000000001))
+                .fee(dec("0.0001"), Some("BNB".into()))
+                .symbol("BTCUSDT")
+                .id("7")
+                .order_id("8")
+                .requested_price(dec("199.5"))
+                .position_id(3)
+                .kind(TradeKind::TakeProfit)
+                .build(),
+        ];
+
+        let mut buffer = Vec::new();
+        write_trades(&mut buffer, &trades).unwrap();
+
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert_eq!(
+            text,
+            "side,price,base_quantity,quote_quantity,timestamp,fee,fee_asset,symbol,id,order_id,\
+             requested_price,position_id,kind\n\
+             BUY,0.00000001,100000000,1.0,1700000000000,,,,,,,,\n\
+             SELL,200,0.4,79.92,1700000000001,0.0001,BNB,BTCUSDT,7,8,199.5,3,TAKE_PROFIT\n"
+        );
+
+        let loaded = read_trades(buffer.as_slice()).unwrap();
+        assert_eq!(loaded.len(), trades.len());
+        for (loaded, trade) in loaded.iter().zip(trades.iter()) {
+            assert!(
+                loaded.eq_with_timestamp(trade),
+                "{:?} != {:?}",
+                loaded,
+                trade
+            );
+        }
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&trades).unwrap()
+        );
+
+        // Files from before the last three columns
+        let text =
+            "side,price,base_quantity,quote_quantity,timestamp,fee,fee_asset,symbol,id,order_id\n\
+                    SELL,200,0.4,79.92,1700000000001,,,,,\n";
+        let loaded = read_trades(text.as_bytes()).unwrap();
+        assert_eq!(loaded[0].requested_price, None);
+        assert_eq!(loaded[0].position_id, None);
+        assert_eq!(loaded[0].kind, None);
+    }
+
+    #[test]
+    fn test_read_error() {
+        let text =
+            "side,price,base_quantity,quote_quantity,timestamp,fee,fee_asset,symbol,id,order_id\n\
+                    HOLD,1,1,1,1,,,,,\n";
+        assert!(read_trades(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_reports_round_trip() {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("50"),
+                dec("0.3996"),
+                dec("20"),
+                Timestamp(1),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("200"),
+                dec("0.3996"),
+                dec("79.84008"),
+                Timestamp(9),
+            ),
+        ];
+        let reports = vec![Evaluate::from_trades(&trades), Evaluate::default()];
+
+        let header = Evaluate::csv_header();
+        assert!(header.starts_with("volume_base_quantity,volume_quote_quantity,"));
+        assert!(header.ends_with(",first_timestamp,last_timestamp"));
+
+        let row = reports[0].to_csv_row();
+        assert!(row.starts_with(
+            "0.7992,99.84008,0.0000,59.84008,1,1,200,50,0.09992,0.3996,20,0.3996,79.84008,79.84008,0.3996,49.92004,0,"
+        ));
+        assert!(row.ends_with(",8,8,8,8,1,9"));
+
+        // The empty report leaves its missing prices and timestamps blank
+        let row = reports[1].to_csv_row();
+        assert!(row.starts_with("0,0,0,0,0,0,,,0,0,0,0,0,0,0,,0,"));
+        assert!(row.ends_with(",0,0,,,"));
+
+        let mut buffer = Vec::new();
+        write_reports(&mut buffer, &reports).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "{}\n{}\n{}\n",
+                header,
+                reports[0].to_csv_row(),
+                reports[1].to_csv_row()
+            )
+        );
+
+        let loaded = read_reports(buffer.as_slice()).unwrap();
+        assert_eq!(loaded, reports);
+    }
+}
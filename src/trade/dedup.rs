@@ -0,0 +1,123 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::types::{BaseQuantity, Price, QuoteQuantity, Timestamp};
+
+use super::{Trade, TradeSide};
+
+// What makes two fills the same: the exchange fill id within its order when present,
+// otherwise every field of the fill itself
+#[derive(PartialEq, Eq, Hash)]
+enum FillKey<'a> {
+    Id(Option<&'a str>, &'a str),
+    Fill(TradeSide, Price, BaseQuantity, QuoteQuantity, Timestamp),
+}
+
+impl<'a> From<&'a Trade> for FillKey<'a> {
+    fn from(trade: &'a Trade) -> Self {
+        match &trade.id {
+            Some(id) => Self::Id(trade.order_id.as_deref(), id),
+            None => Self::Fill(
+                trade.side,
+                trade.price,
+                trade.base_quantity,
+                trade.quote_quantity,
+                trade.timestamp,
+            ),
+        }
+    }
+}
+
+// Pairs of (first occurrence, later duplicate) indices, ordered by the duplicate
+pub fn find_duplicates(trades: &[Trade]) -> Vec<(usize, usize)> {
+    let mut seen: HashMap<FillKey, usize> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (index, trade) in trades.iter().enumerate() {
+        match seen.get(&FillKey::from(trade)) {
+            Some(first) => duplicates.push((*first, index)),
+            None => {
+                seen.insert(FillKey::from(trade), index);
+            }
+        }
+    }
+
+    duplicates
+}
+
+// Keeps the first occurrence of every fill, returns how many trades were removed
+pub fn dedup(trades: &mut Vec<Trade>) -> usize {
+    let duplicates: HashSet<usize> = find_duplicates(trades)
+        .into_iter()
+        .map(|(_, duplicate)| duplicate)
+        .collect();
+
+    let mut index = 0;
+    trades.retain(|_| {
+        let keep = !duplicates.contains(&index);
+        index += 1;
+        keep
+    });
+
+    duplicates.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::types::Decimal;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn fill(id: Option<&str>, price: &str, ts: u64) -> Trade {
+        let mut builder = Trade::builder()
+            .side(TradeSide::Buy)
+            .price(dec(price))
+            .base_quantity(dec("0.3996"))
+            .quote_quantity(dec("20"))
+            .timestamp(Timestamp(ts))
+            .order_id("42");
+        if let Some(id) = id {
+            builder = builder.id(id);
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_dedup_by_id() {
+        let mut trades = vec![
+            fill(Some("1"), "50", 1),
+            fill(Some("2"), "50", 1),
+            // Replayed with a later local timestamp, still the same fill
+            fill(Some("1"), "50", 7),
+            fill(Some("2"), "50", 8),
+        ];
+
+        assert_eq!(find_duplicates(&trades), vec![(0, 2), (1, 3)]);
+        assert_eq!(dedup(&mut trades), 2);
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].id.as_deref(), Some("1"));
+        assert_eq!(trades[1].id.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_dedup_by_fields() {
+        let mut trades = vec![
+            fill(None, "50", 1),
+            fill(None, "50", 2),
+            fill(None, "51", 1),
+            fill(None, "50", 1),
+            fill(None, "50.0", 1),
+        ];
+
+        assert_eq!(find_duplicates(&trades), vec![(0, 3), (0, 4)]);
+        assert_eq!(dedup(&mut trades), 2);
+        assert_eq!(trades.len(), 3);
+        assert_eq!(trades[1].timestamp, Timestamp(2));
+        assert_eq!(trades[2].price, dec("51"));
+
+        assert_eq!(dedup(&mut trades), 0);
+    }
+}
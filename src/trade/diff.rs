@@ -0,0 +1,293 @@
+use std::fmt;
+
+use crate::types::Decimal;
+
+use super::evaluate::{labeled, Evaluate};
+use super::table::{display_decimal, DISPLAY_SCALE};
+
+// How one field of a report moved from a baseline to another report. Plain fields are
+// always present, only optional ones can be added or removed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Unchanged(Option<Decimal>),
+    Added(Decimal),
+    Removed(Decimal),
+    Changed {
+        before: Decimal,
+        after: Decimal,
+        delta: Decimal,
+        // The delta as a fraction of the baseline, `None` from a zero baseline
+        relative: Option<Decimal>,
+    },
+}
+
+impl Change {
+    fn new(before: Option<Decimal>, after: Option<Decimal>) -> Self {
+        match (before, after) {
+            (None, None) => Change::Unchanged(None),
+            (None, Some(after)) => Change::Added(after),
+            (Some(before), None) => Change::Removed(before),
+            (Some(before), Some(after)) if before == after => Change::Unchanged(Some(after)),
+            (Some(before), Some(after)) => {
+                let delta = after - before;
+                let relative = match before.is_zero() {
+                    true => None,
+                    false => Some(delta / before.abs()),
+                };
+
+                Change::Changed {
+                    before,
+                    after,
+                    delta,
+                    relative,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimal = |value: Decimal| display_decimal(value, DISPLAY_SCALE);
+        let signed = |value: String| match value.starts_with('-') {
+            true => value,
+            false => format!("+{}", value),
+        };
+
+        match self {
+            Change::Unchanged(None) => write!(f, "-"),
+            Change::Unchanged(Some(value)) => write!(f, "{}", decimal(*value)),
+            Change::Added(after) => write!(f, "- → {} (added)", decimal(*after)),
+            Change::Removed(before) => write!(f, "{} → - (removed)", decimal(*before)),
+            Change::Changed {
+                before,
+                after,
+                delta,
+                relative,
+            } => {
+                write!(
+                    f,
+                    "{} → {} ({}",
+                    decimal(*before),
+                    decimal(*after),
+                    signed(decimal(*delta))
+                )?;
+                if let Some(relative) = relative {
+                    let percent = display_decimal(relative * Decimal::ONE_HUNDRED, 2);
+                    write!(f, ", {}%", signed(percent))?;
+                }
+
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+// Every numeric field of two reports side by side, in field order. The per-asset
+// `other_fees` are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluateDiff {
+    pub changes: Vec<(&'static str, Change)>,
+}
+
+impl EvaluateDiff {
+    pub fn get(&self, field: &str) -> Option<&Change> {
+        self.changes
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, change)| change)
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|(_, change)| matches!(change, Change::Unchanged(_)))
+    }
+}
+
+// One aligned `field  before → after (delta, percent)` line per field that moved
+impl fmt::Display for EvaluateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<(&str, String)> = self
+            .changes
+            .iter()
+            .filter(|(_, change)| !matches!(change, Change::Unchanged(_)))
+            .map(|(name, change)| (*name, change.to_string()))
+            .collect();
+
+        write!(f, "{}", labeled(&lines))
+    }
+}
+
+impl Evaluate {
+    // The changes from this report, the baseline, to `other`
+    pub fn diff(&self, other: &Evaluate) -> EvaluateDiff {
+        let changes = self
+            .numbers()
+            .into_iter()
+            .zip(other.numbers())
+            .map(|((name, before), (_, after))| (name, Change::new(before, after)))
+            .collect();
+
+        EvaluateDiff { changes }
+    }
+
+    fn numbers(&self) -> Vec<(&'static str, Option<Decimal>)> {
+        let count = |value: usize| Some(Decimal::from(value));
+        let millis = |value: u64| Some(Decimal::from(value));
+
+        vec![
+            ("volume_base_quantity", Some(self.volume_base_quantity)),
+            ("volume_quote_quantity", Some(self.volume_quote_quantity)),
+            ("leave_base_quantity", Some(self.leave_base_quantity)),
+            ("leave_quote_quantity", Some(self.leave_quote_quantity)),
+            ("buy_count", count(self.buy_count)),
+            ("sell_count", count(self.sell_count)),
+            ("max_price", self.max_price),
+            ("min_price", self.min_price),
+            ("costs", Some(self.costs)),
+            ("buy_base_volume", Some(self.buy_base_volume)),
+            ("buy_quote_volume", Some(self.buy_quote_volume)),
+            ("sell_base_volume", Some(self.sell_base_volume)),
+            ("sell_quote_volume", Some(self.sell_quote_volume)),
+            ("max_single_quote", Some(self.max_single_quote)),
+            ("max_single_base", Some(self.max_single_base)),
+            ("avg_quote_per_trade", self.avg_quote_per_trade),
+            ("rebates", Some(self.rebates)),
+            ("avg_buy_price", self.avg_buy_price),
+            ("avg_sell_price", self.avg_sell_price),
+            ("slippage", Some(self.slippage)),
+            ("avg_slippage", self.avg_slippage),
+            ("inconsistent_count", count(self.inconsistent_count)),
+            ("fees", Some(self.fees)),
+            ("max_drawdown", Some(self.max_drawdown)),
+            ("max_drawdown_pct", Some(self.max_drawdown_pct)),
+            ("round_trips", count(self.round_trips)),
+            ("winning_round_trips", count(self.winning_round_trips)),
+            ("win_rate", self.win_rate),
+            ("gross_profit", Some(self.gross_profit)),
+            ("gross_loss", Some(self.gross_loss)),
+            ("profit_factor", self.profit_factor),
+            ("break_even_price", self.break_even_price),
+            (
+                "avg_holding_millis",
+                self.avg_holding_millis.and_then(millis),
+            ),
+            ("max_holding_millis", millis(self.max_holding_millis)),
+            ("time_in_market_millis", millis(self.time_in_market_millis)),
+            ("max_gap_millis", self.max_gap_millis.and_then(millis)),
+            (
+                "first_timestamp",
+                self.first_timestamp.and_then(|ts| millis(ts.as_millis())),
+            ),
+            (
+                "last_timestamp",
+                self.last_timestamp.and_then(|ts| millis(ts.as_millis())),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::trade::{Trade, TradeSide};
+    use crate::types::Timestamp;
+
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    // The trades of one position run once without commission and once at 0.1%
+    fn run(base: &str, sell_quote: &str) -> Evaluate {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("50"),
+                dec(base),
+                dec("20"),
+                Timestamp(0),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("200"),
+                dec(base),
+                dec(sell_quote),
+                Timestamp(60_000),
+            ),
+        ];
+
+        Evaluate::from_trades(&trades)
+    }
+
+    #[test]
+    fn test_diff() {
+        let free = run("0.4", "80");
+        let charged = run("0.3996", "79.84008");
+        let diff = free.diff(&charged);
+
+        assert_eq!(
+            diff.get("costs"),
+            Some(&Change::Changed {
+                before: dec("0"),
+                after: dec("0.09992"),
+                delta: dec("0.09992"),
+                relative: None,
+            })
+        );
+        assert_eq!(
+            diff.get("buy_count"),
+            Some(&Change::Unchanged(Some(dec("1"))))
+        );
+        assert_eq!(diff.get("other_fees"), None);
+
+        let expected = [
+            "volume_base_quantity   0.8 → 0.7992 (-0.0008, -0.1%)",
+            "volume_quote_quantity  100 → 99.84008 (-0.15992, -0.15%)",
+            "leave_quote_quantity   60 → 59.84008 (-0.15992, -0.26%)",
+            "costs                  0 → 0.09992 (+0.09992)",
+            "buy_base_volume        0.4 → 0.3996 (-0.0004, -0.1%)",
+            "sell_base_volume       0.4 → 0.3996 (-0.0004, -0.1%)",
+            "sell_quote_volume      80 → 79.84008 (-0.15992, -0.19%)",
+            "max_single_quote       80 → 79.84008 (-0.15992, -0.19%)",
+            "max_single_base        0.4 → 0.3996 (-0.0004, -0.1%)",
+            "avg_quote_per_trade    50 → 49.92004 (-0.07996, -0.15%)",
+            "max_drawdown           0 → 0.02 (+0.02)",
+            "max_drawdown_pct       0 → 0.001 (+0.001)",
+            "gross_profit           60 → 59.84008 (-0.15992, -0.26%)",
+        ];
+        assert_eq!(diff.to_string(), expected.join("\n"));
+
+        assert!(charged.diff(&charged).is_unchanged());
+        assert_eq!(charged.diff(&charged).to_string(), "");
+    }
+
+    #[test]
+    fn test_diff_options() {
+        let empty = Evaluate::default();
+        let report = run("0.4", "80");
+
+        let diff = empty.diff(&report);
+        assert_eq!(diff.get("max_price"), Some(&Change::Added(dec("200"))));
+        assert_eq!(diff.get("profit_factor"), Some(&Change::Unchanged(None)));
+
+        let diff = report.diff(&empty);
+        assert_eq!(diff.get("min_price"), Some(&Change::Removed(dec("50"))));
+        assert_eq!(
+            diff.get("max_price").unwrap().to_string(),
+            "200 → - (removed)"
+        );
+        assert_eq!(
+            diff.get("gross_profit"),
+            Some(&Change::Changed {
+                before: dec("60"),
+                after: dec("0"),
+                delta: dec("-60"),
+                relative: Some(dec("-1")),
+            })
+        );
+    }
+}
@@ -1,10 +1,24 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Range};
+
+use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
 
-use crate::types::{BaseQuantity, Price, QuoteQuantity};
+use crate::math;
+use crate::types::{BaseQuantity, Decimal, Price, Quantity, QuoteQuantity, Timestamp};
 
-use super::{Trade, TradeSide};
+use super::lots::FifoLots;
+use super::table::{display_decimal, render_table, DISPLAY_SCALE};
+use super::{
+    checked, checked_div, group_by_symbol, ArithmeticError, Trade, TradeSide, QUANTITY_TOLERANCE,
+};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+const MILLIS_PER_HOUR: u64 = 60 * 60 * 1000;
+const MILLIS_PER_YEAR: u64 = 365 * 24 * MILLIS_PER_HOUR;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Evaluate {
     pub volume_base_quantity: BaseQuantity,
     pub volume_quote_quantity: QuoteQuantity,
@@ -12,9 +26,229 @@ pub struct Evaluate {
     pub leave_quote_quantity: QuoteQuantity,
     pub buy_count: usize,
     pub sell_count: usize,
-    pub max_price: Price,
-    pub min_price: Price,
-    pub costs: QuoteQuantity,
+    pub max_price: Option<Price>, // `None` without trades, like the minimum
+    pub min_price: Option<Price>,
+    pub costs: QuoteQuantity, // Signed, rebates offset the commissions
+
+    // The volumes split by side, each pair summing to the volume totals
+    #[serde(default)]
+    pub buy_base_volume: BaseQuantity,
+
+    #[serde(default)]
+    pub buy_quote_volume: QuoteQuantity,
+
+    #[serde(default)]
+    pub sell_base_volume: BaseQuantity,
+
+    #[serde(default)]
+    pub sell_quote_volume: QuoteQuantity,
+
+    // The largest single trade on either side, each quantity on its own, and the mean quote
+    #[serde(default)]
+    pub max_single_quote: QuoteQuantity,
+
+    #[serde(default)]
+    pub max_single_base: BaseQuantity,
+
+    #[serde(default)]
+    pub avg_quote_per_trade: Option<QuoteQuantity>,
+
+    // Sum of the rebated amounts alone, as a positive value
+    #[serde(default)]
+    pub rebates: QuoteQuantity,
+
+    // Volume weighted by base quantity, `None` without trades on that side
+    #[serde(default)]
+    pub avg_buy_price: Option<Price>,
+
+    #[serde(default)]
+    pub avg_sell_price: Option<Price>,
+
+    // Adverse slippage in quote over trades with a requested price, and its average per trade
+    #[serde(default)]
+    pub slippage: QuoteQuantity,
+
+    #[serde(default)]
+    pub avg_slippage: Option<QuoteQuantity>,
+
+    // Trades whose quantities imply a price beyond `QUANTITY_TOLERANCE` of the recorded one
+    #[serde(default)]
+    pub inconsistent_count: usize,
+
+    // Explicit fees in the quote asset, kept apart from the implied `costs`
+    #[serde(default)]
+    pub fees: QuoteQuantity,
+
+    // Explicit fees charged in any other asset, keyed by that asset
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub other_fees: BTreeMap<String, Quantity>,
+
+    // Largest fall of the equity from its running peak. Equity is the leave quote plus the
+    // leave base marked at each trade's own price, starting from zero before the first trade.
+    #[serde(default)]
+    pub max_drawdown: QuoteQuantity,
+
+    // Largest fall relative to the quote committed so far, the deepest leave quote seen
+    #[serde(default)]
+    pub max_drawdown_pct: Decimal,
+
+    // Buys matched first-in-first-out to later sells, one round trip per matched pair.
+    // A win sells the matched base for more quote than the buy spent on it, so the
+    // implied fees of both sides count. Open buys and sells without inventory are left out.
+    #[serde(default)]
+    pub round_trips: usize,
+
+    #[serde(default)]
+    pub winning_round_trips: usize,
+
+    #[serde(default)]
+    pub win_rate: Option<Decimal>,
+
+    // Summed quote won by the winning round trips and lost by the others, both positive
+    #[serde(default)]
+    pub gross_profit: QuoteQuantity,
+
+    #[serde(default)]
+    pub gross_loss: QuoteQuantity,
+
+    // Gross profit over gross loss, `None` rather than infinite without a losing round trip
+    #[serde(default)]
+    pub profit_factor: Option<Decimal>,
+
+    // The mark price at which `net_profit_at` is zero, `None` unless some base is still held
+    #[serde(default)]
+    pub break_even_price: Option<Price>,
+
+    // Milliseconds from a buy to the sell that closes it, over the FIFO matched round trips
+    #[serde(default)]
+    pub avg_holding_millis: Option<u64>,
+
+    #[serde(default)]
+    pub max_holding_millis: u64,
+
+    // Milliseconds from one trade to the next while some base, long or short, was held
+    #[serde(default)]
+    pub time_in_market_millis: u64,
+
+    // Longest wait from one trade to the next in the order given, so the trades are assumed
+    // sorted by time and an earlier stamped trade counts as no wait. `None` below two trades.
+    #[serde(default)]
+    pub max_gap_millis: Option<u64>,
+
+    // Earliest and latest trade timestamps, whatever order the trades came in
+    #[serde(default)]
+    pub first_timestamp: Option<Timestamp>,
+
+    #[serde(default)]
+    pub last_timestamp: Option<Timestamp>,
+
+    // What `update` carries from one trade to the next, serialized along with the report.
+    // `None` once merged with another report that has trades, see `is_updatable`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub running: Option<RunningTotals>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunningTotals {
+    slippages: usize,
+    peak: QuoteQuantity,
+    committed: QuoteQuantity,
+    lots: FifoLots,
+    holding_millis: u128,
+    previous: Option<Timestamp>,
+    buy_notional: QuoteQuantity,
+    buy_volume: BaseQuantity,
+    sell_notional: QuoteQuantity,
+    sell_volume: BaseQuantity,
+}
+
+// Compares the reported figures. `running` is left out, it only records how they were
+// reached, so a merged or read back report equals one evaluated from the same trades.
+impl PartialEq for Evaluate {
+    fn eq(&self, other: &Self) -> bool {
+        let Evaluate {
+            volume_base_quantity,
+            volume_quote_quantity,
+            leave_base_quantity,
+            leave_quote_quantity,
+            buy_count,
+            sell_count,
+            max_price,
+            min_price,
+            costs,
+            buy_base_volume,
+            buy_quote_volume,
+            sell_base_volume,
+            sell_quote_volume,
+            max_single_quote,
+            max_single_base,
+            avg_quote_per_trade,
+            rebates,
+            avg_buy_price,
+            avg_sell_price,
+            slippage,
+            avg_slippage,
+            inconsistent_count,
+            fees,
+            other_fees,
+            max_drawdown,
+            max_drawdown_pct,
+            round_trips,
+            winning_round_trips,
+            win_rate,
+            gross_profit,
+            gross_loss,
+            profit_factor,
+            break_even_price,
+            avg_holding_millis,
+            max_holding_millis,
+            time_in_market_millis,
+            max_gap_millis,
+            first_timestamp,
+            last_timestamp,
+            running: _,
+        } = self;
+
+        *volume_base_quantity == other.volume_base_quantity
+            && *volume_quote_quantity == other.volume_quote_quantity
+            && *leave_base_quantity == other.leave_base_quantity
+            && *leave_quote_quantity == other.leave_quote_quantity
+            && *buy_count == other.buy_count
+            && *sell_count == other.sell_count
+            && *max_price == other.max_price
+            && *min_price == other.min_price
+            && *costs == other.costs
+            && *buy_base_volume == other.buy_base_volume
+            && *buy_quote_volume == other.buy_quote_volume
+            && *sell_base_volume == other.sell_base_volume
+            && *sell_quote_volume == other.sell_quote_volume
+            && *max_single_quote == other.max_single_quote
+            && *max_single_base == other.max_single_base
+            && *avg_quote_per_trade == other.avg_quote_per_trade
+            && *rebates == other.rebates
+            && *avg_buy_price == other.avg_buy_price
+            && *avg_sell_price == other.avg_sell_price
+            && *slippage == other.slippage
+            && *avg_slippage == other.avg_slippage
+            && *inconsistent_count == other.inconsistent_count
+            && *fees == other.fees
+            && *other_fees == other.other_fees
+            && *max_drawdown == other.max_drawdown
+            && *max_drawdown_pct == other.max_drawdown_pct
+            && *round_trips == other.round_trips
+            && *winning_round_trips == other.winning_round_trips
+            && *win_rate == other.win_rate
+            && *gross_profit == other.gross_profit
+            && *gross_loss == other.gross_loss
+            && *profit_factor == other.profit_factor
+            && *break_even_price == other.break_even_price
+            && *avg_holding_millis == other.avg_holding_millis
+            && *max_holding_millis == other.max_holding_millis
+            && *time_in_market_millis == other.time_in_market_millis
+            && *max_gap_millis == other.max_gap_millis
+            && *first_timestamp == other.first_timestamp
+            && *last_timestamp == other.last_timestamp
+    }
 }
 
 impl Default for Evaluate {
@@ -26,67 +260,753 @@ impl Default for Evaluate {
             leave_quote_quantity: QuoteQuantity::ZERO,
             buy_count: 0,
             sell_count: 0,
-            max_price: Price::ZERO,
-            min_price: Price::MAX,
+            max_price: None,
+            min_price: None,
             costs: QuoteQuantity::ZERO,
+            buy_base_volume: BaseQuantity::ZERO,
+            buy_quote_volume: QuoteQuantity::ZERO,
+            sell_base_volume: BaseQuantity::ZERO,
+            sell_quote_volume: QuoteQuantity::ZERO,
+            max_single_quote: QuoteQuantity::ZERO,
+            max_single_base: BaseQuantity::ZERO,
+            avg_quote_per_trade: None,
+            rebates: QuoteQuantity::ZERO,
+            avg_buy_price: None,
+            avg_sell_price: None,
+            slippage: QuoteQuantity::ZERO,
+            avg_slippage: None,
+            inconsistent_count: 0,
+            fees: QuoteQuantity::ZERO,
+            other_fees: BTreeMap::new(),
+            max_drawdown: QuoteQuantity::ZERO,
+            max_drawdown_pct: Decimal::ZERO,
+            round_trips: 0,
+            winning_round_trips: 0,
+            win_rate: None,
+            gross_profit: QuoteQuantity::ZERO,
+            gross_loss: QuoteQuantity::ZERO,
+            profit_factor: None,
+            break_even_price: None,
+            avg_holding_millis: None,
+            max_holding_millis: 0,
+            time_in_market_millis: 0,
+            max_gap_millis: None,
+            first_timestamp: None,
+            last_timestamp: None,
+            running: Some(RunningTotals::default()),
         }
     }
 }
 
+impl Evaluate {
+    pub fn from_trades(trades: &[Trade]) -> Evaluate {
+        Self::try_from_trades(trades).unwrap_or_else(|error| panic!("evaluate: {}", error))
+    }
+
+    // Like `from_trades`, but an out of range Decimal operation is returned instead of panicking
+    pub fn try_from_trades(trades: &[Trade]) -> Result<Evaluate, ArithmeticError> {
+        let mut report = Evaluate::default();
+        for trade in trades.iter() {
+            report.try_update(trade)?;
+        }
+
+        Ok(report)
+    }
+
+    // Folds one more trade into the report, the same arithmetic `from_trades` applies per trade.
+    // Panics on a report that cannot continue, see `is_updatable`.
+    pub fn update(&mut self, trade: &Trade) {
+        assert!(
+            self.is_updatable(),
+            "evaluate: no running totals to update a merged report from"
+        );
+        self.try_update(trade)
+            .unwrap_or_else(|error| panic!("evaluate: {}", error))
+    }
+
+    // False for a report merged from two with trades, whose open lots and peaks cannot be
+    // combined, and for one read back without its running totals. A report without trades
+    // always starts afresh.
+    pub fn is_updatable(&self) -> bool {
+        self.running.is_some() || self.buy_count + self.sell_count == 0
+    }
+
+    pub fn updated(mut self, trade: &Trade) -> Evaluate {
+        self.update(trade);
+        self
+    }
+
+    fn try_update(&mut self, trade: &Trade) -> Result<(), ArithmeticError> {
+        let running = self.running.get_or_insert_with(RunningTotals::default);
+
+        if self.max_price.is_none_or(|max| trade.price > max) {
+            self.max_price = Some(trade.price)
+        }
+
+        if self.min_price.is_none_or(|min| trade.price < min) {
+            self.min_price = Some(trade.price)
+        }
+
+        if self
+            .first_timestamp
+            .is_none_or(|first| trade.timestamp < first)
+        {
+            self.first_timestamp = Some(trade.timestamp)
+        }
+
+        if self
+            .last_timestamp
+            .is_none_or(|last| trade.timestamp > last)
+        {
+            self.last_timestamp = Some(trade.timestamp)
+        }
+
+        let costs = trade.try_implied_costs()?;
+        self.costs = checked(self.costs.checked_add(costs))?;
+        if !trade.price_consistency(QUANTITY_TOLERANCE) {
+            self.inconsistent_count += 1;
+        }
+
+        if costs < QuoteQuantity::ZERO {
+            self.rebates = checked(self.rebates.checked_sub(costs))?;
+        }
+        match (trade.fee, &trade.fee_asset) {
+            (Some(fee), None) => self.fees = checked(self.fees.checked_add(fee))?,
+            (Some(fee), Some(asset)) => {
+                let total = self.other_fees.entry(asset.clone()).or_default();
+                *total = checked(total.checked_add(fee))?;
+            }
+            (None, _) => {}
+        }
+
+        if let Some(requested) = trade.requested_price {
+            let slippage = checked(trade.price.checked_sub(requested))?;
+            let slippage = checked(slippage.checked_mul(trade.base_quantity))?;
+            self.slippage = match trade.side {
+                TradeSide::Buy => checked(self.slippage.checked_add(slippage))?,
+                TradeSide::Sell => checked(self.slippage.checked_sub(slippage))?,
+            };
+            running.slippages += 1;
+            let count = Decimal::from(running.slippages);
+            self.avg_slippage = Some(checked_div(self.slippage, count)?);
+        }
+
+        self.volume_base_quantity =
+            checked(self.volume_base_quantity.checked_add(trade.base_quantity))?;
+        self.volume_quote_quantity =
+            checked(self.volume_quote_quantity.checked_add(trade.quote_quantity))?;
+        self.max_single_quote = std::cmp::max(self.max_single_quote, trade.quote_quantity);
+        self.max_single_base = std::cmp::max(self.max_single_base, trade.base_quantity);
+
+        if let Some(previous) = running.previous {
+            let elapsed = trade
+                .timestamp
+                .as_millis()
+                .saturating_sub(previous.as_millis());
+            if !self.leave_base_quantity.is_zero() {
+                self.time_in_market_millis = self.time_in_market_millis.saturating_add(elapsed);
+            }
+
+            self.max_gap_millis = Some(self.max_gap_millis.map_or(elapsed, |gap| gap.max(elapsed)));
+        }
+        running.previous = Some(trade.timestamp);
+
+        let (base, quote) = (trade.base_quantity, trade.quote_quantity);
+        let notional = checked(trade.price.checked_mul(base))?;
+        let matches = running.lots.push(trade)?;
+        match trade.side {
+            TradeSide::Buy => {
+                running.buy_notional = checked(running.buy_notional.checked_add(notional))?;
+                running.buy_volume = checked(running.buy_volume.checked_add(base))?;
+                if !running.buy_volume.is_zero() {
+                    let price = checked_div(running.buy_notional, running.buy_volume)?;
+                    self.avg_buy_price = Some(price);
+                }
+
+                self.buy_count += 1;
+                self.buy_base_volume = checked(self.buy_base_volume.checked_add(base))?;
+                self.buy_quote_volume = checked(self.buy_quote_volume.checked_add(quote))?;
+                self.leave_base_quantity = checked(self.leave_base_quantity.checked_add(base))?;
+                self.leave_quote_quantity = checked(self.leave_quote_quantity.checked_sub(quote))?;
+            }
+            TradeSide::Sell => {
+                for matched in matches.iter() {
+                    self.round_trips += 1;
+                    let held = trade
+                        .timestamp
+                        .as_millis()
+                        .saturating_sub(matched.bought.as_millis());
+                    self.max_holding_millis = std::cmp::max(self.max_holding_millis, held);
+                    running.holding_millis += u128::from(held);
+                    let avg = running.holding_millis / self.round_trips as u128;
+                    self.avg_holding_millis = Some(u64::try_from(avg).unwrap_or(u64::MAX));
+                    let result = checked(matched.proceeds.checked_sub(matched.cost))?;
+                    if result > QuoteQuantity::ZERO {
+                        self.winning_round_trips += 1;
+                        self.gross_profit = checked(self.gross_profit.checked_add(result))?;
+                    } else {
+                        self.gross_loss = checked(self.gross_loss.checked_sub(result))?;
+                    }
+                }
+
+                running.sell_notional = checked(running.sell_notional.checked_add(notional))?;
+                running.sell_volume = checked(running.sell_volume.checked_add(base))?;
+                if !running.sell_volume.is_zero() {
+                    let price = checked_div(running.sell_notional, running.sell_volume)?;
+                    self.avg_sell_price = Some(price);
+                }
+
+                self.sell_count += 1;
+                self.sell_base_volume = checked(self.sell_base_volume.checked_add(base))?;
+                self.sell_quote_volume = checked(self.sell_quote_volume.checked_add(quote))?;
+                self.leave_base_quantity = checked(self.leave_base_quantity.checked_sub(base))?;
+                self.leave_quote_quantity = checked(self.leave_quote_quantity.checked_add(quote))?;
+            }
+        }
+
+        let marked = checked(self.leave_base_quantity.checked_mul(trade.price))?;
+        let equity = checked(self.leave_quote_quantity.checked_add(marked))?;
+        running.peak = std::cmp::max(running.peak, equity);
+        running.committed = std::cmp::max(running.committed, -self.leave_quote_quantity);
+
+        let drawdown = checked(running.peak.checked_sub(equity))?;
+        self.max_drawdown = std::cmp::max(self.max_drawdown, drawdown);
+        if running.committed > QuoteQuantity::ZERO {
+            let pct = checked_div(drawdown, running.committed)?;
+            self.max_drawdown_pct = std::cmp::max(self.max_drawdown_pct, pct);
+        }
+
+        self.break_even_price = None;
+        if self.leave_base_quantity > BaseQuantity::ZERO {
+            let price = checked_div(-self.leave_quote_quantity, self.leave_base_quantity)?;
+            self.break_even_price = Some(price);
+        }
+
+        let count = Decimal::from(self.buy_count + self.sell_count);
+        self.avg_quote_per_trade = Some(checked_div(self.volume_quote_quantity, count)?);
+
+        if self.round_trips > 0 {
+            let wins = Decimal::from(self.winning_round_trips);
+            self.win_rate = Some(checked_div(wins, Decimal::from(self.round_trips))?);
+        }
+
+        if !self.gross_loss.is_zero() {
+            self.profit_factor = Some(checked_div(self.gross_profit, self.gross_loss)?);
+        }
+
+        Ok(())
+    }
+
+    // One report for two separately evaluated sets of trades. Sums, counts and the extremes
+    // are exact, as are the win rate, profit factor, break-even price and average trade size
+    // recomputed from them.
+    // Without the trades the rest is approximate: average prices and slippage are weighted
+    // by trade count, the average holding time by round trips, the time in market and the
+    // largest gap leave out any gap between the two and the drawdowns are the larger of the two.
+    // Only a merge with a report without trades keeps the running totals to `update` from.
+    pub fn merge(&self, other: &Evaluate) -> Evaluate {
+        let count = |report: &Evaluate| Decimal::from(report.buy_count + report.sell_count);
+
+        let mut other_fees = self.other_fees.clone();
+        for (asset, fee) in other.other_fees.iter() {
+            *other_fees.entry(asset.clone()).or_default() += fee;
+        }
+
+        let mut report = Evaluate {
+            volume_base_quantity: self.volume_base_quantity + other.volume_base_quantity,
+            volume_quote_quantity: self.volume_quote_quantity + other.volume_quote_quantity,
+            leave_base_quantity: self.leave_base_quantity + other.leave_base_quantity,
+            leave_quote_quantity: self.leave_quote_quantity + other.leave_quote_quantity,
+            buy_count: self.buy_count + other.buy_count,
+            sell_count: self.sell_count + other.sell_count,
+            max_price: extreme(self.max_price, other.max_price, std::cmp::max),
+            min_price: extreme(self.min_price, other.min_price, std::cmp::min),
+            costs: self.costs + other.costs,
+            buy_base_volume: self.buy_base_volume + other.buy_base_volume,
+            buy_quote_volume: self.buy_quote_volume + other.buy_quote_volume,
+            sell_base_volume: self.sell_base_volume + other.sell_base_volume,
+            sell_quote_volume: self.sell_quote_volume + other.sell_quote_volume,
+            max_single_quote: std::cmp::max(self.max_single_quote, other.max_single_quote),
+            max_single_base: std::cmp::max(self.max_single_base, other.max_single_base),
+            avg_quote_per_trade: None,
+            rebates: self.rebates + other.rebates,
+            avg_buy_price: weighted_mean(
+                self.avg_buy_price,
+                Decimal::from(self.buy_count),
+                other.avg_buy_price,
+                Decimal::from(other.buy_count),
+            ),
+            avg_sell_price: weighted_mean(
+                self.avg_sell_price,
+                Decimal::from(self.sell_count),
+                other.avg_sell_price,
+                Decimal::from(other.sell_count),
+            ),
+            slippage: self.slippage + other.slippage,
+            avg_slippage: weighted_mean(
+                self.avg_slippage,
+                count(self),
+                other.avg_slippage,
+                count(other),
+            ),
+            inconsistent_count: self.inconsistent_count + other.inconsistent_count,
+            fees: self.fees + other.fees,
+            other_fees,
+            max_drawdown: std::cmp::max(self.max_drawdown, other.max_drawdown),
+            max_drawdown_pct: std::cmp::max(self.max_drawdown_pct, other.max_drawdown_pct),
+            round_trips: self.round_trips + other.round_trips,
+            winning_round_trips: self.winning_round_trips + other.winning_round_trips,
+            win_rate: None,
+            gross_profit: self.gross_profit + other.gross_profit,
+            gross_loss: self.gross_loss + other.gross_loss,
+            profit_factor: None,
+            break_even_price: None,
+            avg_holding_millis: weighted_mean(
+                self.avg_holding_millis.map(Decimal::from),
+                Decimal::from(self.round_trips),
+                other.avg_holding_millis.map(Decimal::from),
+                Decimal::from(other.round_trips),
+            )
+            .and_then(|millis| millis.floor().to_u64()),
+            max_holding_millis: std::cmp::max(self.max_holding_millis, other.max_holding_millis),
+            time_in_market_millis: self.time_in_market_millis + other.time_in_market_millis,
+            max_gap_millis: extreme(self.max_gap_millis, other.max_gap_millis, std::cmp::max),
+            first_timestamp: extreme(self.first_timestamp, other.first_timestamp, std::cmp::min),
+            last_timestamp: extreme(self.last_timestamp, other.last_timestamp, std::cmp::max),
+            running: match (count(self).is_zero(), count(other).is_zero()) {
+                (_, true) => self.running.clone(),
+                (true, false) => other.running.clone(),
+                (false, false) => None,
+            },
+        };
+
+        if !count(&report).is_zero() {
+            report.avg_quote_per_trade = Some(report.volume_quote_quantity / count(&report));
+        }
+
+        if report.round_trips > 0 {
+            let wins = Decimal::from(report.winning_round_trips);
+            report.win_rate = Some(wins / Decimal::from(report.round_trips));
+        }
+
+        if !report.gross_loss.is_zero() {
+            report.profit_factor = Some(report.gross_profit / report.gross_loss);
+        }
+
+        if report.leave_base_quantity > BaseQuantity::ZERO {
+            report.break_even_price =
+                Some(-report.leave_quote_quantity / report.leave_base_quantity);
+        }
+
+        report
+    }
+
+    // For callers of the former plain price fields, zero stands in for no trades
+    pub fn max_price_or_zero(&self) -> Price {
+        self.max_price.unwrap_or(Price::ZERO)
+    }
+
+    pub fn min_price_or_zero(&self) -> Price {
+        self.min_price.unwrap_or(Price::ZERO)
+    }
+
+    // Costs per unit of quote traded over the same trades, negative when rebates dominate
+    pub fn cost_ratio(&self) -> Option<Decimal> {
+        if self.volume_quote_quantity.is_zero() {
+            return None;
+        }
+
+        Some(self.costs / self.volume_quote_quantity)
+    }
+
+    pub fn avg_cost_per_trade(&self) -> Option<QuoteQuantity> {
+        match self.buy_count + self.sell_count {
+            0 => None,
+            count => Some(self.costs / Decimal::from(count)),
+        }
+    }
+
+    // The leave quote plus the leave base valued at `mark_price`, a short base is a debt
+    pub fn net_profit_at(&self, mark_price: &Price) -> QuoteQuantity {
+        self.leave_quote_quantity + self.leave_base_quantity * mark_price
+    }
+
+    // Net profit at `mark_price` as a fraction of `initial_quote`, `None` without an investment
+    pub fn roi_at(&self, mark_price: &Price, initial_quote: &QuoteQuantity) -> Option<Decimal> {
+        if initial_quote.is_zero() {
+            return None;
+        }
+
+        Some(self.net_profit_at(mark_price) / initial_quote)
+    }
+
+    // Milliseconds from the first to the last trade, `None` without trades
+    pub fn duration_millis(&self) -> Option<u64> {
+        let first = self.first_timestamp?.as_millis();
+        let last = self.last_timestamp?.as_millis();
+        Some(last.saturating_sub(first))
+    }
+
+    // Trades over the hours from the first to the last, `None` when all share one millisecond
+    pub fn trades_per_hour(&self) -> Option<Decimal> {
+        let elapsed = self.duration_millis()?;
+        if elapsed == 0 {
+            return None;
+        }
+
+        let count = Decimal::from(self.buy_count + self.sell_count);
+        Some(count * Decimal::from(MILLIS_PER_HOUR) / Decimal::from(elapsed))
+    }
+
+    // `roi_at` scaled linearly, not compounded, from the first to the last trade to a
+    // 365 day year. `None` without an investment or when all trades share one millisecond.
+    pub fn annualized_roi_at(
+        &self,
+        mark_price: &Price,
+        initial_quote: &QuoteQuantity,
+    ) -> Option<Decimal> {
+        let elapsed = self.duration_millis()?;
+        if elapsed == 0 {
+            return None;
+        }
+
+        let roi = self.roi_at(mark_price, initial_quote)?;
+        Some(roi * Decimal::from(MILLIS_PER_YEAR) / Decimal::from(elapsed))
+    }
+
+    // The trades as a table followed by this report's totals, for example programs
+    pub fn render(&self, trades: &[Trade]) -> String {
+        format!("{}\n\n{}", render_table(trades), self)
+    }
+
+    // The labeled report of `Display` with Decimals truncated to `scale` places
+    pub fn display_with_scale(&self, scale: u32) -> String {
+        labeled(&self.summary(scale))
+    }
+
+    // The report followed by the net profit with the leave base valued at `mark_price`
+    pub fn report_at(&self, mark_price: &Price) -> String {
+        let decimal = |value: Decimal| display_decimal(value, DISPLAY_SCALE);
+        let mut summary = self.summary(DISPLAY_SCALE);
+        summary.push(("mark price", decimal(*mark_price)));
+        summary.push(("net profit", decimal(self.net_profit_at(mark_price))));
+        labeled(&summary)
+    }
+
+    fn summary(&self, scale: u32) -> Vec<(&'static str, String)> {
+        let decimal = |value: Decimal| display_decimal(value, scale);
+        let optional = |value: Option<Decimal>| value.map_or(String::from("-"), decimal);
+        let prices = match (self.min_price, self.max_price) {
+            (Some(min), Some(max)) => format!("{} - {}", decimal(min), decimal(max)),
+            _ => String::from("-"),
+        };
+
+        vec![
+            ("buys", self.buy_count.to_string()),
+            ("sells", self.sell_count.to_string()),
+            ("prices", prices),
+            ("volume base", decimal(self.volume_base_quantity)),
+            ("volume quote", decimal(self.volume_quote_quantity)),
+            ("buy base", decimal(self.buy_base_volume)),
+            ("buy quote", decimal(self.buy_quote_volume)),
+            ("sell base", decimal(self.sell_base_volume)),
+            ("sell quote", decimal(self.sell_quote_volume)),
+            ("max quote", decimal(self.max_single_quote)),
+            ("max base", decimal(self.max_single_base)),
+            ("avg quote", optional(self.avg_quote_per_trade)),
+            ("leave base", decimal(self.leave_base_quantity)),
+            ("leave quote", decimal(self.leave_quote_quantity)),
+            ("costs", decimal(self.costs)),
+            ("cost ratio", optional(self.cost_ratio())),
+            ("avg cost", optional(self.avg_cost_per_trade())),
+            (
+                "avg hold ms",
+                self.avg_holding_millis
+                    .map_or(String::from("-"), |ms| ms.to_string()),
+            ),
+            ("max hold ms", self.max_holding_millis.to_string()),
+            ("in market ms", self.time_in_market_millis.to_string()),
+            ("trades/hour", optional(self.trades_per_hour())),
+            (
+                "max gap ms",
+                self.max_gap_millis
+                    .map_or(String::from("-"), |ms| ms.to_string()),
+            ),
+        ]
+    }
+}
+
+// One `label  value` line per entry with the values aligned
+pub(crate) fn labeled(entries: &[(&str, String)]) -> String {
+    let width = entries
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|(label, value)| format!("{:<width$}  {}", label, value, width = width))
+        .collect();
+
+    lines.join("\n")
+}
+
+impl fmt::Display for Evaluate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_with_scale(DISPLAY_SCALE))
+    }
+}
+
+// The one present, or `pick` of both
+fn extreme<T>(a: Option<T>, b: Option<T>, pick: fn(T, T) -> T) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(pick(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+// Mean of two optional values by their weights, either one alone when the other is missing
+fn weighted_mean(
+    a: Option<Decimal>,
+    a_weight: Decimal,
+    b: Option<Decimal>,
+    b_weight: Decimal,
+) -> Option<Decimal> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some((a * a_weight + b * b_weight) / (a_weight + b_weight)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl Add for Evaluate {
+    type Output = Evaluate;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.merge(&rhs)
+    }
+}
+
+impl Sum for Evaluate {
+    fn sum<I: Iterator<Item = Evaluate>>(iter: I) -> Self {
+        iter.fold(Evaluate::default(), |total, report| total.merge(&report))
+    }
+}
+
+impl<'a> Sum<&'a Evaluate> for Evaluate {
+    fn sum<I: Iterator<Item = &'a Evaluate>>(iter: I) -> Self {
+        iter.fold(Evaluate::default(), |total, report| total.merge(report))
+    }
+}
+
 pub trait Evaluater {
+    // A wrapper over the synchronous `Evaluate::from_trades`
     fn evaluate(&self) -> impl std::future::Future<Output = Evaluate> + Send;
+
+    // A wrapper over `Evaluate::try_from_trades`
+    fn try_evaluate(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Evaluate, ArithmeticError>> + Send;
+}
+
+impl Evaluater for [Trade] {
+    async fn evaluate(&self) -> Evaluate {
+        Evaluate::from_trades(self)
+    }
+
+    async fn try_evaluate(&self) -> Result<Evaluate, ArithmeticError> {
+        Evaluate::try_from_trades(self)
+    }
+}
+
+impl Evaluater for &[Trade] {
+    async fn evaluate(&self) -> Evaluate {
+        (**self).evaluate().await
+    }
+
+    async fn try_evaluate(&self) -> Result<Evaluate, ArithmeticError> {
+        (**self).try_evaluate().await
+    }
 }
 
 impl Evaluater for Vec<Trade> {
     async fn evaluate(&self) -> Evaluate {
-        let mut report = Evaluate::default();
+        self.as_slice().evaluate().await
+    }
 
-        if self.is_empty() {
-            return report;
-        }
+    async fn try_evaluate(&self) -> Result<Evaluate, ArithmeticError> {
+        self.as_slice().try_evaluate().await
+    }
+}
 
-        for trade in self.iter() {
-            if trade.price > report.max_price {
-                report.max_price = trade.price
-            }
+// Evaluates borrowed trades from any source, a filtered log for one, without collecting them
+pub fn evaluate_iter<'a>(trades: impl IntoIterator<Item = &'a Trade>) -> Evaluate {
+    trades
+        .into_iter()
+        .fold(Evaluate::default(), Evaluate::updated)
+}
 
-            if trade.price < report.min_price {
-                report.min_price = trade.price
-            }
+// Balances right after a trade, the base valued at that trade's price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub timestamp: Timestamp,
+    pub base: BaseQuantity,
+    pub quote: QuoteQuantity,
+    pub equity_in_quote: QuoteQuantity,
+}
 
-            report.costs += trade.costs();
-            report.volume_base_quantity += trade.base_quantity;
-            report.volume_quote_quantity += trade.quote_quantity;
+// One point per trade starting from the initial balances. The trades are taken in timestamp
+// order, sorted stably so trades sharing a timestamp keep their given order.
+pub fn equity_curve(
+    trades: &[Trade],
+    initial_base: BaseQuantity,
+    initial_quote: QuoteQuantity,
+) -> Vec<EquityPoint> {
+    let mut ordered: Vec<&Trade> = trades.iter().collect();
+    ordered.sort_by_key(|trade| trade.timestamp);
 
+    let (mut base, mut quote) = (initial_base, initial_quote);
+    ordered
+        .into_iter()
+        .map(|trade| {
             match trade.side {
                 TradeSide::Buy => {
-                    report.buy_count += 1;
-                    report.leave_base_quantity += trade.base_quantity;
-                    report.leave_quote_quantity -= trade.quote_quantity;
+                    base += trade.base_quantity;
+                    quote -= trade.quote_quantity;
                 }
                 TradeSide::Sell => {
-                    report.sell_count += 1;
-                    report.leave_base_quantity -= trade.base_quantity;
-                    report.leave_quote_quantity += trade.quote_quantity;
+                    base -= trade.base_quantity;
+                    quote += trade.quote_quantity;
                 }
             }
+
+            EquityPoint {
+                timestamp: trade.timestamp,
+                base,
+                quote,
+                equity_in_quote: quote + base * trade.price,
+            }
+        })
+        .collect()
+}
+
+// One report per `bucket_millis` wide window of time, in chronological order, skipping
+// windows without trades. Hand-built trades stamped zero fall into the very first window.
+pub fn bucketed(trades: &[Trade], bucket_millis: u64) -> Vec<(Range<Timestamp>, Evaluate)> {
+    if bucket_millis == 0 {
+        return Vec::new();
+    }
+
+    let mut buckets: BTreeMap<u64, Vec<&Trade>> = BTreeMap::new();
+    for trade in trades.iter() {
+        let bucket = trade.timestamp.as_millis() / bucket_millis;
+        buckets.entry(bucket).or_default().push(trade);
+    }
+
+    buckets
+        .into_iter()
+        .map(|(bucket, trades)| {
+            let start = bucket * bucket_millis;
+            let end = start.saturating_add(bucket_millis);
+            (Timestamp(start)..Timestamp(end), evaluate_iter(trades))
+        })
+        .collect()
+}
+
+// Mean over standard deviation of the FIFO round trip returns, each return being the round
+// trip's profit as a fraction of `initial_quote`. Trades are matched in the given order. A
+// rough risk adjusted figure for ranking strategies against each other, not annualized and
+// not a true Sharpe ratio, there is no risk free rate and no time scaling. `None` with fewer
+// than two round trips, without an investment or when every return is the same.
+pub fn sharpe_like(trades: &[Trade], initial_quote: &QuoteQuantity) -> Option<Decimal> {
+    if initial_quote.is_zero() {
+        return None;
+    }
+
+    let returns: Vec<Decimal> = round_trip_results(trades)?
+        .into_iter()
+        .map(|result| result.checked_div(*initial_quote))
+        .collect::<Option<_>>()?;
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let stddev = math::stddev(&returns)?;
+    if stddev.is_zero() {
+        return None;
+    }
+
+    math::mean(&returns)?.checked_div(stddev)
+}
+
+// Profit of every FIFO matched buy and sell, the round trips `Evaluate` counts.
+// `None` when the arithmetic overflows.
+fn round_trip_results(trades: &[Trade]) -> Option<Vec<QuoteQuantity>> {
+    let mut fifo = FifoLots::default();
+    let mut results = Vec::new();
+    for trade in trades.iter() {
+        for matched in fifo.push(trade).ok()? {
+            results.push(matched.proceeds.checked_sub(matched.cost)?);
         }
+    }
 
-        report
+    Some(results)
+}
+
+// Untagged trades are grouped under `None`
+pub async fn evaluate_by_symbol(trades: &[Trade]) -> BTreeMap<Option<String>, Evaluate> {
+    let mut reports = BTreeMap::new();
+    for (symbol, trades) in group_by_symbol(trades) {
+        reports.insert(symbol, trades.evaluate().await);
     }
+
+    reports
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::trade::evaluate::{Evaluate, Evaluater};
+    use std::collections::BTreeMap;
+
+    use crate::trade::evaluate::{
+        bucketed, equity_curve, evaluate_by_symbol, evaluate_iter, sharpe_like, EquityPoint,
+        Evaluate, Evaluater,
+    };
     use crate::trade::Trade;
+    use crate::trade::{ArithmeticError, TradeSide};
     use crate::types::Decimal;
+    use crate::types::Timestamp;
 
     fn dec(value: &str) -> Decimal {
         use std::str::FromStr;
         Decimal::from_str(value).unwrap()
     }
 
+    // Hand-built trades all stamped zero, so holding times do not depend on the clock
+    fn stamped(trades: Vec<Trade>) -> Vec<Trade> {
+        trades
+            .into_iter()
+            .map(|trade| Trade {
+                timestamp: Timestamp(0),
+                ..trade
+            })
+            .collect()
+    }
+
+    // A report holding only the timing of the trades, for the timestamps taken at creation
+    fn span(trades: &[Trade]) -> Evaluate {
+        Evaluate {
+            first_timestamp: trades.iter().map(|trade| trade.timestamp).min(),
+            last_timestamp: trades.iter().map(|trade| trade.timestamp).max(),
+            max_gap_millis: trades
+                .windows(2)
+                .map(|pair| {
+                    let (previous, next) = (pair[0].timestamp, pair[1].timestamp);
+                    next.as_millis().saturating_sub(previous.as_millis())
+                })
+                .max(),
+            ..Default::default()
+        }
+    }
+
     #[tokio::test]
     async fn test_evaluate() {
         let trades = vec![
@@ -94,6 +1014,7 @@ mod tests {
             Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
             Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
         ];
+        let trades = stamped(trades);
 
         assert_eq!(
             trades.evaluate().await,
@@ -104,9 +1025,24 @@ mod tests {
                 leave_quote_quantity: dec("2788.75"),
                 buy_count: 1,
                 sell_count: 2,
-                max_price: dec("210"),
-                min_price: dec("80"),
-                costs: dec("0")
+                max_price: Some(dec("210")),
+                min_price: Some(dec("80")),
+                costs: dec("0"),
+                buy_base_volume: dec("13.375"),
+                buy_quote_volume: dec("1070"),
+                sell_base_volume: dec("18.375"),
+                sell_quote_volume: dec("3858.75"),
+                max_single_quote: dec("2808.75"),
+                max_single_base: dec("13.375"),
+                avg_quote_per_trade: Some(dec("4928.75") / dec("3")),
+                avg_buy_price: Some(dec("80")),
+                avg_sell_price: Some(dec("210")),
+                round_trips: 1,
+                winning_round_trips: 1,
+                win_rate: Some(dec("1")),
+                avg_holding_millis: Some(0),
+                gross_profit: dec("1738.75"),
+                ..span(&trades)
             }
         );
 
@@ -114,6 +1050,7 @@ mod tests {
             Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
             Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
         ];
+        let trades = stamped(trades);
 
         assert_eq!(
             trades.evaluate().await,
@@ -124,9 +1061,27 @@ mod tests {
                 leave_quote_quantity: dec("59.8400800"),
                 buy_count: 1,
                 sell_count: 1,
-                max_price: dec("200"),
-                min_price: dec("50"),
-                costs: dec("0.0999200")
+                max_price: Some(dec("200")),
+                min_price: Some(dec("50")),
+                costs: dec("0.0999200"),
+                buy_base_volume: dec("0.3996"),
+                buy_quote_volume: dec("20.0"),
+                sell_base_volume: dec("0.3996"),
+                sell_quote_volume: dec("79.8400800"),
+                max_single_quote: dec("79.8400800"),
+                max_single_base: dec("0.3996"),
+                avg_quote_per_trade: Some(dec("49.92004")),
+                avg_buy_price: Some(dec("50")),
+                avg_sell_price: Some(dec("200")),
+                // The commission of the buy is the only dip in equity
+                max_drawdown: dec("0.02"),
+                max_drawdown_pct: dec("0.001"),
+                round_trips: 1,
+                winning_round_trips: 1,
+                win_rate: Some(dec("1")),
+                avg_holding_millis: Some(0),
+                gross_profit: dec("59.84008"),
+                ..span(&trades)
             }
         );
 
@@ -135,6 +1090,7 @@ mod tests {
             Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
             Trade::with_buy(dec("50"), dec("9.99"), dec("500.0")),
         ];
+        let trades = stamped(trades);
 
         assert_eq!(
             trades.evaluate().await,
@@ -145,9 +1101,27 @@ mod tests {
                 leave_quote_quantity: dec("-440.1599200"),
                 buy_count: 2,
                 sell_count: 1,
-                max_price: dec("200"),
-                min_price: dec("50"),
-                costs: dec("0.5999200")
+                max_price: Some(dec("200")),
+                min_price: Some(dec("50")),
+                costs: dec("0.5999200"),
+                buy_base_volume: dec("10.3896"),
+                buy_quote_volume: dec("520.0"),
+                sell_base_volume: dec("0.3996"),
+                sell_quote_volume: dec("79.8400800"),
+                max_single_quote: dec("500.0"),
+                max_single_base: dec("9.99"),
+                avg_quote_per_trade: Some(dec("599.84008") / dec("3")),
+                avg_buy_price: Some(dec("50")),
+                avg_sell_price: Some(dec("200")),
+                max_drawdown: dec("0.5"),
+                max_drawdown_pct: dec("0.5") / dec("440.15992"),
+                break_even_price: Some(dec("440.15992") / dec("9.99")),
+                round_trips: 1,
+                winning_round_trips: 1,
+                win_rate: Some(dec("1")),
+                avg_holding_millis: Some(0),
+                gross_profit: dec("59.84008"),
+                ..span(&trades)
             }
         );
 
@@ -155,6 +1129,7 @@ mod tests {
             Trade::with_buy(dec("507.545135202621"), dec("0.09841489"), dec("50")),
             Trade::with_sell(dec("509.067770608228"), dec("0.098"), dec("49.8387528781")),
         ];
+        let trades = stamped(trades);
 
         assert_eq!(
             trades.evaluate().await,
@@ -165,10 +1140,1035 @@ mod tests {
                 leave_quote_quantity: dec("-0.1612471219"),
                 buy_count: 1,
                 sell_count: 1,
-                max_price: dec("509.067770608228"),
-                min_price: dec("507.545135202621"),
-                costs: dec("0.0998899905052705733099999825")
+                max_price: Some(dec("509.067770608228")),
+                min_price: Some(dec("507.545135202621")),
+                costs: dec("0.0998899905052705733099999825"),
+                buy_base_volume: dec("0.09841489"),
+                buy_quote_volume: dec("50"),
+                sell_base_volume: dec("0.098"),
+                sell_quote_volume: dec("49.8387528781"),
+                max_single_quote: dec("50"),
+                max_single_base: dec("0.09841489"),
+                avg_quote_per_trade: Some(dec("99.8387528781") / dec("2")),
+                avg_buy_price: Some(dec("507.545135202621")),
+                avg_sell_price: Some(dec("509.067770608228")),
+                // Marked at the buy price the buy alone is worth less than it cost
+                max_drawdown: dec("50") - dec("0.09841489") * dec("507.545135202621"),
+                max_drawdown_pct: (dec("50") - dec("0.09841489") * dec("507.545135202621"))
+                    / dec("50"),
+                break_even_price: Some(dec("0.1612471219") / dec("0.00041489")),
+                round_trips: 1,
+                winning_round_trips: 1,
+                win_rate: Some(dec("1")),
+                avg_holding_millis: Some(0),
+                gross_profit: dec("49.8387528781") - dec("50") * dec("0.098") / dec("0.09841489"),
+                ..span(&trades)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_rebates() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_buy(dec("50"), dec("0.4004"), dec("20")),
+            Trade::with_sell(dec("200"), dec("0.4"), dec("80.08")),
+        ];
+
+        let report = trades.evaluate().await;
+        assert_eq!(report.costs, dec("-0.08"));
+        assert_eq!(report.rebates, dec("0.10"));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_inconsistent() {
+        let mut trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.4"), dec("80")),
+        ];
+        assert_eq!(trades.evaluate().await.inconsistent_count, 0);
+
+        trades[1].price = dec("100");
+        assert_eq!(trades.evaluate().await.inconsistent_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_fees() {
+        let bnb = Some(String::from("BNB"));
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::buy_with_fee(dec("50"), dec("0.4"), dec("20"), dec("0.01"), None),
+            Trade::sell_with_fee(
+                dec("200"),
+                dec("0.4"),
+                dec("80"),
+                dec("0.0001"),
+                bnb.clone(),
+            ),
+            Trade::sell_with_fee(dec("200"), dec("0.3"), dec("60"), dec("0.0002"), bnb),
+        ];
+
+        let report = trades.evaluate().await;
+        assert_eq!(report.costs, dec("0.02"));
+        assert_eq!(report.fees, dec("0.01"));
+        assert_eq!(
+            report.other_fees,
+            BTreeMap::from([(String::from("BNB"), dec("0.0003"))])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_by_symbol() {
+        let mut trades = vec![
+            Trade::with_buy(dec("50"), dec("0.4"), dec("20")),
+            Trade::with_sell(dec("200"), dec("0.4"), dec("80")),
+            Trade::with_buy(dec("10"), dec("2"), dec("20")),
+        ];
+        trades[0].symbol = Some("BTCUSDT".into());
+        trades[1].symbol = Some("BTCUSDT".into());
+
+        let reports = evaluate_by_symbol(&trades).await;
+        assert_eq!(reports.len(), 2);
+
+        let report = &reports[&Some("BTCUSDT".into())];
+        assert_eq!(report.buy_count, 1);
+        assert_eq!(report.sell_count, 1);
+        assert_eq!(report.leave_quote_quantity, dec("60"));
+
+        let report = &reports[&None];
+        assert_eq!(report.buy_count, 1);
+        assert_eq!(report.leave_base_quantity, dec("2"));
+    }
+
+    #[tokio::test]
+    async fn test_render() {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("50"),
+                dec("0.3996"),
+                dec("20"),
+                Timestamp(0),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("200"),
+                dec("0.3996"),
+                dec("79.84008"),
+                Timestamp(60_000),
+            ),
+        ];
+
+        let summary = [
+            "buys          1",
+            "sells         1",
+            "prices        50 - 200",
+            "volume base   0.7992",
+            "volume quote  99.84008",
+            "buy base      0.3996",
+            "buy quote     20",
+            "sell base     0.3996",
+            "sell quote    79.84008",
+            "max quote     79.84008",
+            "max base      0.3996",
+            "avg quote     49.92004",
+            "leave base    0",
+            "leave quote   59.84008",
+            "costs         0.09992",
+            "cost ratio    0.0010008",
+            "avg cost      0.04996",
+            "avg hold ms   60000",
+            "max hold ms   60000",
+            "in market ms  60000",
+            "trades/hour   120",
+            "max gap ms    60000",
+        ];
+        let rendered = trades.evaluate().await.render(&trades);
+        assert_eq!(
+            rendered,
+            format!(
+                "{}\n\n{}",
+                crate::trade::render_table(&trades),
+                summary.join("\n")
+            )
+        );
+
+        let rendered = Evaluate::default().render(&[]);
+        assert!(rendered.contains("\nprices        -\n"));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_scaled() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+        ];
+        let factor = dec("100");
+
+        let original = trades.evaluate().await;
+        let scaled = crate::trade::scale_all(&trades, factor).evaluate().await;
+
+        assert_eq!(
+            scaled,
+            Evaluate {
+                volume_base_quantity: original.volume_base_quantity * factor,
+                volume_quote_quantity: original.volume_quote_quantity * factor,
+                leave_base_quantity: original.leave_base_quantity * factor,
+                leave_quote_quantity: original.leave_quote_quantity * factor,
+                costs: original.costs * factor,
+                buy_base_volume: original.buy_base_volume * factor,
+                buy_quote_volume: original.buy_quote_volume * factor,
+                sell_base_volume: original.sell_base_volume * factor,
+                sell_quote_volume: original.sell_quote_volume * factor,
+                max_single_quote: original.max_single_quote * factor,
+                max_single_base: original.max_single_base * factor,
+                avg_quote_per_trade: original.avg_quote_per_trade.map(|avg| avg * factor),
+                rebates: original.rebates * factor,
+                max_drawdown: original.max_drawdown * factor,
+                gross_profit: original.gross_profit * factor,
+                gross_loss: original.gross_loss * factor,
+                ..original
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_try_evaluate_overflow() {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("1"),
+                Decimal::MAX,
+                Decimal::MAX,
+                Timestamp(0),
+            ),
+            Trade::new(
+                TradeSide::Buy,
+                dec("1"),
+                Decimal::MAX,
+                Decimal::MAX,
+                Timestamp(0),
+            ),
+        ];
+        assert_eq!(trades.try_evaluate().await, Err(ArithmeticError::Overflow));
+
+        let trades = vec![Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0"))];
+        assert_eq!(trades.try_evaluate().await, Ok(trades.evaluate().await));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_drawdown() {
+        // Buying high, averaging down and selling low
+        let trades = vec![
+            Trade::with_buy(dec("100"), dec("1"), dec("100")),
+            Trade::with_buy(dec("50"), dec("1"), dec("50")),
+            Trade::with_sell(dec("40"), dec("2"), dec("80")),
+        ];
+
+        let report = trades.evaluate().await;
+        assert_eq!(report.leave_quote_quantity, dec("-70"));
+        assert_eq!(report.max_drawdown, dec("70"));
+        assert_eq!(report.max_drawdown_pct.round_dp(4), dec("0.4667"));
+
+        // A recovery does not undo the deepest drawdown
+        let mut trades = trades;
+        trades.push(Trade::with_buy(dec("40"), dec("2"), dec("80")));
+        trades.push(Trade::with_sell(dec("200"), dec("2"), dec("400")));
+        let report = trades.evaluate().await;
+        assert_eq!(report.leave_quote_quantity, dec("250"));
+        assert_eq!(report.max_drawdown, dec("70"));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_round_trips() {
+        let trades = vec![
+            Trade::with_buy(dec("100"), dec("0.999"), dec("100")),
+            Trade::with_sell(dec("110"), dec("0.999"), dec("109.78011")),
+            // Sold above the buy price, but the commissions turn it into a loss
+            Trade::with_buy(dec("100"), dec("0.999"), dec("100")),
+            Trade::with_sell(dec("100.05"), dec("0.999"), dec("99.84995005")),
+            Trade::with_buy(dec("90"), dec("0.999"), dec("89.91")),
+            Trade::with_sell(dec("95"), dec("0.999"), dec("94.810095")),
+            // Still open, neither a win nor a loss
+            Trade::with_buy(dec("95"), dec("0.999"), dec("94.905")),
+        ];
+
+        let report = trades.evaluate().await;
+        assert_eq!(report.round_trips, 3);
+        assert_eq!(report.winning_round_trips, 2);
+        assert_eq!(report.win_rate, Some(dec("2") / dec("3")));
+
+        // One sell closing two buys is two round trips
+        let trades = vec![
+            Trade::with_buy(dec("100"), dec("1"), dec("100")),
+            Trade::with_buy(dec("120"), dec("1"), dec("120")),
+            Trade::with_sell(dec("110"), dec("2"), dec("220")),
+        ];
+
+        let report = trades.evaluate().await;
+        assert_eq!(report.round_trips, 2);
+        assert_eq!(report.winning_round_trips, 1);
+        assert_eq!(report.win_rate, Some(dec("0.5")));
+
+        let trades = vec![Trade::with_sell(dec("110"), dec("2"), dec("220"))];
+        assert_eq!(trades.evaluate().await.win_rate, None);
+    }
+
+    #[tokio::test]
+    async fn test_net_profit_at() {
+        let trades = vec![
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
+        ];
+        let report = trades.evaluate().await;
+
+        // Buying back the 5 short base at 210 costs 1050 of the 2788.75
+        assert_eq!(report.net_profit_at(&dec("210")), dec("1738.75"));
+        assert_eq!(report.net_profit_at(&dec("0")), dec("2788.75"));
+        assert_eq!(report.net_profit_at(&dec("600")), dec("-211.25"));
+
+        assert_eq!(
+            report.roi_at(&dec("210"), &dec("1070")),
+            Some(dec("1738.75") / dec("1070"))
+        );
+        assert_eq!(report.roi_at(&dec("210"), &dec("0")), None);
+    }
+
+    #[tokio::test]
+    async fn test_break_even_price() {
+        let trades = vec![Trade::with_buy(dec("50"), dec("0.3996"), dec("20"))];
+        let report = trades.evaluate().await;
+
+        // The commission lifts it above the buy price
+        let price = report.break_even_price.unwrap();
+        assert_eq!(price, dec("20") / dec("0.3996"));
+        assert_eq!(price.round_dp(4), dec("50.0501"));
+        assert_eq!(report.net_profit_at(&price).round_dp(20), dec("0"));
+
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.84008")),
+        ];
+        assert_eq!(trades.evaluate().await.break_even_price, None);
+
+        let trades = vec![Trade::with_sell(dec("210"), dec("5"), dec("1050"))];
+        assert_eq!(trades.evaluate().await.break_even_price, None);
+    }
+
+    #[tokio::test]
+    async fn test_merge() {
+        let a = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+        ];
+        let b = vec![
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::buy_with_fee(dec("90"), dec("1"), dec("90"), dec("0.1"), None),
+        ];
+        let all: Vec<Trade> = a.iter().chain(b.iter()).cloned().collect();
+
+        let merged = a.evaluate().await.merge(&b.evaluate().await);
+        let whole = all.evaluate().await;
+        assert_eq!(merged.volume_base_quantity, whole.volume_base_quantity);
+        assert_eq!(merged.volume_quote_quantity, whole.volume_quote_quantity);
+        assert_eq!(merged.leave_base_quantity, whole.leave_base_quantity);
+        assert_eq!(merged.leave_quote_quantity, whole.leave_quote_quantity);
+        assert_eq!(merged.buy_count, whole.buy_count);
+        assert_eq!(merged.sell_count, whole.sell_count);
+        assert_eq!(merged.max_price, whole.max_price);
+        assert_eq!(merged.min_price, whole.min_price);
+        assert_eq!(merged.costs, whole.costs);
+        assert_eq!(merged.fees, whole.fees);
+        assert_eq!(merged.break_even_price, whole.break_even_price);
+
+        // The empty report is neutral, its missing prices never win
+        let report = a.evaluate().await;
+        assert_eq!(report.merge(&Evaluate::default()), report);
+        assert_eq!(Evaluate::default().merge(&report), report);
+        assert_eq!(report.clone() + Evaluate::default(), report);
+
+        let reports = vec![a.evaluate().await, b.evaluate().await];
+        assert_eq!(reports.iter().sum::<Evaluate>(), merged);
+        assert_eq!(reports.into_iter().sum::<Evaluate>(), merged);
+        assert_eq!(
+            Vec::<Evaluate>::new().into_iter().sum::<Evaluate>(),
+            Evaluate::default()
+        );
+    }
+
+    #[test]
+    fn test_from_trades() {
+        let trades = vec![
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
+        ];
+
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.leave_base_quantity, dec("-5"));
+        assert_eq!(report.leave_quote_quantity, dec("2788.75"));
+        assert_eq!(report.buy_count, 1);
+        assert_eq!(report.sell_count, 2);
+        assert_eq!(Evaluate::from_trades(&[]), Evaluate::default());
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_slice() {
+        let trades = vec![
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
+        ];
+
+        let slice: &[Trade] = &trades[1..];
+        assert_eq!(slice.evaluate().await, Evaluate::from_trades(&trades[1..]));
+        assert_eq!(trades.evaluate().await, Evaluate::from_trades(&trades));
+    }
+
+    #[test]
+    fn test_update() {
+        // A fixed linear congruential sequence stands in for random trades
+        let mut seed: u64 = 20240601;
+        let mut next = |modulo: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % modulo
+        };
+
+        let mut trades = Vec::new();
+        for _ in 0..200 {
+            let price = Decimal::from(50 + next(100));
+            let base = Decimal::new(1 + next(5000) as i64, 3);
+            let fee = Decimal::ONE - Decimal::new(next(3) as i64, 3);
+            let mut trade = match next(2) {
+                0 => Trade::new(
+                    TradeSide::Buy,
+                    price,
+                    base * fee,
+                    base * price,
+                    Timestamp(0),
+                ),
+                _ => Trade::new(
+                    TradeSide::Sell,
+                    price,
+                    base,
+                    base * price * fee,
+                    Timestamp(0),
+                ),
+            };
+            if next(4) == 0 {
+                trade.requested_price = Some(price - Decimal::ONE);
+            }
+            trades.push(trade);
+        }
+
+        let batch = Evaluate::from_trades(&trades);
+        let mut incremental = Evaluate::default();
+        for trade in trades.iter() {
+            incremental.update(trade);
+        }
+        assert_eq!(incremental, batch);
+        assert_eq!(format!("{:?}", incremental), format!("{:?}", batch));
+
+        let folded = trades.iter().fold(Evaluate::default(), Evaluate::updated);
+        assert_eq!(folded, batch);
+        assert!(batch.round_trips > 0);
+        assert!(batch.avg_slippage.is_some());
+    }
+
+    #[test]
+    fn test_update_carried() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("2"), dec("100")),
+            Trade::with_buy(dec("40"), dec("1"), dec("40")),
+            Trade::with_sell(dec("60"), dec("2.5"), dec("150")),
+            Trade::with_sell(dec("45"), dec("0.5"), dec("22.5")),
+        ];
+        let (head, tail) = trades.split_at(2);
+        let whole = Evaluate::from_trades(&trades);
+
+        // A read back report continues from its open lots and peaks
+        let json = serde_json::to_string(&Evaluate::from_trades(head)).unwrap();
+        let mut report: Evaluate = serde_json::from_str(&json).unwrap();
+        tail.iter().for_each(|trade| report.update(trade));
+        assert_eq!(report, whole);
+        assert_eq!(report.running, whole.running);
+
+        let stripped: Evaluate =
+            serde_json::from_str(&json.replace("\"running\"", "\"ignored\"")).unwrap();
+        assert!(!stripped.is_updatable());
+
+        // Merging with an empty report keeps them, merging two with trades cannot
+        let report = Evaluate::from_trades(head);
+        assert!(report.merge(&Evaluate::default()).is_updatable());
+        assert!(Evaluate::default().merge(&report).is_updatable());
+        assert!(!report.merge(&Evaluate::from_trades(tail)).is_updatable());
+        assert!(Evaluate::default()
+            .merge(&Evaluate::default())
+            .is_updatable());
+    }
+
+    #[test]
+    #[should_panic(expected = "no running totals")]
+    fn test_update_merged() {
+        let trades = [
+            Trade::with_buy(dec("50"), dec("2"), dec("100")),
+            Trade::with_sell(dec("60"), dec("2"), dec("120")),
+        ];
+        let mut report =
+            Evaluate::from_trades(&trades[..1]).merge(&Evaluate::from_trades(&trades[1..]));
+        report.update(&trades[0]);
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_window() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
+            Trade::with_buy(dec("50"), dec("1"), dec("50")),
+        ];
+        let trades = stamped(trades);
+
+        let expected = Evaluate {
+            volume_base_quantity: dec("31.750"),
+            volume_quote_quantity: dec("4928.75"),
+            leave_base_quantity: dec("-5"),
+            leave_quote_quantity: dec("2788.75"),
+            buy_count: 1,
+            sell_count: 2,
+            max_price: Some(dec("210")),
+            min_price: Some(dec("80")),
+            costs: dec("0"),
+            buy_base_volume: dec("13.375"),
+            buy_quote_volume: dec("1070"),
+            sell_base_volume: dec("18.375"),
+            sell_quote_volume: dec("3858.75"),
+            max_single_quote: dec("2808.75"),
+            max_single_base: dec("13.375"),
+            avg_quote_per_trade: Some(dec("4928.75") / dec("3")),
+            avg_buy_price: Some(dec("80")),
+            avg_sell_price: Some(dec("210")),
+            round_trips: 1,
+            winning_round_trips: 1,
+            win_rate: Some(dec("1")),
+            avg_holding_millis: Some(0),
+            gross_profit: dec("1738.75"),
+            ..span(&trades[1..4])
+        };
+
+        let window = &trades[1..4];
+        assert_eq!(window.evaluate().await, expected);
+        assert_eq!(trades[1..4].evaluate().await, expected);
+        assert_eq!(evaluate_iter(window), expected);
+        assert_eq!(evaluate_iter(trades.iter().skip(1).take(3)), expected);
+        assert_eq!(evaluate_iter(&trades), trades.evaluate().await);
+    }
+
+    #[test]
+    fn test_bucketed() {
+        const DAY: u64 = 86_400_000;
+        let trade = |side, price: &str, base: &str, quote: &str, ts| {
+            Trade::new(side, dec(price), dec(base), dec(quote), Timestamp(ts))
+        };
+        let trades = vec![
+            trade(TradeSide::Buy, "50", "0.3996", "20", 0),
+            trade(TradeSide::Sell, "200", "0.3996", "79.84008", 3_600_000),
+            trade(TradeSide::Buy, "50", "0.3996", "20", DAY + 1),
+            trade(TradeSide::Sell, "200", "0.3996", "79.84008", 2 * DAY - 1),
+        ];
+
+        let buckets = bucketed(&trades, DAY);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].0, Timestamp(0)..Timestamp(DAY));
+        assert_eq!(buckets[1].0, Timestamp(DAY)..Timestamp(2 * DAY));
+        assert_eq!(buckets[0].1.buy_count, 1);
+        assert_eq!(buckets[1].1.sell_count, 1);
+
+        let merged = buckets[0].1.merge(&buckets[1].1);
+        assert_eq!(merged, Evaluate::from_trades(&trades));
+
+        // A gap of empty days is skipped
+        let buckets = bucketed(&[trades[0].clone(), trades[3].clone()], DAY / 24);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[1].0.start, Timestamp(2 * DAY - DAY / 24));
+
+        assert!(bucketed(&trades, 0).is_empty());
+    }
+
+    #[test]
+    fn test_cost_ratio() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+        ];
+        let report = Evaluate::from_trades(&trades);
+
+        // 0.1% of each side, measured against the quote both sides traded
+        let ratio = report.cost_ratio().unwrap();
+        assert_eq!(ratio, dec("0.0999200") / dec("99.8400800"));
+        assert_eq!(ratio.round_dp(4), dec("0.0010"));
+        assert_eq!(report.avg_cost_per_trade(), Some(dec("0.04996")));
+
+        // A rebate received more base than the price implies
+        let trades = vec![Trade::with_buy(dec("10"), dec("5.04"), dec("50"))];
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.cost_ratio(), Some(dec("-0.008")));
+        assert_eq!(report.avg_cost_per_trade(), Some(dec("-0.4")));
+
+        assert_eq!(Evaluate::default().cost_ratio(), None);
+        assert_eq!(Evaluate::default().avg_cost_per_trade(), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Sell,
+                dec("210"),
+                dec("5"),
+                dec("1050"),
+                Timestamp(0),
+            ),
+            Trade::new(
+                TradeSide::Buy,
+                dec("80"),
+                dec("13.375"),
+                dec("1070"),
+                Timestamp(1000),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("210"),
+                dec("13.375"),
+                dec("2808.75"),
+                Timestamp(4000),
+            ),
+        ];
+        let report = Evaluate::from_trades(&trades);
+
+        let expected = [
+            "buys          1",
+            "sells         2",
+            "prices        80 - 210",
+            "volume base   31.75",
+            "volume quote  4928.75",
+            "buy base      13.375",
+            "buy quote     1070",
+            "sell base     18.375",
+            "sell quote    3858.75",
+            "max quote     2808.75",
+            "max base      13.375",
+            "avg quote     1642.91666666",
+            "leave base    -5",
+            "leave quote   2788.75",
+            "costs         0",
+            "cost ratio    0",
+            "avg cost      0",
+            "avg hold ms   3000",
+            "max hold ms   3000",
+            "in market ms  4000",
+            "trades/hour   2700",
+            "max gap ms    3000",
+        ];
+        assert_eq!(report.to_string(), expected.join("\n"));
+        assert_eq!(report.display_with_scale(8), report.to_string());
+
+        let expected = [
+            "buys          1",
+            "sells         1",
+            "prices        50 - 200",
+            "volume base   0.79",
+            "volume quote  99.84",
+            "buy base      0.39",
+            "buy quote     20",
+            "sell base     0.39",
+            "sell quote    79.84",
+            "max quote     79.84",
+            "max base      0.39",
+            "avg quote     49.92",
+            "leave base    0",
+            "leave quote   59.84",
+            "costs         0.09",
+            "cost ratio    0",
+            "avg cost      0.04",
+            "avg hold ms   0",
+            "max hold ms   0",
+            "in market ms  0",
+            "trades/hour   -",
+            "max gap ms    0",
+        ];
+        let trades = stamped(vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+        ]);
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.display_with_scale(2), expected.join("\n"));
+
+        let empty = Evaluate::default().to_string();
+        assert!(empty.contains("prices        -\n"));
+        assert!(empty.ends_with(
+            "avg hold ms   -\nmax hold ms   0\nin market ms  0\ntrades/hour   -\nmax gap ms    -"
+        ));
+    }
+
+    #[test]
+    fn test_report_at() {
+        let trades = vec![
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
+        ];
+        let report = Evaluate::from_trades(&trades).report_at(&dec("210"));
+
+        assert!(report.starts_with("buys          1\n"));
+        assert!(report.ends_with("\nmark price    210\nnet profit    1738.75"));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_empty() {
+        let trades: Vec<Trade> = Vec::new();
+        let report = trades.evaluate().await;
+        assert_eq!(report, Evaluate::default());
+        assert_eq!(report.max_price, None);
+        assert_eq!(report.min_price, None);
+        assert_eq!(report.max_price_or_zero(), dec("0"));
+        assert_eq!(report.min_price_or_zero(), dec("0"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["max_price"], serde_json::Value::Null);
+        assert_eq!(json["min_price"], serde_json::Value::Null);
+        let back: Evaluate = serde_json::from_value(json).unwrap();
+        assert_eq!(back, report);
+
+        // Merging the empty report with a real one keeps the real prices
+        let trades = vec![Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0"))];
+        let merged = report.merge(&Evaluate::from_trades(&trades));
+        assert_eq!(merged.min_price, Some(dec("50")));
+        assert_eq!(merged.max_price, Some(dec("50")));
+        let json = serde_json::to_value(&merged).unwrap();
+        assert_eq!(json["min_price"], serde_json::json!("50"));
+    }
+
+    #[test]
+    fn test_annualized_roi() {
+        const DAY: u64 = 86_400_000;
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("100"),
+                dec("1"),
+                dec("100"),
+                Timestamp(DAY),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("200"),
+                dec("1"),
+                dec("200"),
+                Timestamp(366 * DAY),
+            ),
+        ];
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.first_timestamp, Some(Timestamp(DAY)));
+        assert_eq!(report.last_timestamp, Some(Timestamp(366 * DAY)));
+
+        // Doubling the money over a year is 100% a year, over half a year 200%
+        assert_eq!(report.roi_at(&dec("200"), &dec("100")), Some(dec("1")));
+        assert_eq!(
+            report.annualized_roi_at(&dec("200"), &dec("100")),
+            Some(dec("1"))
+        );
+
+        let mut trades = trades;
+        trades[1].timestamp = Timestamp(DAY + 365 * DAY / 2);
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(
+            report.annualized_roi_at(&dec("200"), &dec("100")),
+            Some(dec("2"))
+        );
+        assert_eq!(report.annualized_roi_at(&dec("200"), &dec("0")), None);
+
+        // Trades built in the same millisecond have no duration to scale by
+        trades[1].timestamp = trades[0].timestamp;
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.annualized_roi_at(&dec("200"), &dec("100")), None);
+        assert_eq!(
+            Evaluate::default().annualized_roi_at(&dec("200"), &dec("100")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_equity_curve() {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("50"),
+                dec("0.3996"),
+                dec("20"),
+                Timestamp(1),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("200"),
+                dec("0.3996"),
+                dec("79.84008"),
+                Timestamp(2),
+            ),
+            Trade::new(
+                TradeSide::Buy,
+                dec("50"),
+                dec("9.99"),
+                dec("500"),
+                Timestamp(3),
+            ),
+        ];
+
+        let point = |ts, base: &str, quote: &str, equity: &str| EquityPoint {
+            timestamp: Timestamp(ts),
+            base: dec(base),
+            quote: dec(quote),
+            equity_in_quote: dec(equity),
+        };
+        let expected = vec![
+            point(1, "0.3996", "980", "999.98"),
+            point(2, "0", "1059.84008", "1059.84008"),
+            point(3, "9.99", "559.84008", "1059.34008"),
+        ];
+        let curve = equity_curve(&trades, dec("0"), dec("1000"));
+        assert_eq!(curve, expected);
+
+        // Unsorted input gives the same curve
+        let shuffled = vec![trades[2].clone(), trades[0].clone(), trades[1].clone()];
+        assert_eq!(equity_curve(&shuffled, dec("0"), dec("1000")), expected);
+
+        let series: Vec<Decimal> = curve.iter().map(|point| point.equity_in_quote).collect();
+        // The commission of the last buy, relative to the peak
+        let drawdown = dec("0.5") / dec("1059.84008");
+        assert_eq!(crate::math::max_drawdown(&series), Some((drawdown, 1, 2)));
+
+        assert!(equity_curve(&[], dec("1"), dec("1000")).is_empty());
+    }
+
+    #[test]
+    fn test_profit_factor() {
+        let trades = vec![
+            Trade::with_buy(dec("100"), dec("1"), dec("100")),
+            Trade::with_sell(dec("110"), dec("1"), dec("110")),
+            Trade::with_buy(dec("100"), dec("1"), dec("100")),
+            Trade::with_sell(dec("95"), dec("1"), dec("95")),
+        ];
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.gross_profit, dec("10"));
+        assert_eq!(report.gross_loss, dec("5"));
+        assert_eq!(report.profit_factor, Some(dec("2")));
+
+        let halves =
+            Evaluate::from_trades(&trades[..2]).merge(&Evaluate::from_trades(&trades[2..]));
+        assert_eq!(halves.profit_factor, Some(dec("2")));
+
+        // Only winners, the factor is left undefined
+        let report = Evaluate::from_trades(&trades[..2]);
+        assert_eq!(report.gross_profit, dec("10"));
+        assert_eq!(report.profit_factor, None);
+    }
+
+    #[test]
+    fn test_holding_times() {
+        let trade = |side, price: &str, base: &str, ts| {
+            let (base, price) = (dec(base), dec(price));
+            Trade::new(side, price, base, base * price, Timestamp(ts))
+        };
+        let trades = vec![
+            trade(TradeSide::Buy, "100", "1", 1_000),
+            trade(TradeSide::Buy, "90", "1", 3_000),
+            // Closes the first buy and half of the second
+            trade(TradeSide::Sell, "110", "1.5", 11_000),
+            // Flat from here until the next buy
+            trade(TradeSide::Sell, "100", "0.5", 12_000),
+            trade(TradeSide::Buy, "95", "1", 20_000),
+            trade(TradeSide::Sell, "96", "1", 20_500),
+        ];
+
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.round_trips, 4);
+        // 10000, 8000, 9000 and 500 milliseconds
+        assert_eq!(report.max_holding_millis, 10_000);
+        assert_eq!(report.avg_holding_millis, Some(6_875));
+        assert_eq!(report.time_in_market_millis, 11_000 + 500);
+
+        // Trades of one millisecond, or out of order, hold for nothing rather than underflow
+        let trades = vec![
+            trade(TradeSide::Buy, "100", "1", 5_000),
+            trade(TradeSide::Sell, "110", "1", 5_000),
+            trade(TradeSide::Buy, "100", "1", 5_000),
+            trade(TradeSide::Sell, "110", "1", 4_000),
+        ];
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.max_holding_millis, 0);
+        assert_eq!(report.avg_holding_millis, Some(0));
+        assert_eq!(report.time_in_market_millis, 0);
+
+        assert_eq!(Evaluate::default().avg_holding_millis, None);
+    }
+
+    #[test]
+    fn test_duration() {
+        let trade = |side, ts| Trade::new(side, dec("100"), dec("1"), dec("100"), Timestamp(ts));
+        let trades = vec![
+            trade(TradeSide::Buy, 5_000),
+            trade(TradeSide::Sell, 2_000),
+            trade(TradeSide::Buy, 9_000),
+            trade(TradeSide::Sell, 7_000),
+        ];
+
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.first_timestamp, Some(Timestamp(2_000)));
+        assert_eq!(report.last_timestamp, Some(Timestamp(9_000)));
+        assert_eq!(report.duration_millis(), Some(7_000));
+
+        let merged =
+            Evaluate::from_trades(&trades[2..]).merge(&Evaluate::from_trades(&trades[..2]));
+        assert_eq!(merged.first_timestamp, Some(Timestamp(2_000)));
+        assert_eq!(merged.last_timestamp, Some(Timestamp(9_000)));
+
+        let single = Evaluate::from_trades(&trades[..1]);
+        assert_eq!(single.first_timestamp, Some(Timestamp(5_000)));
+        assert_eq!(single.last_timestamp, Some(Timestamp(5_000)));
+        assert_eq!(single.duration_millis(), Some(0));
+
+        assert_eq!(Evaluate::default().duration_millis(), None);
+    }
+
+    #[test]
+    fn test_trading_frequency() {
+        const MINUTE: u64 = 60_000;
+        let trade = |side, ts| Trade::new(side, dec("100"), dec("1"), dec("100"), Timestamp(ts));
+        let trades = vec![
+            trade(TradeSide::Buy, 0),
+            trade(TradeSide::Sell, 30 * MINUTE),
+            trade(TradeSide::Buy, 45 * MINUTE),
+            trade(TradeSide::Sell, 120 * MINUTE),
+        ];
+
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.duration_millis(), Some(120 * MINUTE));
+        assert_eq!(report.trades_per_hour(), Some(dec("2")));
+        assert_eq!(report.max_gap_millis, Some(75 * MINUTE));
+
+        let merged =
+            Evaluate::from_trades(&trades[..2]).merge(&Evaluate::from_trades(&trades[2..]));
+        assert_eq!(merged.trades_per_hour(), Some(dec("2")));
+        assert_eq!(merged.max_gap_millis, Some(75 * MINUTE));
+
+        let single = Evaluate::from_trades(&trades[..1]);
+        assert_eq!(single.trades_per_hour(), None);
+        assert_eq!(single.max_gap_millis, None);
+    }
+
+    #[test]
+    fn test_sharpe_like() {
+        let round_trip = |sell: &str| {
+            vec![
+                Trade::new(
+                    TradeSide::Buy,
+                    dec("100"),
+                    dec("1"),
+                    dec("100"),
+                    Timestamp(0),
+                ),
+                Trade::new(
+                    TradeSide::Sell,
+                    dec(sell),
+                    dec("1"),
+                    dec(sell),
+                    Timestamp(0),
+                ),
+            ]
+        };
+
+        // Returns of 0.1 every time have no variance
+        let constant = [round_trip("110"), round_trip("110")].concat();
+        assert_eq!(sharpe_like(&constant, &dec("100")), None);
+
+        // Returns of 0.3 and 0.1, a mean of 0.2 over a deviation of 0.1
+        let mixed = [round_trip("130"), round_trip("110")].concat();
+        assert_eq!(
+            sharpe_like(&mixed, &dec("100")).map(|v| v.round_dp(10)),
+            Some(dec("2"))
+        );
+        assert_eq!(sharpe_like(&mixed, &dec("0")), None);
+
+        assert_eq!(sharpe_like(&round_trip("130"), &dec("100")), None);
+        assert_eq!(sharpe_like(&[], &dec("100")), None);
+
+        // Returns whose squared deviations overflow a Decimal
+        let large = vec![
+            Trade::with_buy(dec("1"), dec("1"), dec("1")),
+            Trade::with_sell(dec("1"), dec("1"), dec("1")),
+            Trade::with_buy(dec("1"), dec("1"), dec("1")),
+            Trade::with_sell(dec("1000000000000001"), dec("1"), dec("1000000000000001")),
+        ];
+        assert_eq!(sharpe_like(&large, &dec("1")), Some(dec("1")));
+    }
+
+    #[test]
+    fn test_evaluate_side_volumes() {
+        let trades = vec![
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
+        ];
+        let report = Evaluate::from_trades(&trades);
+
+        assert_eq!(report.buy_base_volume, dec("13.375"));
+        assert_eq!(report.buy_quote_volume, dec("1070"));
+        assert_eq!(report.sell_base_volume, dec("18.375"));
+        assert_eq!(report.sell_quote_volume, dec("3858.75"));
+        assert_eq!(
+            report.buy_base_volume + report.sell_base_volume,
+            report.volume_base_quantity
+        );
+        assert_eq!(
+            report.buy_quote_volume + report.sell_quote_volume,
+            report.volume_quote_quantity
+        );
+
+        // A position whose selling range was never reached only ever bought
+        let report = Evaluate::from_trades(&trades[1..2]);
+        assert_eq!(report.buy_base_volume, report.volume_base_quantity);
+        assert!(report.sell_base_volume.is_zero());
+        assert!(report.sell_quote_volume.is_zero());
+
+        let merged =
+            Evaluate::from_trades(&trades[..1]).merge(&Evaluate::from_trades(&trades[1..]));
+        assert_eq!(merged.sell_quote_volume, dec("3858.75"));
+    }
+
+    #[test]
+    fn test_evaluate_trade_sizes() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20")),
+            Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+            Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75")),
+        ];
+
+        let report = Evaluate::from_trades(&trades);
+        assert_eq!(report.max_single_quote, dec("2808.75"));
+        assert_eq!(report.max_single_base, dec("13.375"));
+        assert_eq!(report.avg_quote_per_trade, Some(dec("3898.75") / dec("3")));
+
+        let merged =
+            Evaluate::from_trades(&trades[..2]).merge(&Evaluate::from_trades(&trades[2..]));
+        assert_eq!(merged.max_single_quote, dec("2808.75"));
+        assert_eq!(merged.avg_quote_per_trade, report.avg_quote_per_trade);
+
+        assert_eq!(Evaluate::default().avg_quote_per_trade, None);
+    }
 }
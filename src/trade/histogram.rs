@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::math::Range;
+use crate::types::{BaseQuantity, Decimal, Price, QuoteQuantity};
+
+use super::Trade;
+
+// The most bins `include_empty` fills in between the lowest and highest traded price
+pub const MAX_BINS: u64 = 100_000;
+
+// Traded volume per price bucket of width `bucket`, anchored at the lowest traded price.
+// Each bin covers [min, max) of its range. Empty bins are dropped unless `include_empty`.
+pub fn histogram(
+    trades: &[Trade],
+    bucket: Decimal,
+    include_empty: bool,
+) -> Result<Vec<(Range<Price>, BaseQuantity, QuoteQuantity)>, HistogramError> {
+    if bucket <= Decimal::ZERO {
+        return Ok(Vec::new());
+    }
+
+    let anchor = match trades.iter().map(|trade| trade.price).min() {
+        Some(price) => price,
+        None => return Ok(Vec::new()),
+    };
+
+    // Only the bins something traded in, the bucket may be far narrower than the prices
+    let mut bins: BTreeMap<u64, (BaseQuantity, QuoteQuantity)> = BTreeMap::new();
+    for trade in trades.iter() {
+        let index = (trade.price - anchor)
+            .checked_div(bucket)
+            .and_then(|index| index.floor().to_u64())
+            .ok_or(HistogramError::BucketTooSmall(bucket))?;
+
+        let bin = bins.entry(index).or_insert((Decimal::ZERO, Decimal::ZERO));
+        bin.0 += trade.base_quantity;
+        bin.1 += trade.quote_quantity;
+    }
+
+    if include_empty {
+        let last = bins.keys().next_back().copied().unwrap_or(0);
+        if last >= MAX_BINS {
+            return Err(HistogramError::TooManyBins(MAX_BINS));
+        }
+
+        for index in 0..last {
+            bins.entry(index).or_insert((Decimal::ZERO, Decimal::ZERO));
+        }
+    }
+
+    Ok(bins
+        .into_iter()
+        .map(|(index, (base, quote))| {
+            let min = anchor + bucket * Decimal::from(index);
+            (Range(min, min + bucket), base, quote)
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistogramError {
+    // So narrow that a bin index no longer fits
+    BucketTooSmall(Decimal),
+    TooManyBins(u64),
+}
+
+impl fmt::Display for HistogramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BucketTooSmall(v) => write!(f, "bucket {} is too small for the prices", v),
+            Self::TooManyBins(v) => write!(f, "more than {} bins", v),
+        }
+    }
+}
+
+impl std::error::Error for HistogramError {}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_histogram() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20")),
+            Trade::with_buy(dec("52"), dec("0.999"), dec("52")),
+            Trade::with_sell(dec("80"), dec("0.5"), dec("39.96")),
+        ];
+
+        let bins = histogram(&trades, dec("10"), false).unwrap();
+        assert_eq!(
+            bins,
+            vec![
+                (Range(dec("50"), dec("60")), dec("1.3986"), dec("72")),
+                (Range(dec("80"), dec("90")), dec("0.5"), dec("39.96")),
+            ]
+        );
+
+        let bins = histogram(&trades, dec("10"), true).unwrap();
+        assert_eq!(bins.len(), 4);
+        assert_eq!(bins[1], (Range(dec("60"), dec("70")), dec("0"), dec("0")));
+        assert_eq!(bins[3].0, Range(dec("80"), dec("90")));
+
+        assert!(histogram(&trades, dec("0"), true).unwrap().is_empty());
+        assert!(histogram(&[], dec("10"), true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_histogram_tiny_bucket() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.4"), dec("20")),
+            Trade::with_sell(dec("80"), dec("0.5"), dec("40")),
+        ];
+
+        // 3e11 bins apart, only the two with volume are kept
+        let bins = histogram(&trades, dec("0.0000000001"), false).unwrap();
+        assert_eq!(bins.len(), 2);
+        assert_eq!(
+            bins[1],
+            (
+                Range(dec("80"), dec("80.0000000001")),
+                dec("0.5"),
+                dec("40")
+            )
+        );
+
+        assert_eq!(
+            histogram(&trades, dec("0.0000000001"), true),
+            Err(HistogramError::TooManyBins(MAX_BINS))
+        );
+
+        // The bin index overflows a Decimal, then a u64
+        for bucket in ["0.0000000000000000000000000001", "0.0000000000000000001"] {
+            assert_eq!(
+                histogram(&trades, dec(bucket), false)
+                    .unwrap_err()
+                    .to_string(),
+                format!("bucket {} is too small for the prices", bucket)
+            );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// One JSON object per line, appended and flushed per trade so a crash loses at most one line
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use super::Trade;
+
+pub struct TradeWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TradeWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn append(&mut self, trade: &Trade) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, trade)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// A last line without its newline that fails to parse was cut off mid-write, it is
+// skipped and reported as `Truncated` along with everything read before it. A complete
+// line that fails to parse is `Invalid`, even the last one.
+pub fn read_all<R: BufRead>(mut reader: R) -> Result<Vec<Trade>, JsonlError> {
+    let mut trades = Vec::new();
+    let mut line = Vec::new();
+    let mut number = 0;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line);
+        if read.map_err(JsonlError::Io)? == 0 {
+            break;
+        }
+
+        number += 1;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        match serde_json::from_slice(&line) {
+            Ok(trade) => trades.push(trade),
+            Err(_) if line.last() != Some(&b'\n') => {
+                return Err(JsonlError::Truncated {
+                    line: number,
+                    trades,
+                })
+            }
+            Err(error) => {
+                return Err(JsonlError::Invalid {
+                    line: number,
+                    error: error.to_string(),
+                })
+            }
+        }
+    }
+
+    Ok(trades)
+}
+
+#[derive(Debug)]
+pub enum JsonlError {
+    Io(io::Error),
+    Invalid { line: usize, error: String },
+    Truncated { line: usize, trades: Vec<Trade> },
+}
+
+impl fmt::Display for JsonlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "trade log: {}", e),
+            Self::Invalid { line, error } => write!(f, "trade log line {}: {}", line, error),
+            Self::Truncated { line, trades } => write!(
+                f,
+                "trade log truncated at line {}, {} trades read before it",
+                line,
+                trades.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JsonlError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::trade::Trade;
+    use crate::types::Decimal;
+
+    use super::{read_all, JsonlError, TradeWriter};
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.84008")),
+        ];
+
+        let mut writer = TradeWriter::new(Vec::new());
+        for trade in trades.iter() {
+            writer.append(trade).unwrap();
+        }
+
+        let buffer = writer.into_inner();
+        assert_eq!(buffer.iter().filter(|byte| **byte == b'\n').count(), 2);
+
+        let loaded = read_all(buffer.as_slice()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&trades).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut writer = TradeWriter::new(Vec::new());
+        writer
+            .append(&Trade::with_buy(dec("50"), dec("0.4"), dec("20")))
+            .unwrap();
+
+        let mut buffer = writer.into_inner();
+        buffer.extend_from_slice(br#"{"side":"SELL","pri"#);
+
+        match read_all(buffer.as_slice()) {
+            Err(JsonlError::Truncated { line, trades }) => {
+                assert_eq!(line, 2);
+                assert_eq!(trades.len(), 1);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let text = "{}\n{\"side\":\"BUY\"}\n";
+        assert!(matches!(
+            read_all(text.as_bytes()),
+            Err(JsonlError::Invalid { line: 1, .. })
+        ));
+
+        // A corrupt last line that did end in its newline was written whole
+        buffer.extend_from_slice(b"\n");
+        assert!(matches!(
+            read_all(buffer.as_slice()),
+            Err(JsonlError::Invalid { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::math::Range;
+use crate::types::Timestamp;
+
+use super::evaluate::{Evaluate, Evaluater};
+use super::{Trade, TradeSide};
+
+// Trades kept sorted by timestamp, trades sharing a timestamp keep their arrival order
+#[derive(Debug, Clone, Default)]
+pub struct TradeLog {
+    trades: Vec<Trade>,
+}
+
+impl TradeLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn append(&mut self, trade: Trade) {
+        let index = self
+            .trades
+            .partition_point(|other| other.timestamp <= trade.timestamp);
+        self.trades.insert(index, trade);
+    }
+
+    // Trades within the inclusive time range
+    pub fn between(&self, range: Range<Timestamp>) -> &[Trade] {
+        let start = self
+            .trades
+            .partition_point(|trade| trade.timestamp < *range.min());
+        let end = self
+            .trades
+            .partition_point(|trade| trade.timestamp <= *range.max());
+
+        &self.trades[start..end]
+    }
+
+    pub fn last_n(&self, n: usize) -> &[Trade] {
+        &self.trades[self.trades.len().saturating_sub(n)..]
+    }
+
+    pub fn by_side(&self, side: TradeSide) -> impl Iterator<Item = &Trade> {
+        self.trades.iter().filter(move |trade| trade.side == side)
+    }
+
+    pub fn trades(&self) -> &[Trade] {
+        &self.trades
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    pub fn into_inner(self) -> Vec<Trade> {
+        self.trades
+    }
+
+    pub async fn evaluate(&self) -> Evaluate {
+        self.trades.evaluate().await
+    }
+}
+
+impl From<Vec<Trade>> for TradeLog {
+    fn from(mut trades: Vec<Trade>) -> Self {
+        trades.sort_by_key(|trade| trade.timestamp);
+        Self { trades }
+    }
+}
+
+// Persisted as a plain list of trades, sorted again when loaded
+impl Serialize for TradeLog {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.trades.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TradeLog {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Trade>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Range;
+    use crate::trade::{Trade, TradeSide};
+    use crate::types::{Decimal, Timestamp};
+
+    use super::TradeLog;
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn trade(side: TradeSide, ts: u64) -> Trade {
+        Trade::new(side, dec("10"), dec("1"), dec("10"), Timestamp(ts))
+    }
+
+    fn timestamps(trades: &[Trade]) -> Vec<u64> {
+        trades
+            .iter()
+            .map(|trade| trade.timestamp.as_millis())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_trade_log() {
+        let mut log = TradeLog::from(vec![trade(TradeSide::Buy, 30), trade(TradeSide::Buy, 10)]);
+        log.append(trade(TradeSide::Sell, 20));
+        log.append(trade(TradeSide::Sell, 40));
+        log.append(trade(TradeSide::Sell, 5));
+
+        assert_eq!(timestamps(log.trades()), vec![5, 10, 20, 30, 40]);
+        assert_eq!(
+            timestamps(log.between(Range(Timestamp(10), Timestamp(30)))),
+            vec![10, 20, 30]
+        );
+        assert!(log.between(Range(Timestamp(31), Timestamp(39))).is_empty());
+        assert_eq!(timestamps(log.last_n(2)), vec![30, 40]);
+        assert_eq!(log.last_n(9).len(), 5);
+        assert_eq!(log.by_side(TradeSide::Buy).count(), 2);
+
+        let report = log.evaluate().await;
+        assert_eq!(report.buy_count, 2);
+        assert_eq!(report.sell_count, 3);
+
+        let json = serde_json::to_string(&log).unwrap();
+        let log: TradeLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(timestamps(log.trades()), vec![5, 10, 20, 30, 40]);
+    }
+
+    #[tokio::test]
+    async fn test_empty_trade_log() {
+        let log = TradeLog::new();
+        assert!(log.is_empty());
+        assert_eq!(log.between(Range(Timestamp(0), Timestamp(9))).len(), 0);
+        assert_eq!(log.last_n(3).len(), 0);
+        assert_eq!(log.by_side(TradeSide::Sell).count(), 0);
+        assert_eq!(log.evaluate().await.buy_count, 0);
+        assert_eq!(serde_json::to_string(&log).unwrap(), "[]");
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{BaseQuantity, Price, QuoteQuantity, Timestamp};
+
+use super::{checked, checked_div, ArithmeticError, Trade, TradeSide};
+
+// What is left of a buy after the sells that followed it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lot {
+    pub price: Price,
+    pub base_quantity: BaseQuantity,
+    pub timestamp: Timestamp,
+}
+
+// Buys still held once sells consumed them first-in-first-out, oldest first.
+// Sells beyond the bought inventory consume nothing further.
+pub fn open_lots(trades: &[Trade]) -> Vec<Lot> {
+    let mut fifo = FifoLots::default();
+    for trade in trades.iter() {
+        fifo.push(trade)
+            .unwrap_or_else(|error| panic!("open lots: {}", error));
+    }
+
+    fifo.lots
+        .into_iter()
+        .map(|lot| Lot {
+            price: lot.price,
+            base_quantity: lot.base_quantity,
+            timestamp: lot.timestamp,
+        })
+        .collect()
+}
+
+// The first-in-first-out matching behind `open_lots`, `pnl::fifo` and `Evaluate`. Buys
+// open lots, sells consume them oldest first, sold base beyond them matches nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct FifoLots {
+    pub(crate) lots: VecDeque<OpenLot>,
+}
+
+// What is left of a buy and the quote that remainder cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct OpenLot {
+    pub(crate) price: Price,
+    pub(crate) base_quantity: BaseQuantity,
+    pub(crate) quote_quantity: QuoteQuantity,
+    pub(crate) timestamp: Timestamp,
+}
+
+// The part of a sell matched against one lot, with its share of either side's quote
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LotMatch {
+    pub(crate) base_quantity: BaseQuantity,
+    pub(crate) cost: QuoteQuantity,
+    pub(crate) proceeds: QuoteQuantity,
+    pub(crate) bought: Timestamp,
+}
+
+impl FifoLots {
+    // Applies one trade, a sell returns the lots it matched in order. Buys without base
+    // open nothing.
+    pub(crate) fn push(&mut self, trade: &Trade) -> Result<Vec<LotMatch>, ArithmeticError> {
+        let (base, quote) = (trade.base_quantity, trade.quote_quantity);
+        let mut matches = Vec::new();
+        match trade.side {
+            TradeSide::Buy => {
+                if base > BaseQuantity::ZERO {
+                    self.lots.push_back(OpenLot {
+                        price: trade.price,
+                        base_quantity: base,
+                        quote_quantity: quote,
+                        timestamp: trade.timestamp,
+                    });
+                }
+            }
+            TradeSide::Sell => {
+                let mut left = base;
+                while left > BaseQuantity::ZERO {
+                    let Some(lot) = self.lots.front_mut() else {
+                        break;
+                    };
+
+                    let matched = std::cmp::min(lot.base_quantity, left);
+                    let cost = checked(lot.quote_quantity.checked_mul(matched))?;
+                    let cost = checked_div(cost, lot.base_quantity)?;
+                    let proceeds = checked(quote.checked_mul(matched))?;
+                    let proceeds = checked_div(proceeds, base)?;
+                    matches.push(LotMatch {
+                        base_quantity: matched,
+                        cost,
+                        proceeds,
+                        bought: lot.timestamp,
+                    });
+
+                    left -= matched;
+                    lot.base_quantity -= matched;
+                    lot.quote_quantity -= cost;
+                    if lot.base_quantity.is_zero() {
+                        self.lots.pop_front();
+                    }
+                }
+            }
+        }
+
+        Ok(matches)
+    }
+}
+
+pub trait CostBasis {
+    fn total_cost_basis(&self) -> QuoteQuantity;
+
+    // `None` when no base is held
+    fn weighted_avg_price(&self) -> Option<Price>;
+}
+
+impl CostBasis for [Lot] {
+    fn total_cost_basis(&self) -> QuoteQuantity {
+        self.iter().map(|lot| lot.price * lot.base_quantity).sum()
+    }
+
+    fn weighted_avg_price(&self) -> Option<Price> {
+        let base: BaseQuantity = self.iter().map(|lot| lot.base_quantity).sum();
+        match base.is_zero() {
+            true => None,
+            false => Some(self.total_cost_basis() / base),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trade::{Trade, TradeSide};
+    use crate::types::{Decimal, Timestamp};
+
+    use super::{open_lots, CostBasis, FifoLots, Lot, LotMatch};
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn trade(side: TradeSide, price: &str, base: &str, ts: u64) -> Trade {
+        let quote = dec(price) * dec(base);
+        Trade::new(side, dec(price), dec(base), quote, Timestamp(ts))
+    }
+
+    #[test]
+    fn test_open_lots() {
+        let trades = vec![
+            trade(TradeSide::Buy, "10", "1", 1),
+            trade(TradeSide::Buy, "20", "2", 2),
+            trade(TradeSide::Buy, "30", "1", 3),
+            trade(TradeSide::Sell, "25", "2.5", 4),
+        ];
+
+        let lots = open_lots(&trades);
+        assert_eq!(
+            lots,
+            vec![
+                Lot {
+                    price: dec("20"),
+                    base_quantity: dec("0.5"),
+                    timestamp: Timestamp(2),
+                },
+                Lot {
+                    price: dec("30"),
+                    base_quantity: dec("1"),
+                    timestamp: Timestamp(3),
+                },
+            ]
+        );
+        assert_eq!(lots.total_cost_basis(), dec("40"));
+        assert_eq!(lots.weighted_avg_price(), Some(dec("40") / dec("1.5")));
+    }
+
+    #[test]
+    fn test_open_lots_closed() {
+        let trades = vec![
+            trade(TradeSide::Sell, "25", "1", 1),
+            trade(TradeSide::Buy, "10", "1", 2),
+            trade(TradeSide::Sell, "25", "3", 3),
+        ];
+
+        let lots = open_lots(&trades);
+        assert_eq!(lots, vec![]);
+        assert_eq!(lots.total_cost_basis(), dec("0"));
+        assert_eq!(lots.weighted_avg_price(), None);
+
+        // The earlier sell is not carried forward against later buys
+        let lots = open_lots(&trades[..2]);
+        assert_eq!(lots.len(), 1);
+    }
+
+    #[test]
+    fn test_fifo_lots() {
+        let mut fifo = FifoLots::default();
+        assert_eq!(fifo.push(&trade(TradeSide::Buy, "10", "1", 1)), Ok(vec![]));
+        assert_eq!(fifo.push(&trade(TradeSide::Buy, "20", "2", 2)), Ok(vec![]));
+        assert_eq!(fifo.push(&trade(TradeSide::Buy, "30", "0", 3)), Ok(vec![]));
+
+        // Each matched slice gets its share of the sell's quote and of the lot's cost
+        let matches = fifo.push(&trade(TradeSide::Sell, "25", "4", 4)).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                LotMatch {
+                    base_quantity: dec("1"),
+                    cost: dec("10"),
+                    proceeds: dec("25"),
+                    bought: Timestamp(1),
+                },
+                LotMatch {
+                    base_quantity: dec("2"),
+                    cost: dec("40"),
+                    proceeds: dec("50"),
+                    bought: Timestamp(2),
+                },
+            ]
+        );
+        assert!(fifo.lots.is_empty());
+    }
+}
@@ -1,13 +1,41 @@
+#[cfg(feature = "binance")]
+pub mod binance;
+#[cfg(feature = "csv")]
+pub mod csv;
+mod dedup;
+mod diff;
 pub mod evaluate;
+mod histogram;
+#[cfg(feature = "jsonl")]
+pub mod jsonl;
+mod log;
+pub mod lots;
+pub mod pnl;
 pub mod position;
+pub mod serde_ts;
+pub mod sessions;
+mod table;
+pub mod wire;
 
+pub use self::dedup::{dedup, find_duplicates};
+pub use self::diff::{Change, EvaluateDiff};
+pub use self::histogram::{histogram, HistogramError};
+pub use self::log::TradeLog;
+pub use self::table::{render_table, render_table_with_scale, DISPLAY_SCALE};
+
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt;
 use std::future::Future;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
 use crate::time;
-use crate::types::{BaseQuantity, Price, QuoteQuantity};
+
+use self::position::PositionError;
+use crate::types::{BaseQuantity, Decimal, Price, Quantity, QuoteQuantity, Timestamp};
 
 pub trait Trader {
     fn buy(
@@ -31,6 +59,16 @@ pub trait Executor {
     ) -> impl Future<Output = Result<Vec<Trade>, Box<dyn Error>>>;
 }
 
+// Executors over several markets, each price only reaches the positions of its symbol
+pub trait SymbolExecutor {
+    fn trap_symbol(
+        &mut self,
+        agent: &impl Trader,
+        symbol: &str,
+        price: &Price,
+    ) -> impl Future<Output = Result<Vec<Trade>, Box<dyn Error>>>;
+}
+
 // Buy:  base  -> quote
 // Sell: quote -> base
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,7 +77,37 @@ pub struct Trade {
     pub price: Price,
     pub base_quantity: BaseQuantity, // Actual transaction base quantity
     pub quote_quantity: QuoteQuantity, // Actual transaction quote quantity
-    pub timestamp: u128,             // Actual transaction timestamp
+    pub timestamp: Timestamp,        // Actual transaction timestamp
+
+    // Commission charged by the exchange, in `fee_asset` or the quote asset when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<Quantity>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_asset: Option<String>,
+
+    // Exchange fill and order identifiers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+
+    // Market the trade belongs to, e.g. `BTCUSDT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+
+    // Price the order was placed at, `price` is where it actually filled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_price: Option<Price>,
+
+    // Id of the grid position whose `trap` made the trade
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position_id: Option<u64>,
+
+    // Why a position traded, set by `Position::trap`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<TradeKind>,
 }
 
 impl Trade {
@@ -48,7 +116,7 @@ impl Trade {
         price: Price,
         base_quantity: BaseQuantity,
         quote_quantity: QuoteQuantity,
-        timestamp: u128,
+        timestamp: Timestamp,
     ) -> Self {
         Self {
             side,
@@ -56,21 +124,49 @@ impl Trade {
             base_quantity,
             quote_quantity,
             timestamp,
+            fee: None,
+            fee_asset: None,
+            id: None,
+            order_id: None,
+            symbol: None,
+            requested_price: None,
+            position_id: None,
+            kind: None,
         }
     }
 
+    // Preferred over the positional constructors when attaching metadata
+    pub fn builder() -> TradeBuilder {
+        TradeBuilder::default()
+    }
+
+    // Rejects the trades `validate` does
+    pub fn try_new(
+        side: TradeSide,
+        price: Price,
+        base_quantity: BaseQuantity,
+        quote_quantity: QuoteQuantity,
+        timestamp: Timestamp,
+    ) -> Result<Self, TradeValidationError> {
+        let trade = Self::new(side, price, base_quantity, quote_quantity, timestamp);
+        trade.validate()?;
+        Ok(trade)
+    }
+
     pub fn with_buy(
         price: Price,
         base_quantity: BaseQuantity,
         quote_quantity: QuoteQuantity,
     ) -> Self {
-        Self::new(
+        let trade = Self::new(
             TradeSide::Buy,
             price,
             base_quantity,
             quote_quantity,
-            time::timestamp().as_millis(),
-        )
+            time::timestamp(),
+        );
+        debug_assert_eq!(trade.validate(), Ok(()));
+        trade
     }
 
     pub fn with_sell(
@@ -78,38 +174,833 @@ impl Trade {
         base_quantity: BaseQuantity,
         quote_quantity: QuoteQuantity,
     ) -> Self {
-        Self::new(
+        let trade = Self::new(
             TradeSide::Sell,
             price,
             base_quantity,
             quote_quantity,
-            time::timestamp().as_millis(),
-        )
+            time::timestamp(),
+        );
+        debug_assert_eq!(trade.validate(), Ok(()));
+        trade
     }
 
+    // Price must be positive and quantities non-negative. The received side may exceed
+    // what the price implies by at most `QUANTITY_TOLERANCE`, beyond that it is no rebate.
+    pub fn validate(&self) -> Result<(), TradeValidationError> {
+        if self.price <= Price::ZERO {
+            return Err(TradeValidationError::NonPositivePrice(self.price));
+        }
+
+        if self.base_quantity < BaseQuantity::ZERO {
+            return Err(TradeValidationError::NegativeBaseQuantity(
+                self.base_quantity,
+            ));
+        }
+
+        if self.quote_quantity < QuoteQuantity::ZERO {
+            return Err(TradeValidationError::NegativeQuoteQuantity(
+                self.quote_quantity,
+            ));
+        }
+
+        let tolerance = Decimal::ONE + QUANTITY_TOLERANCE;
+        match self.side {
+            TradeSide::Buy => {
+                let limit = self.quote_quantity / self.price * tolerance;
+                if self.base_quantity > limit {
+                    return Err(TradeValidationError::ExcessBaseQuantity {
+                        base_quantity: self.base_quantity,
+                        limit,
+                    });
+                }
+            }
+            TradeSide::Sell => {
+                let limit = self.base_quantity * self.price * tolerance;
+                if self.quote_quantity > limit {
+                    return Err(TradeValidationError::ExcessQuoteQuantity {
+                        quote_quantity: self.quote_quantity,
+                        limit,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // `base` works out the filled base quantity for the spent quote, e.g. after commission
+    pub fn try_with_buy<E>(
+        price: Price,
+        quote_quantity: QuoteQuantity,
+        base: impl FnOnce(&Price, &QuoteQuantity) -> Result<BaseQuantity, E>,
+    ) -> Result<Self, TradeError<E>> {
+        if price < Price::ZERO {
+            return Err(TradeError::NegativePrice(price));
+        }
+
+        let base_quantity = base(&price, &quote_quantity).map_err(TradeError::QuoteFailed)?;
+        let side = TradeSide::Buy;
+        let timestamp = time::timestamp();
+        Ok(Self::new(
+            side,
+            price,
+            base_quantity,
+            quote_quantity,
+            timestamp,
+        ))
+    }
+
+    // `quote` works out the received quote quantity for the sold base
+    pub fn try_with_sell<E>(
+        price: Price,
+        base_quantity: BaseQuantity,
+        quote: impl FnOnce(&Price, &BaseQuantity) -> Result<QuoteQuantity, E>,
+    ) -> Result<Self, TradeError<E>> {
+        if price < Price::ZERO {
+            return Err(TradeError::NegativePrice(price));
+        }
+
+        let quote_quantity = quote(&price, &base_quantity).map_err(TradeError::QuoteFailed)?;
+        let side = TradeSide::Sell;
+        let timestamp = time::timestamp();
+        Ok(Self::new(
+            side,
+            price,
+            base_quantity,
+            quote_quantity,
+            timestamp,
+        ))
+    }
+
+    pub fn buy_with_fee(
+        price: Price,
+        base_quantity: BaseQuantity,
+        quote_quantity: QuoteQuantity,
+        fee: Quantity,
+        fee_asset: Option<String>,
+    ) -> Self {
+        let mut trade = Self::with_buy(price, base_quantity, quote_quantity);
+        trade.fee = Some(fee);
+        trade.fee_asset = fee_asset;
+        trade
+    }
+
+    pub fn sell_with_fee(
+        price: Price,
+        base_quantity: BaseQuantity,
+        quote_quantity: QuoteQuantity,
+        fee: Quantity,
+        fee_asset: Option<String>,
+    ) -> Self {
+        let mut trade = Self::with_sell(price, base_quantity, quote_quantity);
+        trade.fee = Some(fee);
+        trade.fee_asset = fee_asset;
+        trade
+    }
+
+    // The explicit fee when it is charged in the quote asset, otherwise the implied costs.
+    // A fee in a third asset leaves the quantities untouched, so the implied costs are zero.
     pub fn costs(&self) -> QuoteQuantity {
+        self.try_costs()
+            .unwrap_or_else(|error| panic!("costs: {}", error))
+    }
+
+    pub fn try_costs(&self) -> Result<QuoteQuantity, ArithmeticError> {
+        match (self.fee, &self.fee_asset) {
+            (Some(fee), None) => Ok(fee),
+            _ => self.try_implied_costs(),
+        }
+    }
+
+    // Net base and quote flow, buys add base and spend quote, sells the reverse
+    pub fn profit<'a>(trades: impl IntoIterator<Item = &'a Trade>) -> Profit {
+        Self::try_profit(trades).unwrap_or_else(|error| panic!("profit: {}", error))
+    }
+
+    pub fn try_profit<'a>(
+        trades: impl IntoIterator<Item = &'a Trade>,
+    ) -> Result<Profit, ArithmeticError> {
+        let mut base = BaseQuantity::ZERO;
+        let mut quote = QuoteQuantity::ZERO;
+        for trade in trades {
+            match trade.side {
+                TradeSide::Buy => {
+                    base = checked(base.checked_add(trade.base_quantity))?;
+                    quote = checked(quote.checked_sub(trade.quote_quantity))?;
+                }
+                TradeSide::Sell => {
+                    base = checked(base.checked_sub(trade.base_quantity))?;
+                    quote = checked(quote.checked_add(trade.quote_quantity))?;
+                }
+            }
+        }
+
+        Ok(Profit { base, quote })
+    }
+
+    #[deprecated(note = "use `Trade::profit`, which returns a `Profit`")]
+    pub fn profit_tuple<'a>(
+        trades: impl IntoIterator<Item = &'a Trade>,
+    ) -> (BaseQuantity, QuoteQuantity) {
+        let profit = Self::profit(trades);
+        (profit.base, profit.quote)
+    }
+
+    // Untagged trades are grouped under `None`
+    pub fn profit_by_symbol(trades: &[Trade]) -> BTreeMap<Option<String>, Profit> {
+        group_by_symbol(trades)
+            .into_iter()
+            .map(|(symbol, trades)| (symbol, Self::profit(&trades)))
+            .collect()
+    }
+
+    // Volume weighted average price over trades on `side`, or all trades when `None`.
+    // Sums are accumulated first and divided once to keep full precision.
+    pub fn vwap<'a>(
+        trades: impl IntoIterator<Item = &'a Trade>,
+        side: Option<TradeSide>,
+    ) -> Option<Price> {
+        Self::try_vwap(trades, side).unwrap_or_else(|error| panic!("vwap: {}", error))
+    }
+
+    pub fn try_vwap<'a>(
+        trades: impl IntoIterator<Item = &'a Trade>,
+        side: Option<TradeSide>,
+    ) -> Result<Option<Price>, ArithmeticError> {
+        let mut notional = QuoteQuantity::ZERO;
+        let mut volume = BaseQuantity::ZERO;
+        for trade in trades {
+            if side.is_some_and(|side| side != trade.side) {
+                continue;
+            }
+
+            let value = checked(trade.price.checked_mul(trade.base_quantity))?;
+            notional = checked(notional.checked_add(value))?;
+            volume = checked(volume.checked_add(trade.base_quantity))?;
+        }
+
+        match volume.is_zero() {
+            true => Ok(None),
+            false => checked_div(notional, volume).map(Some),
+        }
+    }
+
+    pub fn vwap_buys<'a>(trades: impl IntoIterator<Item = &'a Trade>) -> Option<Price> {
+        Self::vwap(trades, Some(TradeSide::Buy))
+    }
+
+    pub fn vwap_sells<'a>(trades: impl IntoIterator<Item = &'a Trade>) -> Option<Price> {
+        Self::vwap(trades, Some(TradeSide::Sell))
+    }
+
+    // Back-computes the commission from the gap between the price and the quantities.
+    // Signed, a fill better than the price (an improvement or a rebate) is negative.
+    pub fn implied_costs(&self) -> QuoteQuantity {
+        self.try_implied_costs()
+            .unwrap_or_else(|error| panic!("implied costs: {}", error))
+    }
+
+    pub fn try_implied_costs(&self) -> Result<QuoteQuantity, ArithmeticError> {
         match self.side {
             TradeSide::Buy => {
-                let orgin_base = self.quote_quantity / self.price;
+                let orgin_base = checked_div(self.quote_quantity, self.price)?;
                 if self.base_quantity == orgin_base {
-                    QuoteQuantity::ZERO
+                    Ok(QuoteQuantity::ZERO)
                 } else {
-                    (orgin_base - self.base_quantity) * self.price
+                    let base = checked(orgin_base.checked_sub(self.base_quantity))?;
+                    checked(base.checked_mul(self.price))
                 }
             }
             TradeSide::Sell => {
-                let orgin_quote = self.base_quantity * self.price;
+                let orgin_quote = checked(self.base_quantity.checked_mul(self.price))?;
                 if self.quote_quantity == orgin_quote {
-                    QuoteQuantity::ZERO
+                    Ok(QuoteQuantity::ZERO)
                 } else {
-                    orgin_quote - self.quote_quantity
+                    checked(orgin_quote.checked_sub(self.quote_quantity))
+                }
+            }
+        }
+    }
+
+    // Divides the quantities and any fee by `fraction`, the second part takes the
+    // remainder so the parts always sum back to the original exactly
+    pub fn split(&self, fraction: Decimal) -> Result<(Trade, Trade), SplitError> {
+        if fraction <= Decimal::ZERO || fraction >= Decimal::ONE {
+            return Err(SplitError::InvalidFraction(fraction));
+        }
+
+        let mut parts = self.allocate(&[fraction, Decimal::ONE - fraction])?;
+        let second = parts.pop().expect("two parts");
+        let first = parts.pop().expect("two parts");
+
+        Ok((first, second))
+    }
+
+    // One part per weight in proportion to it, the last part takes the remainder
+    pub fn allocate(&self, weights: &[Decimal]) -> Result<Vec<Trade>, SplitError> {
+        if weights.is_empty() {
+            return Err(SplitError::NoWeights);
+        }
+
+        if let Some(weight) = weights.iter().find(|weight| **weight <= Decimal::ZERO) {
+            return Err(SplitError::InvalidWeight(*weight));
+        }
+
+        let total: Decimal = weights.iter().sum();
+        let mut base = self.base_quantity;
+        let mut quote = self.quote_quantity;
+        let mut fee = self.fee;
+
+        let mut parts = Vec::with_capacity(weights.len());
+        for weight in weights[..weights.len() - 1].iter() {
+            let mut part = self.clone();
+            part.base_quantity = self.base_quantity * weight / total;
+            part.quote_quantity = self.quote_quantity * weight / total;
+            part.fee = self.fee.map(|fee| fee * weight / total);
+
+            base -= part.base_quantity;
+            quote -= part.quote_quantity;
+            fee = fee.zip(part.fee).map(|(fee, part)| fee - part);
+            parts.push(part);
+        }
+
+        let mut last = self.clone();
+        last.base_quantity = base;
+        last.quote_quantity = quote;
+        last.fee = fee;
+        parts.push(last);
+
+        Ok(parts)
+    }
+
+    // The same fill at `factor` times the size, quantities and any fee scale, price and side
+    // do not. Nonlinear exchange effects like min-notional or lot-size rounding are ignored.
+    pub fn scale(&self, factor: Decimal) -> Trade {
+        let mut trade = self.clone();
+        trade.base_quantity = self.base_quantity * factor;
+        trade.quote_quantity = self.quote_quantity * factor;
+        trade.fee = self.fee.map(|fee| fee * factor);
+        trade
+    }
+
+    // Merges consecutive fills of the same order into one trade, see `aggregate_with`
+    pub fn aggregate<'a>(trades: impl IntoIterator<Item = &'a Trade>) -> Vec<Trade> {
+        Self::aggregate_with(trades, AggregatePolicy::default())
+    }
+
+    // Consecutive trades merge when side, symbol, order id, fee asset, position and kind
+    // agree. The merged price is the volume weighted average, quantities and fees are
+    // summed and the latest timestamp is kept, so the implied costs equal the sum of the parts.
+    pub fn aggregate_with<'a>(
+        trades: impl IntoIterator<Item = &'a Trade>,
+        policy: AggregatePolicy,
+    ) -> Vec<Trade> {
+        let mut result: Vec<Trade> = Vec::new();
+        let mut notional = QuoteQuantity::ZERO;
+        for trade in trades {
+            if let Some(last) = result
+                .last_mut()
+                .filter(|last| last.merges_with(trade, policy))
+            {
+                notional += trade.price * trade.base_quantity;
+                last.base_quantity += trade.base_quantity;
+                last.quote_quantity += trade.quote_quantity;
+                last.timestamp = std::cmp::max(last.timestamp, trade.timestamp);
+                last.fee = match (last.fee, trade.fee) {
+                    (None, None) => None,
+                    (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+                };
+                if last.id != trade.id {
+                    last.id = None;
                 }
+
+                if !last.base_quantity.is_zero() {
+                    last.price = notional / last.base_quantity;
+                }
+
+                continue;
             }
+
+            notional = trade.price * trade.base_quantity;
+            result.push(trade.clone());
+        }
+
+        result
+    }
+
+    fn merges_with(&self, other: &Trade, policy: AggregatePolicy) -> bool {
+        let same_price = match policy {
+            AggregatePolicy::AnyPrice => true,
+            AggregatePolicy::SamePrice => self.price == other.price,
+        };
+
+        same_price
+            && self.side == other.side
+            && self.symbol == other.symbol
+            && self.order_id == other.order_id
+            && self.fee_asset == other.fee_asset
+            && self.position_id == other.position_id
+            && self.kind == other.kind
+    }
+
+    // Quote over base at full Decimal precision (28 significant digits), `None` for
+    // a zero base or when the division overflows
+    pub fn effective_price(&self) -> Option<Price> {
+        self.quote_quantity.checked_div(self.base_quantity)
+    }
+
+    // Whether the effective price is within `tolerance`, relative to the recorded price.
+    // A trade with no base is only consistent when it has no quote either.
+    pub fn price_consistency(&self, tolerance: Decimal) -> bool {
+        match self.effective_price() {
+            Some(price) => {
+                // Beyond the representable range counts as inconsistent rather than panicking
+                let gap = price.checked_sub(self.price).map(|gap| gap.abs());
+                let limit = self.price.abs().checked_mul(tolerance);
+                gap.zip(limit).is_some_and(|(gap, limit)| gap <= limit)
+            }
+            None => self.quote_quantity.is_zero(),
+        }
+    }
+
+    // What `costs` should be at `fee_rate`, charged on the quote spent or received
+    pub fn expected_cost(&self, fee_rate: Decimal) -> QuoteQuantity {
+        match self.side {
+            TradeSide::Buy => self.quote_quantity * fee_rate,
+            TradeSide::Sell => self.base_quantity * self.price * fee_rate,
+        }
+    }
+
+    // Actual minus expected costs, positive when charged more than the rate implies
+    pub fn cost_deviation(&self, fee_rate: Decimal) -> QuoteQuantity {
+        self.costs() - self.expected_cost(fee_rate)
+    }
+
+    // Indices of the trades whose costs deviate from `fee_rate` by more than `tolerance`
+    pub fn audit<'a>(
+        trades: impl IntoIterator<Item = &'a Trade>,
+        fee_rate: Decimal,
+        tolerance: QuoteQuantity,
+    ) -> Vec<usize> {
+        trades
+            .into_iter()
+            .enumerate()
+            .filter(|(_, trade)| trade.cost_deviation(fee_rate).abs() > tolerance)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // Executed minus requested price, `None` when the requested price is unknown
+    pub fn slippage(&self) -> Option<Price> {
+        self.requested_price.map(|requested| self.price - requested)
+    }
+
+    // Slippage in quote over the traded base, positive when the fill was worse than requested
+    pub fn slippage_cost(&self) -> Option<QuoteQuantity> {
+        let slippage = self.slippage()? * self.base_quantity;
+        match self.side {
+            TradeSide::Buy => Some(slippage),
+            TradeSide::Sell => Some(-slippage),
+        }
+    }
+
+    pub fn is_rebate(&self) -> bool {
+        self.costs() < QuoteQuantity::ZERO
+    }
+}
+
+// Net flow of a set of trades, positive base is inventory still held
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profit {
+    pub base: BaseQuantity,
+    pub quote: QuoteQuantity,
+}
+
+impl Profit {
+    pub fn new(base: BaseQuantity, quote: QuoteQuantity) -> Self {
+        Self { base, quote }
+    }
+
+    // Values the leftover base at `mark_price`
+    pub fn total_in_quote(&self, mark_price: &Price) -> QuoteQuantity {
+        self.quote + self.base * mark_price
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.base.is_zero()
+    }
+}
+
+impl Add for Profit {
+    type Output = Profit;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Profit::new(self.base + rhs.base, self.quote + rhs.quote)
+    }
+}
+
+impl Sub for Profit {
+    type Output = Profit;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Profit::new(self.base - rhs.base, self.quote - rhs.quote)
+    }
+}
+
+// Equal when side, price and quantities agree, regardless of scale. The timestamp and the
+// metadata are ignored so a replayed trade matches the original, see `eq_with_timestamp`.
+impl PartialEq for Trade {
+    fn eq(&self, other: &Self) -> bool {
+        self.side == other.side
+            && self.price == other.price
+            && self.base_quantity == other.base_quantity
+            && self.quote_quantity == other.quote_quantity
+    }
+}
+
+impl Trade {
+    // Every field, timestamp and metadata included
+    pub fn eq_with_timestamp(&self, other: &Self) -> bool {
+        self == other
+            && self.timestamp == other.timestamp
+            && self.fee == other.fee
+            && self.fee_asset == other.fee_asset
+            && self.id == other.id
+            && self.order_id == other.order_id
+            && self.symbol == other.symbol
+            && self.requested_price == other.requested_price
+            && self.position_id == other.position_id
+            && self.kind == other.kind
+    }
+
+    // Same side with price and quantities each within `epsilon`, the timestamp is ignored
+    pub fn approx_eq(&self, other: &Self, epsilon: Decimal) -> bool {
+        let close = |a: Decimal, b: Decimal| (a - b).abs() <= epsilon;
+
+        self.side == other.side
+            && close(self.price, other.price)
+            && close(self.base_quantity, other.base_quantity)
+            && close(self.quote_quantity, other.quote_quantity)
+    }
+}
+
+// `BTCUSDT BUY 0.3996 @ 50 (quote 20) ts=1716000000000`, the symbol only when set
+impl fmt::Display for Trade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(symbol) = &self.symbol {
+            write!(f, "{} ", symbol)?;
+        }
+
+        write!(
+            f,
+            "{} {} @ {} (quote {}) ts={}",
+            self.side,
+            self.base_quantity.normalize(),
+            self.price.normalize(),
+            self.quote_quantity.normalize(),
+            self.timestamp
+        )
+    }
+}
+
+impl Trade {
+    // Column aligned table with a header row, numbers right aligned
+    pub fn summary(trades: &[Trade]) -> String {
+        let header = ["symbol", "side", "price", "base", "quote", "timestamp"];
+        let rows: Vec<[String; 6]> = trades
+            .iter()
+            .map(|trade| {
+                [
+                    trade.symbol.clone().unwrap_or_default(),
+                    trade.side.to_string(),
+                    trade.price.normalize().to_string(),
+                    trade.base_quantity.normalize().to_string(),
+                    trade.quote_quantity.normalize().to_string(),
+                    trade.timestamp.to_string(),
+                ]
+            })
+            .collect();
+
+        // The symbol column is dropped when no trade carries one
+        let first = match rows.iter().any(|row| !row[0].is_empty()) {
+            true => 0,
+            false => 1,
+        };
+
+        let mut widths = header.map(str::len);
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = std::cmp::max(*width, cell.len());
+            }
+        }
+
+        let header = header.map(String::from);
+        let mut lines = Vec::with_capacity(rows.len() + 1);
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let cells: Vec<String> = (first..row.len())
+                .map(|i| match i < 2 {
+                    true => format!("{:<width$}", row[i], width = widths[i]),
+                    false => format!("{:>width$}", row[i], width = widths[i]),
+                })
+                .collect();
+            lines.push(cells.join("  ").trim_end().to_string());
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AggregatePolicy {
+    // Fills at different prices merge at their volume weighted average
+    #[default]
+    AnyPrice,
+
+    // Only fills at an identical price merge
+    SamePrice,
+}
+
+// How far the received quantity may exceed the one implied by the price, 1%
+pub const QUANTITY_TOLERANCE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TradeValidationError {
+    NonPositivePrice(Price),
+    NegativeBaseQuantity(BaseQuantity),
+    NegativeQuoteQuantity(QuoteQuantity),
+    ExcessBaseQuantity {
+        base_quantity: BaseQuantity,
+        limit: BaseQuantity,
+    },
+    ExcessQuoteQuantity {
+        quote_quantity: QuoteQuantity,
+        limit: QuoteQuantity,
+    },
+}
+
+impl fmt::Display for TradeValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonPositivePrice(v) => write!(f, "price {} is not positive", v),
+            Self::NegativeBaseQuantity(v) => write!(f, "base_quantity {} is negative", v),
+            Self::NegativeQuoteQuantity(v) => write!(f, "quote_quantity {} is negative", v),
+            Self::ExcessBaseQuantity {
+                base_quantity,
+                limit,
+            } => write!(f, "base_quantity {} exceeds {}", base_quantity, limit),
+            Self::ExcessQuoteQuantity {
+                quote_quantity,
+                limit,
+            } => write!(f, "quote_quantity {} exceeds {}", quote_quantity, limit),
+        }
+    }
+}
+
+impl std::error::Error for TradeValidationError {}
+
+// Opt-in validation on load, `Vec<ValidatedTrade>` rejects any invalid trade
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Trade", into = "Trade")]
+pub struct ValidatedTrade(pub Trade);
+
+impl TryFrom<Trade> for ValidatedTrade {
+    type Error = TradeValidationError;
+
+    fn try_from(value: Trade) -> Result<Self, Self::Error> {
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+impl From<ValidatedTrade> for Trade {
+    fn from(value: ValidatedTrade) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TradeError<E> {
+    NegativePrice(Price),
+    QuoteFailed(E),
+    InvalidPosition(PositionError),
+}
+
+impl<E: fmt::Display> fmt::Display for TradeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NegativePrice(v) => write!(f, "trade price {} is negative", v),
+            Self::QuoteFailed(e) => write!(f, "trade quote failed: {}", e),
+            Self::InvalidPosition(e) => write!(f, "invalid position: {}", e),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, PartialEq, Deserialize)]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for TradeError<E> {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitError {
+    InvalidFraction(Decimal),
+    InvalidWeight(Decimal),
+    NoWeights,
+    NoParts,
+    // The range is too narrow to divide into that many parts
+    Indivisible(usize),
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFraction(v) => write!(f, "split fraction {} is not within (0, 1)", v),
+            Self::InvalidWeight(v) => write!(f, "allocation weight {} is not positive", v),
+            Self::NoWeights => write!(f, "allocation needs at least one weight"),
+            Self::NoParts => write!(f, "split needs at least one part"),
+            Self::Indivisible(n) => write!(f, "price range cannot be split into {} parts", n),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+// A checked Decimal operation that would otherwise have panicked
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArithmeticError {
+    Overflow,
+    DivisionByZero,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow => write!(f, "decimal arithmetic overflowed"),
+            Self::DivisionByZero => write!(f, "decimal division by zero"),
+        }
+    }
+}
+
+impl std::error::Error for ArithmeticError {}
+
+// `checked_*` results are `None` only on overflow, division is checked for zero first
+pub(crate) fn checked(value: Option<Decimal>) -> Result<Decimal, ArithmeticError> {
+    value.ok_or(ArithmeticError::Overflow)
+}
+
+pub(crate) fn checked_div(value: Decimal, divisor: Decimal) -> Result<Decimal, ArithmeticError> {
+    if divisor.is_zero() {
+        return Err(ArithmeticError::DivisionByZero);
+    }
+
+    checked(value.checked_div(divisor))
+}
+
+pub(crate) fn group_by_symbol(trades: &[Trade]) -> BTreeMap<Option<String>, Vec<Trade>> {
+    let mut groups: BTreeMap<Option<String>, Vec<Trade>> = BTreeMap::new();
+    for trade in trades.iter() {
+        let group = groups.entry(trade.symbol.clone()).or_default();
+        group.push(trade.clone());
+    }
+
+    groups
+}
+
+// Every trade resized by `factor`, see `Trade::scale` for what this ignores
+pub fn scale_all(trades: &[Trade], factor: Decimal) -> Vec<Trade> {
+    trades.iter().map(|trade| trade.scale(factor)).collect()
+}
+
+// Starts as an empty buy stamped with the current time
+#[derive(Debug, Clone)]
+pub struct TradeBuilder {
+    trade: Trade,
+}
+
+impl Default for TradeBuilder {
+    fn default() -> Self {
+        Self {
+            trade: Trade::new(
+                TradeSide::Buy,
+                Price::ZERO,
+                BaseQuantity::ZERO,
+                QuoteQuantity::ZERO,
+                time::timestamp(),
+            ),
+        }
+    }
+}
+
+impl TradeBuilder {
+    pub fn side(mut self, side: TradeSide) -> Self {
+        self.trade.side = side;
+        self
+    }
+
+    pub fn price(mut self, price: Price) -> Self {
+        self.trade.price = price;
+        self
+    }
+
+    pub fn base_quantity(mut self, base_quantity: BaseQuantity) -> Self {
+        self.trade.base_quantity = base_quantity;
+        self
+    }
+
+    pub fn quote_quantity(mut self, quote_quantity: QuoteQuantity) -> Self {
+        self.trade.quote_quantity = quote_quantity;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.trade.timestamp = timestamp;
+        self
+    }
+
+    pub fn fee(mut self, fee: Quantity, fee_asset: Option<String>) -> Self {
+        self.trade.fee = Some(fee);
+        self.trade.fee_asset = fee_asset;
+        self
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.trade.id = Some(id.into());
+        self
+    }
+
+    pub fn order_id(mut self, order_id: impl Into<String>) -> Self {
+        self.trade.order_id = Some(order_id.into());
+        self
+    }
+
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.trade.symbol = Some(symbol.into());
+        self
+    }
+
+    pub fn requested_price(mut self, requested_price: Price) -> Self {
+        self.trade.requested_price = Some(requested_price);
+        self
+    }
+
+    pub fn position_id(mut self, position_id: u64) -> Self {
+        self.trade.position_id = Some(position_id);
+        self
+    }
+
+    pub fn kind(mut self, kind: TradeKind) -> Self {
+        self.trade.kind = Some(kind);
+        self
+    }
+
+    pub fn build(self) -> Trade {
+        self.trade
+    }
+
+    pub fn try_build(self) -> Result<Trade, TradeValidationError> {
+        self.trade.validate()?;
+        Ok(self.trade)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash, Deserialize)]
 pub enum TradeSide {
     #[serde(rename = "BUY")]
     Buy,
@@ -118,19 +1009,86 @@ pub enum TradeSide {
     Sell,
 }
 
+// A buy within the buying range, a sell within the selling range or a sell at the stop
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash, Deserialize)]
+pub enum TradeKind {
+    #[serde(rename = "ENTRY")]
+    Entry,
+
+    #[serde(rename = "TAKE_PROFIT")]
+    TakeProfit,
+
+    #[serde(rename = "STOP_LOSS")]
+    StopLoss,
+}
+
+impl TradeSide {
+    pub fn opposite(&self) -> Self {
+        match self {
+            Self::Buy => Self::Sell,
+            Self::Sell => Self::Buy,
+        }
+    }
+
+    pub fn is_buy(&self) -> bool {
+        matches!(self, Self::Buy)
+    }
+
+    pub fn is_sell(&self) -> bool {
+        matches!(self, Self::Sell)
+    }
+}
+
+impl fmt::Display for TradeSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Buy => f.write_str("BUY"),
+            Self::Sell => f.write_str("SELL"),
+        }
+    }
+}
+
+impl FromStr for TradeSide {
+    type Err = ParseTradeSideError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("BUY") {
+            return Ok(Self::Buy);
+        }
+
+        if s.eq_ignore_ascii_case("SELL") {
+            return Ok(Self::Sell);
+        }
+
+        Err(ParseTradeSideError(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseTradeSideError(pub String);
+
+impl fmt::Display for ParseTradeSideError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid trade side {:?}, expected BUY or SELL", self.0)
+    }
+}
 
+impl std::error::Error for ParseTradeSideError {}
 
 #[cfg(test)]
 mod tests {
-    use crate::types::Decimal;
+    use crate::types::{Decimal, Timestamp};
 
-    use super::Trade;
+    use super::{
+        scale_all, AggregatePolicy, ArithmeticError, ParseTradeSideError, Profit, SplitError,
+        Trade, TradeError, TradeKind, TradeSide, TradeValidationError, ValidatedTrade,
+    };
 
     fn dec(value: &str) -> Decimal {
         use std::str::FromStr;
         Decimal::from_str(value).unwrap()
     }
-    
+
     #[tokio::test]
     async fn test_costs() {
         let trade = Trade::with_buy(dec("10"), dec("5"), dec("50"));
@@ -148,4 +1106,626 @@ mod tests {
         let trade = Trade::with_sell(dec("200"), dec("0.3996"), dec("79.84008"));
         assert_eq!(trade.costs(), dec("0.07992"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_costs_with_fee() {
+        let trade = Trade::buy_with_fee(dec("50"), dec("0.3996"), dec("20.0"), dec("0.03"), None);
+        assert_eq!(trade.costs(), dec("0.03"));
+        assert_eq!(trade.implied_costs(), dec("0.02"));
+
+        let bnb = Some(String::from("BNB"));
+        let trade = Trade::sell_with_fee(dec("200"), dec("0.4"), dec("80"), dec("0.0001"), bnb);
+        assert_eq!(trade.costs(), dec("0"));
+
+        let trade = Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0"));
+        let json = serde_json::to_string(&trade).unwrap();
+        assert!(!json.contains("fee"));
+
+        let trade: Trade = serde_json::from_str(&json).unwrap();
+        assert_eq!(trade.fee, None);
+        assert_eq!(trade.fee_asset, None);
+    }
+
+    #[tokio::test]
+    async fn test_vwap() {
+        let trades = vec![
+            Trade::with_buy(dec("10"), dec("1"), dec("10")),
+            Trade::with_buy(dec("13"), dec("2"), dec("26")),
+            Trade::with_sell(dec("20"), dec("3"), dec("60")),
+        ];
+
+        assert_eq!(Trade::vwap_buys(&trades), Some(dec("12")));
+        assert_eq!(Trade::vwap_sells(&trades), Some(dec("20")));
+        assert_eq!(Trade::vwap(&trades, None), Some(dec("16")));
+        assert_eq!(Trade::vwap_sells(&trades[..2]), None);
+        assert_eq!(Trade::vwap(&[], None), None);
+
+        let trades = vec![
+            Trade::with_buy(dec("1"), dec("1"), dec("1")),
+            Trade::with_buy(dec("2"), dec("1"), dec("2")),
+            Trade::with_buy(dec("2"), dec("1"), dec("2")),
+        ];
+        assert_eq!(
+            Trade::vwap(&trades, Some(TradeSide::Buy)),
+            Some(dec("5") / dec("3"))
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let trade = Trade::builder()
+            .side(TradeSide::Sell)
+            .price(dec("200"))
+            .base_quantity(dec("0.4"))
+            .quote_quantity(dec("80"))
+            .timestamp(Timestamp(1700000000000))
+            .id("28457")
+            .order_id("12345")
+            .build();
+
+        assert_eq!(trade.side, TradeSide::Sell);
+        assert_eq!(trade.price, dec("200"));
+        assert_eq!(trade.base_quantity, dec("0.4"));
+        assert_eq!(trade.quote_quantity, dec("80"));
+        assert_eq!(trade.timestamp, Timestamp(1700000000000));
+        assert_eq!(trade.id.as_deref(), Some("28457"));
+        assert_eq!(trade.order_id.as_deref(), Some("12345"));
+        assert_eq!(trade.fee, None);
+
+        let json = r#"{"side":"BUY","price":"10","base_quantity":"1","quote_quantity":"10","timestamp":1}"#;
+        let trade: Trade = serde_json::from_str(json).unwrap();
+        assert_eq!(trade.id, None);
+        assert_eq!(trade.order_id, None);
+        assert_eq!(serde_json::to_string(&trade).unwrap(), json);
+    }
+
+    #[test]
+    fn test_costs_rebate() {
+        let trade = Trade::with_buy(dec("50"), dec("0.4004"), dec("20"));
+        assert_eq!(trade.costs(), dec("-0.02"));
+        assert!(trade.is_rebate());
+
+        let trade = Trade::with_sell(dec("200"), dec("0.4"), dec("80.08"));
+        assert_eq!(trade.costs(), dec("-0.08"));
+        assert!(trade.is_rebate());
+
+        let trade = Trade::with_sell(dec("200"), dec("0.4"), dec("80"));
+        assert!(!trade.is_rebate());
+
+        let trade = Trade::with_sell(dec("200"), dec("0.4"), dec("79.92"));
+        assert!(!trade.is_rebate());
+    }
+
+    #[test]
+    fn test_profit() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+            Trade::with_buy(dec("50"), dec("9.99"), dec("500.0")),
+        ];
+        assert_eq!(
+            Trade::profit(&trades),
+            Profit::new(dec("9.99"), dec("-440.15992"))
+        );
+        assert_eq!(Trade::profit(&[]), Profit::default());
+        assert!(Trade::profit(&trades[..2]).is_flat());
+
+        #[allow(deprecated)]
+        let tuple = Trade::profit_tuple(&trades);
+        assert_eq!(tuple, (dec("9.99"), dec("-440.15992")));
+    }
+
+    #[test]
+    fn test_profit_total_in_quote() {
+        let profit = Profit::new(dec("9.99"), dec("-440.15992"));
+        assert_eq!(profit.total_in_quote(&dec("50")), dec("59.34008"));
+        assert_eq!(profit.total_in_quote(&dec("0")), dec("-440.15992"));
+
+        let flat = Profit::new(dec("0"), dec("59.84008"));
+        assert_eq!(flat.total_in_quote(&dec("1000")), dec("59.84008"));
+
+        let short = Profit::new(dec("-1"), dec("210"));
+        assert_eq!(short.total_in_quote(&dec("200")), dec("10"));
+
+        assert_eq!(profit + short, Profit::new(dec("8.99"), dec("-230.15992")));
+        assert_eq!(profit - profit, Profit::default());
+    }
+
+    #[test]
+    fn test_profit_by_symbol() {
+        let trade = |side: TradeSide, symbol: Option<&str>, quote: &str| {
+            let builder = Trade::builder()
+                .side(side)
+                .price(dec("10"))
+                .base_quantity(dec("1"))
+                .quote_quantity(dec(quote));
+
+            match symbol {
+                Some(symbol) => builder.symbol(symbol).build(),
+                None => builder.build(),
+            }
+        };
+
+        let trades = vec![
+            trade(TradeSide::Buy, Some("BTCUSDT"), "10"),
+            trade(TradeSide::Buy, Some("ETHUSDT"), "10"),
+            trade(TradeSide::Sell, Some("BTCUSDT"), "12"),
+            trade(TradeSide::Sell, None, "9"),
+        ];
+
+        let profits = Trade::profit_by_symbol(&trades);
+        assert_eq!(profits.len(), 3);
+        assert_eq!(
+            profits[&Some("BTCUSDT".into())],
+            Profit::new(dec("0"), dec("2"))
+        );
+        assert_eq!(
+            profits[&Some("ETHUSDT".into())],
+            Profit::new(dec("1"), dec("-10"))
+        );
+        assert_eq!(profits[&None], Profit::new(dec("-1"), dec("9")));
+    }
+
+    #[test]
+    fn test_split() {
+        let trade = Trade::with_buy(dec("3"), dec("0.3333333"), dec("1"));
+
+        let (first, second) = trade.split(dec("0.3")).unwrap();
+        assert_eq!(first.base_quantity, dec("0.09999999"));
+        assert_eq!(first.quote_quantity, dec("0.3"));
+        assert_eq!(second.base_quantity, dec("0.23333331"));
+        assert_eq!(second.quote_quantity, dec("0.7"));
+        assert_eq!(first.timestamp, trade.timestamp);
+        assert_eq!(second.price, trade.price);
+
+        assert_eq!(
+            trade.split(dec("0")).unwrap_err(),
+            SplitError::InvalidFraction(dec("0"))
+        );
+        assert_eq!(
+            trade.split(dec("1")).unwrap_err(),
+            SplitError::InvalidFraction(dec("1"))
+        );
+    }
+
+    #[test]
+    fn test_allocate() {
+        let trade = Trade::sell_with_fee(dec("7"), dec("1"), dec("7"), dec("0.007"), None);
+
+        let parts = trade.allocate(&[dec("1"), dec("1"), dec("1")]).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].base_quantity, dec("1") / dec("3"));
+
+        let base: Decimal = parts.iter().map(|part| part.base_quantity).sum();
+        let quote: Decimal = parts.iter().map(|part| part.quote_quantity).sum();
+        let fee: Decimal = parts.iter().filter_map(|part| part.fee).sum();
+        assert_eq!(base, trade.base_quantity);
+        assert_eq!(quote, trade.quote_quantity);
+        assert_eq!(fee, dec("0.007"));
+
+        assert_eq!(trade.allocate(&[]).unwrap_err(), SplitError::NoWeights);
+        assert_eq!(
+            trade.allocate(&[dec("1"), dec("-1")]).unwrap_err(),
+            SplitError::InvalidWeight(dec("-1"))
+        );
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let fill = |side: TradeSide, price: &str, base: &str, quote: &str, ts: u64| {
+            Trade::builder()
+                .side(side)
+                .price(dec(price))
+                .base_quantity(dec(base))
+                .quote_quantity(dec(quote))
+                .timestamp(Timestamp(ts))
+                .order_id("1")
+                .build()
+        };
+
+        let trades = vec![
+            fill(TradeSide::Buy, "10", "0.999", "10", 1),
+            fill(TradeSide::Buy, "20", "0.999", "20", 3),
+            fill(TradeSide::Buy, "20", "1.998", "40", 2),
+            fill(TradeSide::Sell, "25", "1", "24.9", 4),
+            fill(TradeSide::Sell, "25", "2", "49.8", 5),
+        ];
+
+        let merged = Trade::aggregate(&trades);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].price, dec("17.5"));
+        assert_eq!(merged[0].base_quantity, dec("3.996"));
+        assert_eq!(merged[0].quote_quantity, dec("70"));
+        assert_eq!(merged[0].timestamp, Timestamp(3));
+        assert_eq!(merged[1].base_quantity, dec("3"));
+        assert_eq!(merged[1].timestamp, Timestamp(5));
+
+        for (merged, parts) in [(&merged[0], &trades[..3]), (&merged[1], &trades[3..])] {
+            let costs: Decimal = parts.iter().map(|trade| trade.costs()).sum();
+            assert_eq!(merged.costs(), costs);
+        }
+
+        let merged = Trade::aggregate_with(&trades, AggregatePolicy::SamePrice);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[1].base_quantity, dec("2.997"));
+
+        let mut other_order = trades[1].clone();
+        other_order.order_id = Some("2".into());
+        assert_eq!(Trade::aggregate(&[trades[0].clone(), other_order]).len(), 2);
+
+        // A stop and a take profit at the same price are separate trades
+        let mut stop = trades[3].clone();
+        stop.kind = Some(TradeKind::StopLoss);
+        let mut take_profit = trades[4].clone();
+        take_profit.kind = Some(TradeKind::TakeProfit);
+        let merged = Trade::aggregate(&[stop, take_profit]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].kind, Some(TradeKind::StopLoss));
+        assert_eq!(merged[1].kind, Some(TradeKind::TakeProfit));
+    }
+
+    #[test]
+    fn test_trade_side() {
+        use std::str::FromStr;
+
+        for side in [TradeSide::Buy, TradeSide::Sell] {
+            assert_eq!(TradeSide::from_str(&side.to_string()), Ok(side));
+            assert_eq!(side.opposite().opposite(), side);
+            assert_ne!(side.is_buy(), side.is_sell());
+            assert_eq!(
+                serde_json::to_string(&side).unwrap(),
+                format!("\"{}\"", side)
+            );
+        }
+
+        assert_eq!(TradeSide::Buy.opposite(), TradeSide::Sell);
+        assert!(TradeSide::Buy.is_buy());
+        assert_eq!("sell".parse(), Ok(TradeSide::Sell));
+        assert_eq!("Buy".parse(), Ok(TradeSide::Buy));
+        assert_eq!(
+            "hold".parse::<TradeSide>(),
+            Err(ParseTradeSideError("hold".into()))
+        );
+        assert_eq!(
+            ParseTradeSideError("hold".into()).to_string(),
+            "invalid trade side \"hold\", expected BUY or SELL"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let trade = Trade::new(
+            TradeSide::Buy,
+            dec("50.00"),
+            dec("0.39960"),
+            dec("20.0"),
+            Timestamp(1716000000000),
+        );
+        assert_eq!(
+            trade.to_string(),
+            "BUY 0.3996 @ 50 (quote 20) ts=1716000000000"
+        );
+
+        let mut trade = trade;
+        trade.symbol = Some("BTCUSDT".into());
+        assert_eq!(
+            trade.to_string(),
+            "BTCUSDT BUY 0.3996 @ 50 (quote 20) ts=1716000000000"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("50.00"),
+                dec("0.39960"),
+                dec("20.0"),
+                Timestamp(1716000000000),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("200"),
+                dec("0.3996"),
+                dec("79.84008"),
+                Timestamp(1716000000001),
+            ),
+        ];
+
+        assert_eq!(
+            Trade::summary(&trades),
+            "side  price    base     quote      timestamp\n\
+             BUY      50  0.3996        20  1716000000000\n\
+             SELL    200  0.3996  79.84008  1716000000001"
+        );
+
+        let mut trades = trades;
+        trades[1].symbol = Some("BTCUSDT".into());
+        assert_eq!(
+            Trade::summary(&trades),
+            [
+                "symbol   side  price    base     quote      timestamp",
+                "         BUY      50  0.3996        20  1716000000000",
+                "BTCUSDT  SELL    200  0.3996  79.84008  1716000000001",
+            ]
+            .join("\n")
+        );
+
+        assert_eq!(Trade::summary(&[]), "side  price  base  quote  timestamp");
+    }
+
+    #[test]
+    fn test_try_with_buy_sell() {
+        let commission = |price: &Decimal, quote: &Decimal| -> Result<Decimal, String> {
+            Ok(quote / price * dec("0.999"))
+        };
+
+        let trade = Trade::try_with_buy(dec("50"), dec("20"), commission).unwrap();
+        assert_eq!(trade.side, TradeSide::Buy);
+        assert_eq!(trade.base_quantity, dec("0.3996"));
+
+        assert_eq!(
+            Trade::try_with_buy(dec("-50"), dec("20"), commission).unwrap_err(),
+            TradeError::NegativePrice(dec("-50"))
+        );
+
+        let failed = |_: &Decimal, _: &Decimal| Err(String::from("no liquidity"));
+        let error = Trade::try_with_sell(dec("200"), dec("1"), failed).unwrap_err();
+        assert_eq!(error, TradeError::QuoteFailed(String::from("no liquidity")));
+        assert_eq!(error.to_string(), "trade quote failed: no liquidity");
+
+        let trade =
+            Trade::try_with_sell(dec("200"), dec("0.5"), |p, b| Ok::<_, String>(p * b)).unwrap();
+        assert_eq!(trade.quote_quantity, dec("100"));
+    }
+
+    #[test]
+    fn test_eq() {
+        let trade = Trade::new(
+            TradeSide::Buy,
+            dec("50"),
+            dec("0.3996"),
+            dec("20.0"),
+            Timestamp(1),
+        );
+
+        let mut later = trade.clone();
+        later.timestamp = Timestamp(2);
+        later.price = dec("50.00");
+        assert_eq!(trade, later);
+        assert!(!trade.eq_with_timestamp(&later));
+        assert!(trade.eq_with_timestamp(&trade.clone()));
+
+        let mut tagged = trade.clone();
+        tagged.symbol = Some("BTCUSDT".into());
+        assert_eq!(trade, tagged);
+        assert!(!trade.eq_with_timestamp(&tagged));
+
+        let mut requested = trade.clone();
+        requested.requested_price = Some(dec("49.5"));
+        assert_eq!(trade, requested);
+        assert!(!trade.eq_with_timestamp(&requested));
+
+        let mut sell = trade.clone();
+        sell.side = TradeSide::Sell;
+        assert_ne!(trade, sell);
+
+        let mut close = trade.clone();
+        close.base_quantity = dec("0.39960000001");
+        assert_ne!(trade, close);
+        assert!(trade.approx_eq(&close, dec("0.0000001")));
+        assert!(!trade.approx_eq(&close, dec("0.000000000001")));
+        assert!(!trade.approx_eq(&sell, dec("1")));
+    }
+
+    #[test]
+    fn test_try_new() {
+        let ts = Timestamp(1);
+        let buy = |price: &str, base: &str, quote: &str| {
+            Trade::try_new(TradeSide::Buy, dec(price), dec(base), dec(quote), ts)
+        };
+
+        assert!(buy("50", "0.3996", "20").is_ok());
+        assert!(buy("50", "0.4004", "20").is_ok());
+        assert_eq!(
+            buy("0", "1", "20").unwrap_err(),
+            TradeValidationError::NonPositivePrice(dec("0"))
+        );
+        assert_eq!(
+            buy("50", "-1", "20").unwrap_err(),
+            TradeValidationError::NegativeBaseQuantity(dec("-1"))
+        );
+        assert_eq!(
+            buy("50", "1", "-20").unwrap_err(),
+            TradeValidationError::NegativeQuoteQuantity(dec("-20"))
+        );
+        assert_eq!(
+            buy("50", "4", "20").unwrap_err(),
+            TradeValidationError::ExcessBaseQuantity {
+                base_quantity: dec("4"),
+                limit: dec("0.404"),
+            }
+        );
+
+        let sell = Trade::try_new(TradeSide::Sell, dec("200"), dec("1"), dec("300"), ts);
+        assert_eq!(
+            sell.unwrap_err().to_string(),
+            "quote_quantity 300 exceeds 202.00"
+        );
+    }
+
+    #[test]
+    fn test_validated_trade() {
+        let result = Trade::builder()
+            .price(dec("-1"))
+            .base_quantity(dec("1"))
+            .try_build();
+        assert_eq!(
+            result,
+            Err(TradeValidationError::NonPositivePrice(dec("-1")))
+        );
+
+        let json = r#"{"side":"BUY","price":"10","base_quantity":"1","quote_quantity":"10","timestamp":1}"#;
+        let trade: ValidatedTrade = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&trade).unwrap(), json);
+
+        let json = r#"{"side":"BUY","price":"10","base_quantity":"-1","quote_quantity":"10","timestamp":1}"#;
+        let error = serde_json::from_str::<ValidatedTrade>(json).unwrap_err();
+        assert!(error.to_string().contains("base_quantity -1 is negative"));
+        assert!(serde_json::from_str::<Trade>(json).is_ok());
+    }
+
+    #[test]
+    fn test_slippage() {
+        let trade = Trade::with_buy(dec("100.1"), dec("1"), dec("100.1"));
+        assert_eq!(trade.slippage(), None);
+        assert_eq!(trade.slippage_cost(), None);
+
+        let trade = Trade::builder()
+            .price(dec("100.1"))
+            .base_quantity(dec("2"))
+            .quote_quantity(dec("200.2"))
+            .requested_price(dec("100"))
+            .build();
+        assert_eq!(trade.slippage(), Some(dec("0.1")));
+        assert_eq!(trade.slippage_cost(), Some(dec("0.2")));
+
+        let trade = Trade::builder()
+            .side(TradeSide::Sell)
+            .price(dec("99.9"))
+            .base_quantity(dec("2"))
+            .quote_quantity(dec("199.8"))
+            .requested_price(dec("100"))
+            .build();
+        assert_eq!(trade.slippage(), Some(dec("-0.1")));
+        assert_eq!(trade.slippage_cost(), Some(dec("0.2")));
+    }
+
+    #[test]
+    fn test_profit_iterators() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.4"), dec("20")),
+            Trade::with_sell(dec("200"), dec("0.4"), dec("80")),
+            Trade::with_buy(dec("50"), dec("10"), dec("500")),
+        ];
+
+        let expected = Profit::new(dec("10"), dec("-440"));
+        assert_eq!(Trade::profit(&trades), expected);
+        assert_eq!(Trade::profit(trades.as_slice()), expected);
+        assert_eq!(Trade::profit(trades.iter()), expected);
+
+        let buys = trades.iter().filter(|trade| trade.side.is_buy());
+        assert_eq!(Trade::profit(buys), Profit::new(dec("10.4"), dec("-520")));
+
+        let buys = trades.iter().filter(|trade| trade.side.is_buy());
+        assert_eq!(Trade::vwap(buys, None), Some(dec("50")));
+        assert_eq!(Trade::aggregate(trades[..1].iter()).len(), 1);
+
+        let log = crate::trade::TradeLog::from(trades.clone());
+        let sells = log.by_side(TradeSide::Sell);
+        assert_eq!(Trade::profit(sells), Profit::new(dec("-0.4"), dec("80")));
+    }
+
+    #[test]
+    fn test_effective_price() {
+        let trade = Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0"));
+        assert_eq!(trade.effective_price(), Some(dec("20") / dec("0.3996")));
+        assert!(trade.price_consistency(dec("0.002")));
+        assert!(!trade.price_consistency(dec("0.001")));
+
+        let mut trade = Trade::with_sell(dec("200"), dec("0.5"), dec("100"));
+        assert_eq!(trade.effective_price(), Some(dec("200")));
+        assert!(trade.price_consistency(dec("0")));
+
+        trade.price = dec("150");
+        assert!(!trade.price_consistency(dec("0.01")));
+
+        trade.base_quantity = dec("0");
+        assert_eq!(trade.effective_price(), None);
+        assert!(!trade.price_consistency(dec("0.01")));
+
+        trade.quote_quantity = dec("0");
+        assert!(trade.price_consistency(dec("0.01")));
+    }
+
+    #[test]
+    fn test_expected_cost() {
+        let rate = dec("0.001");
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+            Trade::with_buy(dec("50"), dec("9.99"), dec("500.0")),
+            Trade::with_sell(dec("200"), dec("0.4"), dec("79.6")),
+        ];
+
+        assert_eq!(trades[0].expected_cost(rate), dec("0.02"));
+        assert_eq!(trades[0].cost_deviation(rate), dec("0"));
+        assert_eq!(trades[1].expected_cost(rate), dec("0.07992"));
+        assert_eq!(trades[1].cost_deviation(rate), dec("0"));
+        assert_eq!(trades[2].cost_deviation(rate), dec("0"));
+        assert_eq!(trades[3].cost_deviation(rate), dec("0.32"));
+
+        assert_eq!(Trade::audit(&trades, rate, dec("0.0001")), vec![3]);
+        assert!(Trade::audit(&trades, rate, dec("1")).is_empty());
+        assert_eq!(
+            Trade::audit(&trades, dec("0"), dec("0.0001")),
+            vec![0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_scale() {
+        let trade = Trade::buy_with_fee(dec("50"), dec("0.4"), dec("20"), dec("0.02"), None);
+        let scaled = trade.scale(dec("100"));
+
+        assert_eq!(scaled.side, TradeSide::Buy);
+        assert_eq!(scaled.price, dec("50"));
+        assert_eq!(scaled.base_quantity, dec("40"));
+        assert_eq!(scaled.quote_quantity, dec("2000"));
+        assert_eq!(scaled.fee, Some(dec("2")));
+        assert_eq!(scaled.timestamp, trade.timestamp);
+
+        let trades = scale_all(&[trade.clone(), trade], dec("0.5"));
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[1].quote_quantity, dec("10"));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let trade = Trade::new(
+            TradeSide::Sell,
+            dec("2"),
+            Decimal::MAX,
+            dec("1"),
+            Timestamp(0),
+        );
+        assert_eq!(trade.try_costs(), Err(ArithmeticError::Overflow));
+
+        let trade = Trade::new(TradeSide::Buy, dec("0"), dec("1"), dec("1"), Timestamp(0));
+        assert_eq!(trade.try_costs(), Err(ArithmeticError::DivisionByZero));
+
+        let trades = vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("1"),
+                dec("1"),
+                Decimal::MAX,
+                Timestamp(0),
+            ),
+            Trade::new(
+                TradeSide::Buy,
+                dec("1"),
+                dec("1"),
+                Decimal::MAX,
+                Timestamp(0),
+            ),
+        ];
+        assert_eq!(Trade::try_profit(&trades), Err(ArithmeticError::Overflow));
+        assert_eq!(Trade::try_vwap(&trades, None), Ok(Some(dec("1"))));
+
+        let trade = Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0"));
+        assert_eq!(trade.try_costs(), Ok(trade.costs()));
+        assert_eq!(Trade::try_profit([&trade]), Ok(Trade::profit([&trade])));
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{BaseQuantity, Price, QuoteQuantity};
+
+use super::lots::FifoLots;
+use super::Trade;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PnlReport {
+    pub realized_quote: QuoteQuantity,
+    pub unrealized_quote: QuoteQuantity, // Open inventory valued at the mark price minus its cost
+    pub open_base: BaseQuantity,
+    pub open_average_cost: Option<Price>, // Quote spent per open base, `None` when flat
+
+    // Sold base with no earlier buy to match, e.g. inventory seeded before the trades
+    pub unmatched_sell_base: BaseQuantity,
+    pub unmatched_sell_quote: QuoteQuantity,
+}
+
+// Matches sells against the earliest open buys. Costs and proceeds are the quote quantities
+// actually exchanged, so commissions already reduce the realized result.
+pub fn fifo(trades: &[Trade], mark_price: &Price) -> PnlReport {
+    let mut report = PnlReport::default();
+    let mut fifo = FifoLots::default();
+
+    for trade in trades.iter() {
+        if trade.base_quantity.is_zero() {
+            continue;
+        }
+
+        let matches = fifo
+            .push(trade)
+            .unwrap_or_else(|error| panic!("fifo: {}", error));
+        if trade.side.is_buy() {
+            continue;
+        }
+
+        let mut base = trade.base_quantity;
+        let mut proceeds = trade.quote_quantity;
+        for matched in matches.iter() {
+            report.realized_quote += matched.proceeds - matched.cost;
+            base -= matched.base_quantity;
+            proceeds -= matched.proceeds;
+        }
+
+        report.unmatched_sell_base += base;
+        report.unmatched_sell_quote += proceeds;
+    }
+
+    let mut cost = QuoteQuantity::ZERO;
+    for lot in fifo.lots.iter() {
+        report.open_base += lot.base_quantity;
+        cost += lot.quote_quantity;
+    }
+
+    report.unrealized_quote = report.open_base * mark_price - cost;
+    if !report.open_base.is_zero() {
+        report.open_average_cost = Some(cost / report.open_base);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trade::Trade;
+    use crate::types::Decimal;
+
+    use super::{fifo, PnlReport};
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_fifo() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+            Trade::with_buy(dec("50"), dec("9.99"), dec("500.0")),
+        ];
+
+        let report = fifo(&trades, &dec("50"));
+        assert_eq!(
+            report,
+            PnlReport {
+                realized_quote: dec("59.84008"),
+                unrealized_quote: dec("-0.5"),
+                open_base: dec("9.99"),
+                open_average_cost: Some(dec("500") / dec("9.99")),
+                ..Default::default()
+            }
+        );
+
+        let total = Trade::profit(&trades).total_in_quote(&dec("50"));
+        assert_eq!(report.realized_quote + report.unrealized_quote, total);
+
+        assert_eq!(fifo(&trades[..2], &dec("50")).open_average_cost, None);
+        assert_eq!(fifo(&[], &dec("50")), PnlReport::default());
+    }
+
+    #[test]
+    fn test_fifo_partial_lots() {
+        let trades = vec![
+            Trade::with_buy(dec("10"), dec("2"), dec("20")),
+            Trade::with_buy(dec("20"), dec("2"), dec("40")),
+            Trade::with_sell(dec("30"), dec("3"), dec("90")),
+        ];
+
+        let report = fifo(&trades, &dec("25"));
+        assert_eq!(report.realized_quote, dec("50"));
+        assert_eq!(report.open_base, dec("1"));
+        assert_eq!(report.open_average_cost, Some(dec("20")));
+        assert_eq!(report.unrealized_quote, dec("5"));
+    }
+
+    #[test]
+    fn test_fifo_unmatched_sell() {
+        let trades = vec![
+            Trade::with_sell(dec("200"), dec("1"), dec("200")),
+            Trade::with_buy(dec("100"), dec("1"), dec("100")),
+            Trade::with_sell(dec("150"), dec("2"), dec("300")),
+        ];
+
+        let report = fifo(&trades, &dec("150"));
+        assert_eq!(report.realized_quote, dec("50"));
+        assert_eq!(report.unmatched_sell_base, dec("2"));
+        assert_eq!(report.unmatched_sell_quote, dec("350"));
+        assert_eq!(report.open_base, dec("0"));
+        assert_eq!(report.unrealized_quote, dec("0"));
+    }
+}
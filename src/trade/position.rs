@@ -1,345 +1,2818 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 
-use crate::math::Range;
-use crate::types::{BaseQuantity, Price, QuoteQuantity};
+use crate::math::{self, Bound, MathError, PriceRange, Range};
+use crate::types::{BaseQuantity, Decimal, Price, QuoteQuantity};
 
-use super::{Executor, Trade, Trader};
+use super::evaluate::Evaluate;
+use super::{
+    Executor, SplitError, SymbolExecutor, Trade, TradeError, TradeKind, TradeSide, Trader,
+};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+// The most decimal places a `Decimal` holds, slices of a split are not truncated
+const MAX_SCALE: u32 = 28;
+
+// The price ranges are kept sorted and disjoint so `is_within_*` can binary search them.
+// The constructors, `normalize_prices` and deserializing establish that, a struct literal
+// listing ranges out of order or overlapping should call `normalize_prices`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
+    #[serde(
+        serialize_with = "serialize_sorted",
+        deserialize_with = "deserialize_sorted"
+    )]
     pub buying_prices: Vec<Range<Price>>,
+
+    #[serde(
+        serialize_with = "serialize_sorted",
+        deserialize_with = "deserialize_sorted"
+    )]
     pub selling_prices: Vec<Range<Price>>,
+
     pub base_quantity: BaseQuantity,
     pub quote_quantity: QuoteQuantity,
+
+    // Exclusive keeps neighbouring grid levels from both claiming a shared price
+    #[serde(default, skip_serializing_if = "Bound::is_inclusive")]
+    pub buying_upper_bound: Bound,
+
+    // Exchange lot size, sells are floored onto it before reaching the trader
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_step: Option<BaseQuantity>,
+
+    // Copied onto the trades `trap` produces unless the trader already tagged them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+
+    // Identify the position in logs, the id is also copied onto its trades like the symbol
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    // Orders below these minimums are dust, `trap` skips them rather than sending them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_base_quantity: Option<BaseQuantity>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_quote_quantity: Option<QuoteQuantity>,
+
+    // At or below this price `trap` sells the whole base, apart from the selling ranges
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_loss: Option<Price>,
+
+    // Whether a fired stop halts the position, a halted position no longer trades
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub halt_on_stop: bool,
+
+    // Which side `trap` trades next, kept across restarts. Unset it follows the quantities,
+    // see `state`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<PositionState>,
+
+    // Trade whichever side the price is in regardless of `state`, as `trap` used to. A
+    // halted position still stays out.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ignore_state: bool,
+
+    // What `trap` does at a price inside both a selling and a buying range
+    #[serde(default, skip_serializing_if = "ChurnPolicy::is_allow")]
+    pub churn_policy: ChurnPolicy,
 }
 
-impl Position {
-    pub fn is_short(&self) -> bool {
-        self.base_quantity.is_zero()
+// What `dedup_positions` compares, the ranges in sorted order and the quantities. Ids,
+// labels, state and the other settings are left out so the same band from different runs or
+// strategies counts once, and scale is ignored like in `Decimal`.
+#[derive(PartialEq, Eq, Hash)]
+struct DedupKey {
+    buying_prices: Vec<Range<Price>>,
+    selling_prices: Vec<Range<Price>>,
+    base_quantity: BaseQuantity,
+    quote_quantity: QuoteQuantity,
+}
+
+impl From<&Position> for DedupKey {
+    fn from(position: &Position) -> Self {
+        let mut buying_prices = position.buying_prices.clone();
+        let mut selling_prices = position.selling_prices.clone();
+        math::sort_ranges(&mut buying_prices);
+        math::sort_ranges(&mut selling_prices);
+
+        Self {
+            buying_prices,
+            selling_prices,
+            base_quantity: position.base_quantity,
+            quote_quantity: position.quote_quantity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PositionState {
+    // Only buys, a buy moves the position to `Holding`
+    #[default]
+    #[serde(rename = "WAITING_TO_BUY")]
+    WaitingToBuy,
+
+    // Only sells, a sell moves the position back to `WaitingToBuy`
+    #[serde(rename = "HOLDING")]
+    Holding,
+
+    // Set by a stop with `halt_on_stop`, the position no longer trades
+    #[serde(rename = "HALTED")]
+    Halted,
+}
+
+impl PositionState {
+    pub fn is_halted(&self) -> bool {
+        matches!(self, Self::Halted)
     }
+}
 
-    pub fn max_buying_price(&self) -> &Price {
-        let mut max_buy_price = &Price::ZERO;
-        for range in self.buying_prices.iter() {
-            if range.max() > max_buy_price {
-                max_buy_price = range.max();
-            }
+fn serialize_sorted<S>(ranges: &[Range<Price>], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let mut ranges = ranges.to_vec();
+    math::sort_ranges(&mut ranges);
+    ranges.serialize(serializer)
+}
+
+// Read back sorted and disjoint, overlapping ranges of a hand-edited file are coalesced
+fn deserialize_sorted<'de, D>(deserializer: D) -> Result<Vec<Range<Price>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(math::merge_ranges(Vec::deserialize(deserializer)?))
+}
+
+// Whether `value` lies in any of `ranges`, sorted and disjoint as `Position` keeps them.
+// Coalescing touching ranges keeps the answer, a shared price lies in the upper one anyway.
+fn is_within_any(ranges: &[Range<Price>], value: &Price, upper: Bound) -> bool {
+    // Only the last range starting at or below the value can hold it
+    match ranges.partition_point(|range| range.min() <= value) {
+        0 => false,
+        index => ranges[index - 1].is_within_bounds(value, Bound::Inclusive, upper),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChurnPolicy {
+    // Sell and then buy back with the proceeds, paying both commissions
+    #[default]
+    #[serde(rename = "ALLOW")]
+    Allow,
+
+    #[serde(rename = "PREFER_SELL")]
+    PreferSell,
+
+    #[serde(rename = "PREFER_BUY")]
+    PreferBuy,
+
+    // Neither side trades until the price leaves the overlap
+    #[serde(rename = "SKIP")]
+    Skip,
+}
+
+impl ChurnPolicy {
+    pub fn is_allow(&self) -> bool {
+        matches!(self, Self::Allow)
+    }
+
+    // Whether to sell and whether to buy, given the ranges the price is within
+    fn resolve(&self, sell: bool, buy: bool) -> (bool, bool) {
+        match (self, sell && buy) {
+            (_, false) | (Self::Allow, true) => (sell, buy),
+            (Self::PreferSell, true) => (true, false),
+            (Self::PreferBuy, true) => (false, true),
+            (Self::Skip, true) => (false, false),
+        }
+    }
+}
+
+impl Position {
+    pub fn new(
+        buying_prices: Vec<PriceRange>,
+        selling_prices: Vec<PriceRange>,
+        base_quantity: BaseQuantity,
+        quote_quantity: QuoteQuantity,
+    ) -> Self {
+        let ranges = |ranges: Vec<PriceRange>| {
+            math::merge_ranges(ranges.into_iter().map(Range::from).collect())
+        };
+
+        Self {
+            buying_prices: ranges(buying_prices),
+            selling_prices: ranges(selling_prices),
+            base_quantity,
+            quote_quantity,
+            ..Default::default()
         }
+    }
+
+    // Like `new` for unchecked ranges, rejecting a position that could never trade sensibly.
+    // With `require_spread` every buying price must also lie below every selling price.
+    pub fn try_new(
+        buying_prices: Vec<Range<Price>>,
+        selling_prices: Vec<Range<Price>>,
+        base_quantity: BaseQuantity,
+        quote_quantity: QuoteQuantity,
+        require_spread: bool,
+    ) -> Result<Self, PositionError> {
+        let position = Self {
+            buying_prices: math::merge_ranges(buying_prices),
+            selling_prices: math::merge_ranges(selling_prices),
+            base_quantity,
+            quote_quantity,
+            ..Default::default()
+        };
+        position.validate(require_spread)?;
 
-        max_buy_price
+        Ok(position)
     }
 
-    pub fn min_selling_price(&self) -> &Price {
-        let mut min_sell_price = &Price::MAX;
-        for range in self.selling_prices.iter() {
-            if range.min() < min_sell_price {
-                min_sell_price = range.min();
+    pub fn validate(&self, require_spread: bool) -> Result<(), PositionError> {
+        if self.base_quantity < BaseQuantity::ZERO {
+            return Err(PositionError::NegativeBaseQuantity(self.base_quantity));
+        }
+
+        if self.quote_quantity < QuoteQuantity::ZERO {
+            return Err(PositionError::NegativeQuoteQuantity(self.quote_quantity));
+        }
+
+        if self.buying_prices.is_empty() && self.selling_prices.is_empty() {
+            return Err(PositionError::NoPrices);
+        }
+
+        let mut ranges = self.buying_prices.iter().chain(self.selling_prices.iter());
+        if let Some(range) = ranges.find(|range| *range.min() < Price::ZERO) {
+            return Err(PositionError::NegativePrice(range.clone()));
+        }
+
+        if let (Some(max_buying), Some(min_selling)) =
+            (self.max_buying_price(), self.min_selling_price())
+        {
+            if require_spread && max_buying >= min_selling {
+                return Err(PositionError::CrossedPrices {
+                    max_buying: *max_buying,
+                    min_selling: *min_selling,
+                });
             }
         }
 
-        min_sell_price
+        Ok(())
+    }
+
+    // The stored state, or without one `Holding` while there is base and `WaitingToBuy`
+    // otherwise. Struct literals and files from before the state existed resume that way.
+    pub fn state(&self) -> PositionState {
+        self.state.unwrap_or(match self.base_quantity.is_zero() {
+            true => PositionState::WaitingToBuy,
+            false => PositionState::Holding,
+        })
+    }
+
+    pub fn is_short(&self) -> bool {
+        self.base_quantity.is_zero()
+    }
+
+    // `None` without buying prices
+    pub fn max_buying_price(&self) -> Option<&Price> {
+        self.buying_prices.iter().map(|range| range.max()).max()
+    }
+
+    // `None` without selling prices
+    pub fn min_selling_price(&self) -> Option<&Price> {
+        self.selling_prices.iter().map(|range| range.min()).min()
+    }
+
+    // What `max_buying_price` returned before it became optional
+    pub fn max_buying_price_or_zero(&self) -> &Price {
+        self.max_buying_price().unwrap_or(&Price::ZERO)
+    }
+
+    // What `min_selling_price` returned before it became optional, `Price::MAX` when empty
+    pub fn min_selling_price_or_max(&self) -> &Price {
+        self.min_selling_price().unwrap_or(&Price::MAX)
     }
 
     pub fn is_within_buying_price(&self, value: &Price) -> bool {
-        if self.buying_prices.is_empty() {
+        is_within_any(&self.buying_prices, value, self.buying_upper_bound)
+    }
+
+    pub fn is_within_selling_price(&self, value: &Price) -> bool {
+        is_within_any(&self.selling_prices, value, Bound::Inclusive)
+    }
+
+    // Floors both quantities onto their step sizes and returns the dust shaved off
+    pub fn round_quantities(
+        &mut self,
+        step_base: &BaseQuantity,
+        step_quote: &QuoteQuantity,
+    ) -> Result<(BaseQuantity, QuoteQuantity), MathError> {
+        let base_quantity = math::round_to_step(self.base_quantity, *step_base)?;
+        let quote_quantity = math::round_to_step(self.quote_quantity, *step_quote)?;
+        let dust = (
+            self.base_quantity - base_quantity,
+            self.quote_quantity - quote_quantity,
+        );
+
+        self.base_quantity = base_quantity;
+        self.quote_quantity = quote_quantity;
+
+        Ok(dust)
+    }
+
+    pub fn selling_quantity(&self) -> Result<BaseQuantity, MathError> {
+        match self.base_step {
+            Some(step) => math::round_to_step(self.base_quantity, step),
+            None => Ok(self.base_quantity),
+        }
+    }
+
+    // The lowest price at which selling all base, less `sell_fee`, brings the quote back to
+    // `initial_quote`, zero once the quote alone covers it. `None` without base to sell or
+    // when the fee takes everything.
+    pub fn break_even_price(
+        &self,
+        initial_quote: &QuoteQuantity,
+        sell_fee: &Decimal,
+    ) -> Option<Price> {
+        let proceeds = self.base_quantity * (Decimal::ONE - sell_fee);
+        if proceeds <= Decimal::ZERO {
+            return None;
+        }
+
+        let missing = *initial_quote - self.quote_quantity;
+        if missing <= Decimal::ZERO {
+            return Some(Price::ZERO);
+        }
+
+        missing.checked_div(proceeds)
+    }
+
+    // Whether even the lowest selling price recovers `initial_quote` after fees
+    pub fn is_break_even_reachable(
+        &self,
+        initial_quote: &QuoteQuantity,
+        sell_fee: &Decimal,
+    ) -> bool {
+        let Some(min_selling) = self.min_selling_price() else {
             return false;
+        };
+
+        self.break_even_price(initial_quote, sell_fee)
+            .is_some_and(|price| *min_selling >= price)
+    }
+
+    // Quote gained by one sell, buy, sell cycle as `min_profit_trades` runs it, once at the
+    // conservative prices it uses and once at the opposite ends of the ranges. Held base is
+    // liquidated first and counts as already paid for.
+    pub fn expected_profit(&self, buy_fee: &Decimal, sell_fee: &Decimal) -> ExpectedProfit {
+        let buying = self.buying_prices.iter();
+        let selling = self.selling_prices.iter();
+
+        let cycle = |buy: Option<&Price>, sell: Option<&Price>| {
+            let Some(sell) = sell else {
+                return QuoteQuantity::ZERO;
+            };
+
+            let liquidated =
+                self.quote_quantity + (self.base_quantity * sell) * (Decimal::ONE - sell_fee);
+            let profit = match buy {
+                Some(buy) if *buy > Price::ZERO => {
+                    let base_quantity = (liquidated / buy) * (Decimal::ONE - buy_fee);
+                    (base_quantity * sell) * (Decimal::ONE - sell_fee)
+                }
+                _ => liquidated,
+            };
+
+            profit - self.quote_quantity
+        };
+
+        ExpectedProfit {
+            conservative: cycle(
+                buying.clone().map(|range| range.max()).max(),
+                selling.clone().map(|range| range.min()).min(),
+            ),
+            optimistic: cycle(
+                buying.map(|range| range.min()).min(),
+                selling.map(|range| range.max()).max(),
+            ),
+        }
+    }
+
+    // One position holding both quantities and trading over both sets of ranges. The other
+    // settings are kept from `self`, the state follows the merged base.
+    pub fn merge(&self, other: &Position) -> Result<Position, MergeError> {
+        if let (Some(a), Some(b)) = (&self.symbol, &other.symbol) {
+            if a != b {
+                return Err(MergeError::SymbolConflict(a.clone(), b.clone()));
+            }
         }
 
-        for range in self.buying_prices.iter() {
-            if range.is_within(value) {
-                return true;
+        if let (Some(a), Some(b)) = (self.id, other.id) {
+            if a != b {
+                return Err(MergeError::IdConflict(a, b));
             }
+        }
 
-            continue;
+        if self.state().is_halted() || other.state().is_halted() {
+            return Err(MergeError::Halted);
         }
 
-        false
+        let ranges = |a: &Vec<Range<Price>>, b: &Vec<Range<Price>>| {
+            math::merge_ranges(a.iter().chain(b.iter()).cloned().collect())
+        };
+
+        Ok(Position {
+            buying_prices: ranges(&self.buying_prices, &other.buying_prices),
+            selling_prices: ranges(&self.selling_prices, &other.selling_prices),
+            base_quantity: self.base_quantity + other.base_quantity,
+            quote_quantity: self.quote_quantity + other.quote_quantity,
+            symbol: self.symbol.clone().or_else(|| other.symbol.clone()),
+            id: self.id.or(other.id),
+            state: None,
+            ..self.clone()
+        })
     }
 
-    pub fn is_within_selling_price(&self, value: &Price) -> bool {
-        if self.selling_prices.is_empty() {
-            return false;
+    // `n` positions each buying in its own slice of the first buying range, with an equal
+    // share of both quantities. The last takes the remainders so the totals are exact, any
+    // other buying ranges and the selling ranges are shared. The parts carry no id or label,
+    // they would all claim the parent's, so the caller numbers them as a grid does.
+    pub fn split(&self, n: usize) -> Result<Vec<Position>, SplitError> {
+        match n {
+            0 => return Err(SplitError::NoParts),
+            1 => return Ok(vec![self.clone()]),
+            _ => {}
+        }
+
+        let bands = match self.buying_prices.first() {
+            Some(range) => range.split_evenly(n, MAX_SCALE),
+            None => vec![],
+        };
+        if bands.len() != n && !self.buying_prices.is_empty() {
+            return Err(SplitError::Indivisible(n));
+        }
+
+        let parts = Decimal::from(n);
+        let base_quantity = self.base_quantity / parts;
+        let quote_quantity = self.quote_quantity / parts;
+
+        let mut positions = Vec::with_capacity(n);
+        for i in 0..n {
+            let mut position = Position {
+                id: None,
+                label: None,
+                ..self.clone()
+            };
+            if let Some(band) = bands.get(i) {
+                position.buying_prices[0] = band.clone();
+            }
+
+            let rest = Decimal::from(n - 1);
+            (position.base_quantity, position.quote_quantity) = match i + 1 == n {
+                true => (
+                    self.base_quantity - base_quantity * rest,
+                    self.quote_quantity - quote_quantity * rest,
+                ),
+                false => (base_quantity, quote_quantity),
+            };
+
+            positions.push(position);
         }
 
-        for range in self.selling_prices.iter() {
-            if range.is_within(value) {
-                return true;
+        Ok(positions)
+    }
+
+    // Shifts every range and the stop so the midpoint of all prices lands on `new_mid`,
+    // the quantities are untouched
+    pub fn recenter(&mut self, new_mid: &Price) -> Result<(), RecenterError> {
+        recenter_all(std::slice::from_mut(self), new_mid)
+    }
+
+    // Distance between the closest buying ranges of both positions, zero when they overlap
+    fn buying_gap(&self, other: &Position) -> Option<Decimal> {
+        let mut gaps = self.buying_prices.iter().flat_map(|a| {
+            other.buying_prices.iter().map(move |b| {
+                (*b.min() - *a.max())
+                    .max(*a.min() - *b.max())
+                    .max(Decimal::ZERO)
+            })
+        });
+
+        gaps.next().map(|first| gaps.fold(first, Decimal::min))
+    }
+
+    pub fn has_degenerate_prices(&self) -> bool {
+        let mut ranges = self.buying_prices.iter().chain(self.selling_prices.iter());
+        ranges.any(|range| range.is_degenerate())
+    }
+
+    pub fn is_disjoint_prices(&self) -> bool {
+        for buying in self.buying_prices.iter() {
+            for selling in self.selling_prices.iter() {
+                if buying.overlaps(selling) {
+                    return false;
+                }
             }
+        }
+
+        true
+    }
+
+    pub fn normalize_prices(&mut self) {
+        self.buying_prices = math::merge_ranges(std::mem::take(&mut self.buying_prices));
+        self.selling_prices = math::merge_ranges(std::mem::take(&mut self.selling_prices));
+    }
+
+    pub fn shift_prices(&mut self, delta: Price) {
+        for range in self.buying_prices.iter_mut() {
+            range.shift_mut(delta);
+        }
+
+        for range in self.selling_prices.iter_mut() {
+            range.shift_mut(delta);
+        }
+    }
 
-            continue;
+    // Ranges grown into each other are coalesced, see `normalize_prices`
+    pub fn expand_buying(&mut self, pct: Decimal) {
+        for range in self.buying_prices.iter_mut() {
+            *range = range.expand_percent(pct);
         }
+        self.buying_prices = math::merge_ranges(std::mem::take(&mut self.buying_prices));
+    }
 
-        false
+    pub fn expand_selling(&mut self, pct: Decimal) {
+        for range in self.selling_prices.iter_mut() {
+            *range = range.expand_percent(pct);
+        }
+        self.selling_prices = math::merge_ranges(std::mem::take(&mut self.selling_prices));
     }
 
+    // Trader failures are passed through as `TradeError::QuoteFailed`, the cycle needs both
+    // buying and selling prices
     pub async fn min_profit_trades(
         &mut self,
         agent: &impl Trader,
-    ) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let buying_price = self.max_buying_price();
-        let selling_price = self.min_selling_price();
+    ) -> Result<Vec<Trade>, TradeError<Box<dyn Error>>> {
+        let mut ranges = self.buying_prices.iter().chain(self.selling_prices.iter());
+        if let Some(range) = ranges.find(|range| *range.min() < Price::ZERO) {
+            return Err(TradeError::NegativePrice(*range.min()));
+        }
 
-        let prices = vec![*selling_price, *buying_price, *selling_price];
+        let buying_price = *self
+            .max_buying_price()
+            .ok_or(TradeError::InvalidPosition(PositionError::NoBuyingPrices))?;
+        let selling_price = *self
+            .min_selling_price()
+            .ok_or(TradeError::InvalidPosition(PositionError::NoSellingPrices))?;
+
+        let prices = [selling_price, buying_price, selling_price];
 
         let mut trades = Vec::new();
         for price in prices.iter() {
-            trades.extend(self.trap(agent, &price).await?);
+            let traded = self.trap(agent, price).await;
+            trades.extend(traded.map_err(TradeError::QuoteFailed)?);
         }
 
         Ok(trades)
     }
 }
 
-impl Executor for Position {
-    async fn trap(
-        &mut self,
-        agent: &impl Trader,
-        price: &Price,
-    ) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
-
-        if self.is_within_selling_price(price) && !self.base_quantity.is_zero() {
-            trades.extend(agent.sell(price, &self.base_quantity).await?);
+impl Position {
+    // Fills in what the trader left unset, the symbol, position id, kind and trigger price
+    fn tag_trades(&self, mut trades: Vec<Trade>, price: &Price, kind: TradeKind) -> Vec<Trade> {
+        for trade in trades.iter_mut() {
+            if trade.kind.is_none() {
+                trade.kind = Some(kind);
+            }
 
-            for trade in trades.iter() {
-                self.base_quantity -= trade.base_quantity;
-                self.quote_quantity += trade.quote_quantity;
+            if trade.symbol.is_none() {
+                trade.symbol.clone_from(&self.symbol);
             }
-        }
 
-        if self.is_within_buying_price(price) && !self.quote_quantity.is_zero() {
-            trades.extend(agent.buy(price, &self.quote_quantity).await?);
+            if trade.position_id.is_none() {
+                trade.position_id = self.id;
+            }
 
-            for trade in trades.iter() {
-                self.base_quantity += trade.base_quantity;
-                self.quote_quantity -= trade.quote_quantity;
+            if trade.requested_price.is_none() {
+                trade.requested_price = Some(*price);
             }
         }
 
-        Ok(trades)
+        trades
     }
 }
 
-impl Executor for Vec<Position> {
-    async fn trap(
-        &mut self,
-        agent: &impl Trader,
-        price: &Price,
-    ) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
+#[derive(Debug)]
+pub struct TrapError {
+    pub executed: Vec<Trade>, // Made before the failure but not applied to the position
+    pub source: Box<dyn Error>,
+}
 
-        for position in self.iter_mut() {
-            trades.extend(position.trap(agent, price).await?);
-        }
+impl fmt::Display for TrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trap failed after {} trades: {}",
+            self.executed.len(),
+            self.source
+        )
+    }
+}
 
-        Ok(trades)
+impl std::error::Error for TrapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
     }
 }
 
-#[cfg(test)]
-mod tests_position {
-    use std::error::Error;
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecenterError {
+    pub clamped: Vec<usize>, // Positions with prices that would have gone below zero
+}
 
-    use crate::math::Range;
-    use crate::trade::{Executor, Trader};
-    use crate::types::{BaseQuantity, Decimal, Price, QuoteQuantity};
+impl fmt::Display for RecenterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "prices clamped at zero for positions {:?}", self.clamped)
+    }
+}
 
-    use super::Position;
-    use super::Trade;
+impl std::error::Error for RecenterError {}
 
-    struct TradeAgent {
-        commission: Decimal,
-    }
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeError {
+    SymbolConflict(String, String),
+    IdConflict(u64, u64),
+    Halted,
+}
 
-    impl TradeAgent {
-        fn with_commission(value: &str) -> Self {
-            Self {
-                commission: dec(value),
-            }
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SymbolConflict(a, b) => write!(f, "cannot merge symbols {} and {}", a, b),
+            Self::IdConflict(a, b) => write!(f, "cannot merge position ids {} and {}", a, b),
+            Self::Halted => write!(f, "cannot merge a halted position"),
         }
     }
+}
 
-    impl Default for TradeAgent {
-        fn default() -> Self {
-            Self {
-                commission: dec("0"),
-            }
+impl std::error::Error for MergeError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PositionError {
+    NegativeBaseQuantity(BaseQuantity),
+    NegativeQuoteQuantity(QuoteQuantity),
+    NoPrices,
+    NoBuyingPrices,
+    NoSellingPrices,
+    NegativePrice(Range<Price>),
+    InvalidFraction(Decimal),
+    CrossedPrices {
+        max_buying: Price,
+        min_selling: Price,
+    },
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NegativeBaseQuantity(v) => write!(f, "base_quantity {} is negative", v),
+            Self::NegativeQuoteQuantity(v) => write!(f, "quote_quantity {} is negative", v),
+            Self::NoPrices => write!(f, "position has neither buying nor selling prices"),
+            Self::NoBuyingPrices => write!(f, "position has no buying prices"),
+            Self::NoSellingPrices => write!(f, "position has no selling prices"),
+            Self::NegativePrice(range) => write!(f, "price range {} is negative", range),
+            Self::InvalidFraction(v) => write!(f, "fill fraction {} is not within (0, 1]", v),
+            Self::CrossedPrices {
+                max_buying,
+                min_selling,
+            } => write!(
+                f,
+                "buying price {} is not below selling price {}",
+                max_buying, min_selling
+            ),
         }
     }
+}
 
-    impl Trader for TradeAgent {
-        async fn buy(
-            &self,
-            price: &Price,
-            quote_quantity: &QuoteQuantity,
-        ) -> Result<Vec<Trade>, Box<dyn Error>> {
-            if price > &Decimal::ZERO {
-                let base_quantity = (quote_quantity / price) * (Decimal::ONE - self.commission);
+impl std::error::Error for PositionError {}
 
-                return Ok(vec![Trade::with_buy(
-                    price.clone(),
-                    base_quantity,
-                    quote_quantity.clone(),
-                )]);
-            };
+// Opt-in validation on load without the spread check, the way `ValidatedTrade` works
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Position", into = "Position")]
+pub struct ValidatedPosition(pub Position);
 
-            Err("Buy Trade Error")?
-        }
+impl TryFrom<Position> for ValidatedPosition {
+    type Error = PositionError;
 
-        async fn sell(
-            &self,
-            price: &Price,
-            base_quantity: &BaseQuantity,
-        ) -> Result<Vec<Trade>, Box<dyn Error>> {
-            if price > &Decimal::ZERO {
-                let quote_quantity = (base_quantity * price) * (Decimal::ONE - self.commission);
+    fn try_from(value: Position) -> Result<Self, Self::Error> {
+        value.validate(false)?;
+        Ok(Self(value))
+    }
+}
 
-                return Ok(vec![Trade::with_sell(
-                    price.clone(),
-                    base_quantity.clone(),
-                    quote_quantity.clone(),
-                )]);
-            };
+impl From<ValidatedPosition> for Position {
+    fn from(value: ValidatedPosition) -> Self {
+        value.0
+    }
+}
 
-            Err("Sell Trade Error")?
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpectedProfit {
+    pub conservative: QuoteQuantity, // Buying at the highest, selling at the lowest price
+    pub optimistic: QuoteQuantity,   // Buying at the lowest, selling at the highest price
+}
+
+// Lookups over a grid, called on a `Vec<Position>` or a slice of one like the executors
+pub trait PositionLookup {
+    fn find_by_id(&self, id: u64) -> Option<&Position>;
+}
+
+impl PositionLookup for [Position] {
+    fn find_by_id(&self, id: u64) -> Option<&Position> {
+        self.iter().find(|position| position.id == Some(id))
+    }
+}
+
+// Every pair of positions whose buying or selling ranges share a price, with the shared part.
+// Touching buying ranges do not count when the lower one excludes its upper bound.
+pub fn find_overlaps(positions: &[Position], side: TradeSide) -> Vec<(usize, usize, Range<Price>)> {
+    let ranges = |position: &Position| match side {
+        TradeSide::Buy => position.buying_prices.clone(),
+        TradeSide::Sell => position.selling_prices.clone(),
+    };
+    let excluded = |position: &Position, range: &Range<Price>, shared: &Range<Price>| {
+        side.is_buy() && !position.buying_upper_bound.is_inclusive() && range.max() == shared.min()
+    };
+
+    let mut overlaps = Vec::new();
+    for (i, a) in positions.iter().enumerate() {
+        for (j, b) in positions.iter().enumerate().skip(i + 1) {
+            for range_a in ranges(a).iter() {
+                for range_b in ranges(b).iter() {
+                    let Some(shared) = range_a.intersect(range_b) else {
+                        continue;
+                    };
+
+                    let touching = shared.is_degenerate()
+                        && (excluded(a, range_a, &shared) || excluded(b, range_b, &shared));
+                    if !touching {
+                        overlaps.push((i, j, shared));
+                    }
+                }
+            }
         }
     }
 
-    fn dec(value: &str) -> Decimal {
-        use std::str::FromStr;
-        Decimal::from_str(value).unwrap()
+    overlaps
+}
+
+// Only the buying ranges are checked, overlapping ones commit the same capital twice.
+// Selling ranges of a grid share the top of the band by design.
+pub fn assert_disjoint(positions: &[Position]) -> Result<(), OverlapError> {
+    let overlaps = find_overlaps(positions, TradeSide::Buy);
+    if !overlaps.is_empty() {
+        return Err(OverlapError { overlaps });
     }
 
-    #[tokio::test]
-    async fn test_min_profit_trades_with_short() {
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlapError {
+    pub overlaps: Vec<(usize, usize, Range<Price>)>, // Both position indices and the shared prices
+}
+
+impl fmt::Display for OverlapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "overlapping buying prices:")?;
+
+        for (a, b, range) in self.overlaps.iter() {
+            write!(f, " positions {} and {} at {}", a, b, range)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for OverlapError {}
+
+// Shifts the whole grid by one delta so the midpoint of all its prices lands on `new_mid`,
+// keeping the spacing between positions. Prices that would go below zero are clamped there
+// by `Range::shift`, the shift is still applied and the error lists those positions.
+pub fn recenter_all(positions: &mut [Position], new_mid: &Price) -> Result<(), RecenterError> {
+    let ranges = positions.iter().flat_map(|position| {
+        position
+            .buying_prices
+            .iter()
+            .chain(&position.selling_prices)
+    });
+    let min = ranges.clone().map(|range| *range.min()).min();
+    let max = ranges.map(|range| *range.max()).max();
+    let (Some(min), Some(max)) = (min, max) else {
+        return Ok(());
+    };
+
+    let delta = new_mid - Range(min, max).midpoint();
+    let mut clamped = Vec::new();
+    for (index, position) in positions.iter_mut().enumerate() {
+        let mut ranges = position
+            .buying_prices
+            .iter()
+            .chain(&position.selling_prices);
+        let below_zero = ranges.any(|range| *range.min() + delta < Price::ZERO)
+            || position
+                .stop_loss
+                .is_some_and(|stop| stop + delta < Price::ZERO);
+        if below_zero {
+            clamped.push(index);
+        }
+
+        position.shift_prices(delta);
+        position.stop_loss = position
+            .stop_loss
+            .map(|stop| (stop + delta).max(Price::ZERO));
+    }
+
+    if !clamped.is_empty() {
+        return Err(RecenterError { clamped });
+    }
+
+    Ok(())
+}
+
+// Folds each position into the first earlier one whose buying ranges come within `max_gap`
+// of its own. Positions that cannot merge are kept apart.
+pub fn merge_adjacent(positions: Vec<Position>, max_gap: Decimal) -> Vec<Position> {
+    let mut merged: Vec<Position> = Vec::with_capacity(positions.len());
+
+    'positions: for position in positions {
+        for earlier in merged.iter_mut() {
+            if earlier
+                .buying_gap(&position)
+                .is_none_or(|gap| gap > max_gap)
+            {
+                continue;
+            }
+
+            if let Ok(consolidated) = earlier.merge(&position) {
+                *earlier = consolidated;
+                continue 'positions;
+            }
+        }
+
+        merged.push(position);
+    }
+
+    merged
+}
+
+// Keeps the first of each position trading the same ranges with the same quantities, see
+// `DedupKey` for what is compared
+pub fn dedup_positions(positions: Vec<Position>) -> Vec<Position> {
+    let mut seen = HashSet::with_capacity(positions.len());
+    positions
+        .into_iter()
+        .filter(|position| seen.insert(DedupKey::from(position)))
+        .collect()
+}
+
+impl Executor for Position {
+    async fn trap(
+        &mut self,
+        agent: &impl Trader,
+        price: &Price,
+    ) -> Result<Vec<Trade>, Box<dyn Error>> {
+        self.trap_with_fraction(agent, price, Decimal::ONE).await
+    }
+}
+
+impl Position {
+    // Like `trap`, but only `fraction` of the base or quote goes to the trader and the rest
+    // stays for later prices. The fraction must lie within (0, 1].
+    pub async fn trap_with_fraction(
+        &mut self,
+        agent: &impl Trader,
+        price: &Price,
+        fraction: Decimal,
+    ) -> Result<Vec<Trade>, Box<dyn Error>> {
+        if fraction <= Decimal::ZERO || fraction > Decimal::ONE {
+            return Err(Box::new(PositionError::InvalidFraction(fraction)));
+        }
+
+        let mut trades = Vec::new();
+        self.trap_into(agent, price, fraction, &mut trades).await?;
+
+        Ok(trades)
+    }
+
+    // Like `trap`, but the position only changes once every order went through. On an error
+    // it is left as it was and the trades already made come back with the error.
+    pub async fn try_trap(
+        &mut self,
+        agent: &impl Trader,
+        price: &Price,
+    ) -> Result<Vec<Trade>, TrapError> {
+        let mut working = self.clone();
+        let mut trades = Vec::new();
+
+        match working
+            .trap_into(agent, price, Decimal::ONE, &mut trades)
+            .await
+        {
+            Ok(()) => {
+                *self = working;
+                Ok(trades)
+            }
+            Err(source) => Err(TrapError {
+                executed: trades,
+                source,
+            }),
+        }
+    }
+
+    // Trades go into `trades` as soon as the trader confirms them, so a caller still has
+    // them when a later order fails
+    async fn trap_into(
+        &mut self,
+        agent: &impl Trader,
+        price: &Price,
+        fraction: Decimal,
+        trades: &mut Vec<Trade>,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.state().is_halted() {
+            return Ok(());
+        }
+
+        let stop_quantity = self.selling_quantity()?;
+        let stopped = self.stop_loss.is_some_and(|stop| *price <= stop);
+        if stopped && !is_dust(stop_quantity, self.min_base_quantity) {
+            let sold = agent.sell(price, &stop_quantity).await?;
+            let sold = self.tag_trades(sold, price, TradeKind::StopLoss);
+
+            for trade in sold.iter() {
+                self.base_quantity -= trade.base_quantity;
+                self.quote_quantity += trade.quote_quantity;
+            }
+            trades.extend(sold);
+            self.state = Some(PositionState::WaitingToBuy);
+
+            if self.halt_on_stop {
+                self.state = Some(PositionState::Halted);
+                return Ok(());
+            }
+        }
+
+        // Holding only sells and waiting only buys, though a partly filled wait may also take
+        // profit on the base it already holds. A price in both ranges while holding sells and
+        // buys back as the churn policy decides, the sell ends the holding.
+        let state = self.state();
+        let partly_filled = state == PositionState::WaitingToBuy
+            && !is_dust(self.base_quantity, self.min_base_quantity);
+        let sell = self.is_within_selling_price(price)
+            && (self.ignore_state || state == PositionState::Holding || partly_filled);
+        let buy = self.is_within_buying_price(price)
+            && (self.ignore_state || state == PositionState::WaitingToBuy || sell);
+        let (sell, buy) = self.churn_policy.resolve(sell, buy);
+
+        let selling_quantity = match self.base_step {
+            Some(step) => math::round_to_step(self.base_quantity * fraction, step)?,
+            None => self.base_quantity * fraction,
+        };
+        if sell && !is_dust(selling_quantity, self.min_base_quantity) {
+            let sold = agent.sell(price, &selling_quantity).await?;
+            let sold = self.tag_trades(sold, price, TradeKind::TakeProfit);
+
+            for trade in sold.iter() {
+                self.base_quantity -= trade.base_quantity;
+                self.quote_quantity += trade.quote_quantity;
+            }
+            // Partial sells keep the position selling
+            if !sold.is_empty() && fraction == Decimal::ONE {
+                self.state = Some(PositionState::WaitingToBuy);
+            }
+            trades.extend(sold);
+        }
+
+        let buying_quantity = self.quote_quantity * fraction;
+        if buy && !is_dust(buying_quantity, self.min_quote_quantity) {
+            let bought = agent.buy(price, &buying_quantity).await?;
+            let bought = self.tag_trades(bought, price, TradeKind::Entry);
+
+            // Only the fills just received, the sells above are already applied
+            for trade in bought.iter() {
+                self.base_quantity += trade.base_quantity;
+                self.quote_quantity -= trade.quote_quantity;
+            }
+            // Partial buys keep the position buying, the rest of the quote still scales in
+            if !bought.is_empty() {
+                self.state = Some(match fraction == Decimal::ONE {
+                    true => PositionState::Holding,
+                    false => PositionState::WaitingToBuy,
+                });
+            }
+            trades.extend(bought);
+        }
+
+        Ok(())
+    }
+}
+
+fn is_dust(quantity: Decimal, minimum: Option<Decimal>) -> bool {
+    quantity.is_zero() || minimum.is_some_and(|minimum| quantity < minimum)
+}
+
+// Traps every position with the plain `trap`, not `try_trap`. A failing order stops the
+// grid and leaves that position with whatever the earlier orders applied.
+impl Executor for Vec<Position> {
+    async fn trap(
+        &mut self,
+        agent: &impl Trader,
+        price: &Price,
+    ) -> Result<Vec<Trade>, Box<dyn Error>> {
+        let trades = trap_attributed(self, agent, price).await?;
+        Ok(trades.into_iter().map(|(_, trade)| trade).collect())
+    }
+}
+
+impl Executor for &mut [Position] {
+    async fn trap(
+        &mut self,
+        agent: &impl Trader,
+        price: &Price,
+    ) -> Result<Vec<Trade>, Box<dyn Error>> {
+        let trades = trap_attributed(self, agent, price).await?;
+        Ok(trades.into_iter().map(|(_, trade)| trade).collect())
+    }
+}
+
+// Grids keyed by symbol, a symbol without positions trades nothing. The key is copied onto
+// trades that carry no symbol yet.
+impl SymbolExecutor for HashMap<String, Vec<Position>> {
+    async fn trap_symbol(
+        &mut self,
+        agent: &impl Trader,
+        symbol: &str,
+        price: &Price,
+    ) -> Result<Vec<Trade>, Box<dyn Error>> {
+        let Some(positions) = self.get_mut(symbol) else {
+            return Ok(Vec::new());
+        };
+
+        let mut trades = positions.trap(agent, price).await?;
+        for trade in trades.iter_mut() {
+            if trade.symbol.is_none() {
+                trade.symbol = Some(symbol.to_string());
+            }
+        }
+
+        Ok(trades)
+    }
+}
+
+// Like trapping the whole grid, each trade paired with the index of the position that made it
+pub async fn trap_attributed(
+    positions: &mut [Position],
+    agent: &impl Trader,
+    price: &Price,
+) -> Result<Vec<(usize, Trade)>, Box<dyn Error>> {
+    let mut trades = Vec::new();
+
+    for (index, position) in positions.iter_mut().enumerate() {
+        let trapped = position.trap(agent, price).await?;
+        trades.extend(trapped.into_iter().map(|trade| (index, trade)));
+    }
+
+    Ok(trades)
+}
+
+// One report per position, in the order of `positions`, from the trades attributed to it
+pub fn evaluate_per_position(positions: &[Position], trades: &[(usize, Trade)]) -> Vec<Evaluate> {
+    let mut reports = vec![Evaluate::default(); positions.len()];
+    for (index, trade) in trades.iter() {
+        if let Some(report) = reports.get_mut(*index) {
+            report.update(trade);
+        }
+    }
+
+    reports
+}
+
+#[cfg(test)]
+mod tests_position {
+    use std::collections::HashMap;
+    use std::error::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::math::{self, Bound, PriceRange, Range};
+    use crate::trade::evaluate::Evaluater;
+    use crate::trade::{Executor, SymbolExecutor, TradeError, TradeKind, Trader};
+    use crate::types::{BaseQuantity, Decimal, Price, QuoteQuantity};
+
+    use super::Trade;
+    use super::{
+        assert_disjoint, dedup_positions, evaluate_per_position, find_overlaps, merge_adjacent,
+        recenter_all, trap_attributed, ChurnPolicy, ExpectedProfit, MergeError, OverlapError,
+        Position, PositionError, PositionLookup, PositionState, RecenterError, SplitError,
+        TradeSide, ValidatedPosition,
+    };
+
+    struct TradeAgent {
+        commission: Decimal,
+    }
+
+    impl TradeAgent {
+        fn with_commission(value: &str) -> Self {
+            Self {
+                commission: dec(value),
+            }
+        }
+    }
+
+    impl Default for TradeAgent {
+        fn default() -> Self {
+            Self {
+                commission: dec("0"),
+            }
+        }
+    }
+
+    impl Trader for TradeAgent {
+        async fn buy(
+            &self,
+            price: &Price,
+            quote_quantity: &QuoteQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            if price > &Decimal::ZERO {
+                let base_quantity = (quote_quantity / price) * (Decimal::ONE - self.commission);
+
+                return Ok(vec![Trade::with_buy(
+                    *price,
+                    base_quantity,
+                    *quote_quantity,
+                )]);
+            };
+
+            Err("Buy Trade Error")?
+        }
+
+        async fn sell(
+            &self,
+            price: &Price,
+            base_quantity: &BaseQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            if price > &Decimal::ZERO {
+                let quote_quantity = (base_quantity * price) * (Decimal::ONE - self.commission);
+
+                return Ok(vec![Trade::with_sell(
+                    *price,
+                    *base_quantity,
+                    quote_quantity,
+                )]);
+            };
+
+            Err("Sell Trade Error")?
+        }
+    }
+
+    // Fills like the commission-free `TradeAgent` but fails the second order it gets
+    #[derive(Default)]
+    struct FailingAgent {
+        calls: AtomicUsize,
+    }
+
+    impl FailingAgent {
+        fn check(&self) -> Result<(), Box<dyn Error>> {
+            match self.calls.fetch_add(1, Ordering::SeqCst) {
+                1 => Err("Second Trade Error")?,
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Trader for FailingAgent {
+        async fn buy(
+            &self,
+            price: &Price,
+            quote_quantity: &QuoteQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            self.check()?;
+            TradeAgent::default().buy(price, quote_quantity).await
+        }
+
+        async fn sell(
+            &self,
+            price: &Price,
+            base_quantity: &BaseQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            self.check()?;
+            TradeAgent::default().sell(price, base_quantity).await
+        }
+    }
+
+    // Fills every order in two parts, a quarter first and the rest after
+    struct PartialAgent;
+
+    impl Trader for PartialAgent {
+        async fn buy(
+            &self,
+            price: &Price,
+            quote_quantity: &QuoteQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            let first = quote_quantity / dec("4");
+            let rest = quote_quantity - first;
+            Ok(vec![
+                Trade::with_buy(*price, first / price, first),
+                Trade::with_buy(*price, rest / price, rest),
+            ])
+        }
+
+        async fn sell(
+            &self,
+            price: &Price,
+            base_quantity: &BaseQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            let first = base_quantity / dec("4");
+            let rest = base_quantity - first;
+            Ok(vec![
+                Trade::with_sell(*price, first, first * price),
+                Trade::with_sell(*price, rest, rest * price),
+            ])
+        }
+    }
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_min_profit_trades_with_short() {
         let position = Position {
             buying_prices: vec![Range(dec("30"), dec("50"))],
             selling_prices: vec![Range(dec("200"), dec("250"))],
             base_quantity: dec("0.0"),
             quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+
+        let agent = TradeAgent::default();
+
+        let trades = position.clone().min_profit_trades(&agent).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                Trade::with_buy(dec("50"), dec("0.4"), dec("20.0")),
+                Trade::with_sell(dec("200"), dec("0.4"), dec("80.0"))
+            ]
+        );
+
+        let agent = TradeAgent::with_commission("0.001");
+        let trades = position.clone().min_profit_trades(&agent).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+                Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_break_even_price() {
+        let fee = dec("0.001");
+        // What is left after buying 0.3996 base for the whole 20 quote at 50 with 0.1% commission
+        let mut position = Position {
+            buying_prices: vec![Range(dec("40"), dec("50"))],
+            selling_prices: vec![Range(dec("50.1"), dec("60"))],
+            base_quantity: dec("0.3996"),
+            quote_quantity: dec("0"),
+            ..Default::default()
+        };
+
+        let price = position.break_even_price(&dec("20"), &fee).unwrap();
+        assert_eq!(price.round_dp(8), dec("50.10015020"));
+        let ceiled = price.round_dp_with_strategy(8, rust_decimal::RoundingStrategy::AwayFromZero);
+        assert!(position.base_quantity * ceiled * (Decimal::ONE - fee) >= dec("20"));
+        assert!(!position.is_break_even_reachable(&dec("20"), &fee));
+        assert!(position.is_break_even_reachable(&dec("20"), &dec("0")));
+
+        position.selling_prices = vec![Range(dec("50.11"), dec("60"))];
+        assert!(position.is_break_even_reachable(&dec("20"), &fee));
+
+        // Quote left over already covers part of the investment
+        position.quote_quantity = dec("10");
+        let price = position.break_even_price(&dec("20"), &fee).unwrap();
+        assert_eq!(price.round_dp(8), dec("25.05007510"));
+
+        position.quote_quantity = dec("20");
+        assert_eq!(position.break_even_price(&dec("20"), &fee), Some(dec("0")));
+
+        assert_eq!(position.break_even_price(&dec("20"), &dec("1")), None);
+        position.base_quantity = dec("0");
+        assert_eq!(position.break_even_price(&dec("20"), &fee), None);
+        assert!(!position.is_break_even_reachable(&dec("20"), &fee));
+
+        position.base_quantity = dec("0.3996");
+        position.selling_prices = vec![];
+        assert!(!position.is_break_even_reachable(&dec("20"), &fee));
+    }
+
+    #[tokio::test]
+    async fn test_expected_profit() {
+        let fee = dec("0.001");
+        let agent = TradeAgent::with_commission("0.001");
+        let net_quote = |trades: &[Trade]| {
+            trades
+                .iter()
+                .fold(Decimal::ZERO, |sum, trade| match trade.side {
+                    TradeSide::Buy => sum - trade.quote_quantity,
+                    TradeSide::Sell => sum + trade.quote_quantity,
+                })
+        };
+
+        let short = Position {
+            buying_prices: vec![Range(dec("30"), dec("50"))],
+            selling_prices: vec![Range(dec("200"), dec("250"))],
+            quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+        let trades = short.clone().min_profit_trades(&agent).await.unwrap();
+        let expected = short.expected_profit(&fee, &fee);
+        assert_eq!(expected.conservative, net_quote(&trades));
+        assert_eq!(expected.conservative, dec("59.84008"));
+        // 20 / 30 * 0.999 base sold at 250 less 0.1%, minus the 20 spent
+        assert_eq!(expected.optimistic, dec("146.3335"));
+
+        let long = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250"))],
+            base_quantity: dec("5.0"),
+            quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+        let trades = long.clone().min_profit_trades(&agent).await.unwrap();
+        let expected = long.expected_profit(&fee, &fee);
+        assert_eq!(trades.len(), 3);
+        assert_eq!(expected.conservative, net_quote(&trades));
+        assert!(expected.optimistic > expected.conservative);
+
+        let free = long.expected_profit(&dec("0"), &dec("0"));
+        assert_eq!(free.conservative, dec("2808.75") - dec("20"));
+
+        let unsold = Position {
+            selling_prices: vec![],
+            ..long.clone()
+        };
+        assert_eq!(
+            unsold.expected_profit(&fee, &fee),
+            ExpectedProfit {
+                conservative: dec("0"),
+                optimistic: dec("0"),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_min_profit_trades_negative_price() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("-50"), dec("-30"))],
+            selling_prices: vec![Range(dec("200"), dec("250"))],
+            quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+
+        let agent = TradeAgent::default();
+        match position.min_profit_trades(&agent).await {
+            Err(TradeError::NegativePrice(price)) => assert_eq!(price, dec("-50")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_min_profit_trades_one_side() {
+        let agent = TradeAgent::default();
+
+        let mut buying_only = Position {
+            buying_prices: vec![Range(dec("30"), dec("50"))],
+            quote_quantity: dec("20"),
+            ..Default::default()
+        };
+        assert_eq!(buying_only.max_buying_price(), Some(&dec("50")));
+        assert_eq!(buying_only.min_selling_price(), None);
+        assert_eq!(buying_only.min_selling_price_or_max(), &Price::MAX);
+
+        let error = buying_only.min_profit_trades(&agent).await.unwrap_err();
+        assert!(matches!(
+            error,
+            TradeError::InvalidPosition(PositionError::NoSellingPrices)
+        ));
+        assert_eq!(
+            error.to_string(),
+            "invalid position: position has no selling prices"
+        );
+        assert_eq!(buying_only.quote_quantity, dec("20"));
+
+        let mut selling_only = Position {
+            selling_prices: vec![Range(dec("200"), dec("250"))],
+            base_quantity: dec("1"),
+            ..Default::default()
+        };
+        assert_eq!(selling_only.max_buying_price(), None);
+        assert_eq!(selling_only.max_buying_price_or_zero(), &dec("0"));
+        assert_eq!(selling_only.min_selling_price(), Some(&dec("200")));
+
+        let error = selling_only.min_profit_trades(&agent).await.unwrap_err();
+        assert!(matches!(
+            error,
+            TradeError::InvalidPosition(PositionError::NoBuyingPrices)
+        ));
+        assert_eq!(selling_only.base_quantity, dec("1"));
+    }
+
+    #[tokio::test]
+    async fn test_min_profit_trades() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250"))],
+            base_quantity: dec("5.0"),
+            quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+
+        let trades = position
+            .min_profit_trades(&TradeAgent::with_commission("0"))
+            .await
+            .unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+                Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
+                Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75"))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_min_profit_trades_with_mulit_prices() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80")), Range(dec("90"), dec("100"))],
+            selling_prices: vec![Range(dec("210"), dec("250")), Range(dec("205"), dec("200"))],
+            base_quantity: dec("5.0"),
+            quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+
+        let trades = position
+            .min_profit_trades(&TradeAgent::with_commission("0"))
+            .await
+            .unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                Trade::with_sell(dec("200"), dec("5"), dec("1000")),
+                Trade::with_buy(dec("100"), dec("10.2"), dec("1020")),
+                Trade::with_sell(dec("200"), dec("10.2"), dec("2040"))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trap_same_price() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("70"), dec("80"))],
+            base_quantity: dec("5.0"),
+            quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+
+        let trades = position
+            .trap(&TradeAgent::with_commission("0"), &dec("80"))
+            .await
+            .unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                Trade::with_sell(dec("80"), dec("5"), dec("400")),
+                Trade::with_buy(dec("80"), dec("5.250"), dec("420.0"))
+            ]
+        );
+        assert_eq!(position.base_quantity, dec("5.25"));
+        assert_eq!(position.quote_quantity, dec("0"));
+    }
+
+    #[tokio::test]
+    async fn test_trap_churn_policy() {
+        let position = |churn_policy| Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("70"), dec("80"))],
+            base_quantity: dec("5"),
+            quote_quantity: dec("20"),
+            churn_policy,
+            ..Default::default()
+        };
+        let agent = TradeAgent::default();
+        let sell = Trade::with_sell(dec("80"), dec("5"), dec("400"));
+
+        let mut allow = position(ChurnPolicy::default());
+        let trades = allow.trap(&agent, &dec("80")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                sell.clone(),
+                Trade::with_buy(dec("80"), dec("5.25"), dec("420"))
+            ]
+        );
+
+        let mut prefer_sell = position(ChurnPolicy::PreferSell);
+        let trades = prefer_sell.trap(&agent, &dec("80")).await.unwrap();
+        assert_eq!(trades, vec![sell]);
+        assert_eq!(prefer_sell.quote_quantity, dec("420"));
+
+        let mut prefer_buy = position(ChurnPolicy::PreferBuy);
+        let trades = prefer_buy.trap(&agent, &dec("80")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("80"), dec("0.25"), dec("20"))]
+        );
+        assert_eq!(prefer_buy.base_quantity, dec("5.25"));
+
+        let mut skip = position(ChurnPolicy::Skip);
+        assert!(skip.trap(&agent, &dec("80")).await.unwrap().is_empty());
+        assert_eq!(skip, position(ChurnPolicy::Skip));
+
+        // Outside the overlap every policy trades as usual, here a position waiting to buy
+        let mut waiting = Position {
+            base_quantity: dec("0"),
+            ..position(ChurnPolicy::Skip)
+        };
+        let trades = waiting.trap(&agent, &dec("40")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("40"), dec("0.5"), dec("20"))]
+        );
+
+        let json = serde_json::to_string(&position(ChurnPolicy::PreferSell)).unwrap();
+        assert!(json.ends_with(r#""churn_policy":"PREFER_SELL"}"#));
+        let json = serde_json::to_string(&position(ChurnPolicy::Allow)).unwrap();
+        assert!(!json.contains("churn_policy"));
+    }
+
+    #[test]
+    fn test_find_overlaps() {
+        let position = |buying: (&str, &str), selling: (&str, &str)| Position {
+            buying_prices: vec![Range(dec(buying.0), dec(buying.1))],
+            selling_prices: vec![Range(dec(selling.0), dec(selling.1))],
+            quote_quantity: dec("20"),
+            ..Default::default()
+        };
+        let mut positions = vec![
+            position(("40", "50"), ("80", "100")),
+            position(("50", "60"), ("90", "100")),
+            position(("55", "58"), ("110", "120")),
+            position(("70", "75"), ("130", "140")),
+        ];
+
+        assert_eq!(
+            find_overlaps(&positions, TradeSide::Buy),
+            vec![
+                (0, 1, Range(dec("50"), dec("50"))),
+                (1, 2, Range(dec("55"), dec("58"))),
+            ]
+        );
+        assert_eq!(
+            find_overlaps(&positions, TradeSide::Sell),
+            vec![(0, 1, Range(dec("90"), dec("100")))]
+        );
+
+        // Excluding its upper bound the lower position no longer claims the shared price
+        positions[0].buying_upper_bound = Bound::Exclusive;
+        assert_eq!(
+            find_overlaps(&positions, TradeSide::Buy),
+            vec![(1, 2, Range(dec("55"), dec("58")))]
+        );
+
+        let error = assert_disjoint(&positions).unwrap_err();
+        assert_eq!(
+            error,
+            OverlapError {
+                overlaps: vec![(1, 2, Range(dec("55"), dec("58")))]
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "overlapping buying prices: positions 1 and 2 at 55..58"
+        );
+
+        positions.remove(2);
+        assert_eq!(assert_disjoint(&positions), Ok(()));
+    }
+
+    #[test]
+    fn test_grid_disjoint() {
+        use crate::strategy::{grid::Grid, grid_percent::GridPercent, Strategy};
+
+        let range = PriceRange::new(dec("50"), dec("100")).unwrap();
+        for copies in 1..10 {
+            let grid = Grid::new(dec("30"), range.clone(), copies);
+            assert_eq!(assert_disjoint(&grid.assign_position()), Ok(()));
+        }
+
+        let grid = GridPercent::new(dec("100"), range, dec("0.01"), dec("0.1"));
+        assert_eq!(assert_disjoint(&grid.assign_position()), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_trap_multiple_fills() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("70"), dec("80"))],
+            base_quantity: dec("4"),
+            quote_quantity: dec("20"),
+            ..Default::default()
+        };
+
+        let trades = position.trap(&PartialAgent, &dec("80")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                Trade::with_sell(dec("80"), dec("1"), dec("80")),
+                Trade::with_sell(dec("80"), dec("3"), dec("240")),
+                Trade::with_buy(dec("80"), dec("1.0625"), dec("85")),
+                Trade::with_buy(dec("80"), dec("3.1875"), dec("255")),
+            ]
+        );
+        assert_eq!(position.base_quantity, dec("4.25"));
+        assert_eq!(position.quote_quantity, dec("0"));
+
+        // The balances move by exactly the fills, whichever branches fired
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("50"))],
+            selling_prices: vec![Range(dec("70"), dec("80"))],
+            quote_quantity: dec("100"),
+            ..Default::default()
+        };
+        let mut fills = Vec::new();
+        for price in ["40", "75", "45", "80"] {
+            fills.extend(position.trap(&PartialAgent, &dec(price)).await.unwrap());
+        }
+        let report = fills.evaluate().await;
+        assert_eq!(fills.len(), 8);
+        assert_eq!(position.base_quantity, report.leave_base_quantity);
+        assert_eq!(
+            position.quote_quantity,
+            dec("100") + report.leave_quote_quantity
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trap() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("10"), dec("20"))],
+            selling_prices: vec![Range(dec("50"), dec("80"))],
+            base_quantity: dec("0"),
+            quote_quantity: dec("20.0"),
+            ..Default::default()
+        };
+
+        let trades = position
+            .trap(&TradeAgent::with_commission("0"), &dec("80"))
+            .await
+            .unwrap();
+        assert_eq!(trades, vec![]);
+
+        let trades = position
+            .trap(&TradeAgent::with_commission("0"), &dec("20"))
+            .await
+            .unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("20"), dec("1"), dec("20"))]
+        );
+    }
+
+    #[test]
+    fn test_serde_buying_upper_bound() {
+        let json = r#"{"buying_prices":[["10","20"]],"selling_prices":[["50","80"]],"base_quantity":"0","quote_quantity":"20"}"#;
+
+        let mut position: Position = serde_json::from_str(json).unwrap();
+        assert_eq!(position.buying_upper_bound, Bound::Inclusive);
+        assert_eq!(serde_json::to_string(&position).unwrap(), json);
+
+        position.buying_upper_bound = Bound::Exclusive;
+        assert!(!position.is_within_buying_price(&dec("20")));
+
+        let position: Position =
+            serde_json::from_str(&serde_json::to_string(&position).unwrap()).unwrap();
+        assert_eq!(position.buying_upper_bound, Bound::Exclusive);
+    }
+
+    #[test]
+    fn test_serde_sorted_prices() {
+        let position = Position {
+            buying_prices: vec![Range(dec("30"), dec("20")), Range(dec("10"), dec("15"))],
+            selling_prices: vec![Range(dec("90"), dec("100")), Range(dec("50"), dec("60"))],
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&position).unwrap();
+        assert!(json.starts_with(
+            r#"{"buying_prices":[["10","15"],["20","30"]],"selling_prices":[["50","60"],["90","100"]]"#
+        ));
+
+        let json = r#"{"buying_prices":[["30","20"],["10","15"]],"selling_prices":[["90","100"],["50","60"]],"base_quantity":"0","quote_quantity":"20"}"#;
+        let position: Position = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            position.buying_prices,
+            vec![Range(dec("10"), dec("15")), Range(dec("20"), dec("30"))]
+        );
+        assert_eq!(position.selling_prices[0], Range(dec("50"), dec("60")));
+    }
+
+    #[test]
+    fn test_is_within_many_ranges() {
+        let sorted = Position {
+            buying_prices: vec![
+                Range(dec("10"), dec("20")),
+                Range(dec("20"), dec("30")),
+                Range(dec("40"), dec("50")),
+            ],
+            selling_prices: vec![Range(dec("60"), dec("70")), Range(dec("80"), dec("90"))],
+            ..Default::default()
+        };
+        let mut unsorted = sorted.clone();
+        unsorted.buying_prices.reverse();
+        unsorted.selling_prices.reverse();
+        unsorted.normalize_prices();
+        let json = serde_json::to_string(&unsorted).unwrap();
+        let read_back: Position = serde_json::from_str(&json).unwrap();
+
+        for position in [&sorted, &unsorted, &read_back] {
+            for price in ["10", "20", "25", "30", "40", "50"] {
+                assert!(position.is_within_buying_price(&dec(price)), "{}", price);
+            }
+            for price in ["5", "35", "55"] {
+                assert!(!position.is_within_buying_price(&dec(price)), "{}", price);
+            }
+            for price in ["60", "70", "80", "90"] {
+                assert!(position.is_within_selling_price(&dec(price)), "{}", price);
+            }
+            for price in ["59", "75", "91"] {
+                assert!(!position.is_within_selling_price(&dec(price)), "{}", price);
+            }
+        }
+
+        let overlapping = Position::try_new(
+            vec![Range(dec("40"), dec("50")), Range(dec("10"), dec("60"))],
+            vec![Range(dec("80"), dec("90"))],
+            dec("0"),
+            dec("100"),
+            false,
+        )
+        .unwrap();
+        assert!(math::is_sorted_disjoint(&overlapping.buying_prices));
+        assert!(overlapping.is_within_buying_price(&dec("35")));
+        assert!(overlapping.is_within_buying_price(&dec("55")));
+
+        let mut expanded = sorted.clone();
+        expanded.expand_selling(dec("1.5"));
+        assert!(math::is_sorted_disjoint(&expanded.selling_prices));
+        assert!(expanded.is_within_selling_price(&dec("75")));
+
+        // Touching ranges still share the price when the lower one excludes it
+        let mut half_open = sorted.clone();
+        half_open.buying_upper_bound = Bound::Exclusive;
+        assert!(half_open.is_within_buying_price(&dec("20")));
+        assert!(!half_open.is_within_buying_price(&dec("30")));
+        assert!(!half_open.is_within_buying_price(&dec("50")));
+    }
+
+    #[test]
+    fn test_is_disjoint_prices() {
+        let position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250"))],
+            ..Default::default()
+        };
+        assert!(position.is_disjoint_prices());
+
+        let position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("70"), dec("80"))],
+            ..Default::default()
+        };
+        assert!(!position.is_disjoint_prices());
+    }
+
+    #[test]
+    fn test_normalize_prices() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("90"), dec("100")), Range(dec("80"), dec("30"))],
+            selling_prices: vec![Range(dec("210"), dec("250")), Range(dec("205"), dec("200"))],
+            ..Default::default()
+        };
+        position.normalize_prices();
+
+        assert_eq!(
+            position.buying_prices,
+            vec![Range(dec("30"), dec("80")), Range(dec("90"), dec("100"))]
+        );
+        assert_eq!(
+            position.selling_prices,
+            vec![Range(dec("200"), dec("205")), Range(dec("210"), dec("250"))]
+        );
+    }
+
+    #[test]
+    fn test_shift_prices() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250")), Range(dec("0"), dec("20"))],
+            ..Default::default()
+        };
+        position.shift_prices(dec("-40"));
+
+        assert_eq!(position.buying_prices, vec![Range(dec("0"), dec("40"))]);
+        assert_eq!(
+            position.selling_prices,
+            vec![Range(dec("170"), dec("210")), Range(dec("0"), dec("0"))]
+        );
+    }
+
+    #[test]
+    fn test_has_degenerate_prices() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250"))],
+            ..Default::default()
+        };
+        assert!(!position.has_degenerate_prices());
+
+        position
+            .selling_prices
+            .push(Range(dec("220.0"), dec("220")));
+        assert!(position.has_degenerate_prices());
+    }
+
+    #[test]
+    fn test_new() {
+        let position = Position::new(
+            vec![PriceRange::new(dec("80"), dec("30")).unwrap()],
+            vec![PriceRange::new(dec("210"), dec("250")).unwrap()],
+            dec("0"),
+            dec("20"),
+        );
+
+        assert_eq!(
+            position,
+            Position {
+                buying_prices: vec![Range(dec("30"), dec("80"))],
+                selling_prices: vec![Range(dec("210"), dec("250"))],
+                base_quantity: dec("0"),
+                quote_quantity: dec("20"),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_try_new() {
+        let position = Position::try_new(
+            vec![Range(dec("80"), dec("30"))],
+            vec![Range(dec("210"), dec("250"))],
+            dec("0"),
+            dec("20"),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            position,
+            Position::new(
+                vec![PriceRange::new(dec("30"), dec("80")).unwrap()],
+                vec![PriceRange::new(dec("210"), dec("250")).unwrap()],
+                dec("0"),
+                dec("20"),
+            )
+        );
+
+        let build = |buying: Vec<Range<Price>>, base: &str, quote: &str, spread| {
+            let selling = vec![Range(dec("210"), dec("250"))];
+            Position::try_new(buying, selling, dec(base), dec(quote), spread)
+        };
+        let buying = || vec![Range(dec("30"), dec("80"))];
+
+        assert_eq!(
+            build(buying(), "-1", "20", false),
+            Err(PositionError::NegativeBaseQuantity(dec("-1")))
+        );
+        assert_eq!(
+            build(buying(), "0", "-20", false),
+            Err(PositionError::NegativeQuoteQuantity(dec("-20")))
+        );
+        assert_eq!(
+            Position::try_new(vec![], vec![], dec("0"), dec("20"), false),
+            Err(PositionError::NoPrices)
+        );
+        assert_eq!(
+            build(vec![Range(dec("-30"), dec("80"))], "0", "20", false),
+            Err(PositionError::NegativePrice(Range(dec("-30"), dec("80"))))
+        );
+
+        // A buying range reaching into the selling one is only rejected on request
+        let crossed = || vec![Range(dec("30"), dec("220"))];
+        assert!(build(crossed(), "0", "20", false).is_ok());
+        assert_eq!(
+            build(crossed(), "0", "20", true),
+            Err(PositionError::CrossedPrices {
+                max_buying: dec("220"),
+                min_selling: dec("210"),
+            })
+        );
+
+        // The stop-loss range of `GridPercent` starts at zero
+        assert!(build(vec![Range(dec("0"), dec("80"))], "0", "20", false).is_ok());
+    }
+
+    #[test]
+    fn test_validated_position() {
+        let json = r#"{"buying_prices":[["30","80"]],"selling_prices":[["210","250"]],"base_quantity":"0","quote_quantity":"20"}"#;
+        let position: ValidatedPosition = serde_json::from_str(json).unwrap();
+        assert_eq!(position.0.quote_quantity, dec("20"));
+        assert_eq!(serde_json::to_string(&position).unwrap(), json);
+
+        let json = r#"{"buying_prices":[],"selling_prices":[],"base_quantity":"0","quote_quantity":"-20"}"#;
+        let error = serde_json::from_str::<ValidatedPosition>(json).unwrap_err();
+        assert!(error.to_string().contains("quote_quantity -20 is negative"));
+        assert!(serde_json::from_str::<Position>(json).is_ok());
+    }
+
+    #[test]
+    fn test_round_quantities() {
+        let mut position = Position {
+            base_quantity: dec("0.09841489"),
+            quote_quantity: dec("20.123"),
+            ..Default::default()
+        };
+
+        let dust = position
+            .round_quantities(&dec("0.00001"), &dec("0.01"))
+            .unwrap();
+        assert_eq!(dust, (dec("0.00000489"), dec("0.003")));
+        assert_eq!(position.base_quantity, dec("0.09841"));
+        assert_eq!(position.quote_quantity, dec("20.12"));
+    }
+
+    #[tokio::test]
+    async fn test_trap_base_step() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("10"), dec("20"))],
+            selling_prices: vec![Range(dec("50"), dec("80"))],
+            base_quantity: dec("0.09841489"),
+            quote_quantity: dec("0"),
+            base_step: Some(dec("0.00001")),
+            ..Default::default()
+        };
+
+        let trades = position
+            .trap(&TradeAgent::with_commission("0"), &dec("50"))
+            .await
+            .unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_sell(dec("50"), dec("0.09841"), dec("4.92050"))]
+        );
+        assert_eq!(position.base_quantity, dec("0.00000489"));
+
+        // What is left is below one step, so nothing is sent
+        let trades = position
+            .trap(&TradeAgent::with_commission("0"), &dec("50"))
+            .await
+            .unwrap();
+        assert_eq!(trades, vec![]);
+    }
+
+    #[test]
+    fn test_expand_prices() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250"))],
+            ..Default::default()
+        };
+
+        position.expand_buying(dec("0.2"));
+        assert_eq!(position.buying_prices, vec![Range(dec("25"), dec("85"))]);
+        assert_eq!(position.selling_prices, vec![Range(dec("210"), dec("250"))]);
+
+        position.expand_selling(dec("-0.5"));
+        assert_eq!(position.selling_prices, vec![Range(dec("220"), dec("240"))]);
+    }
+
+    #[test]
+    fn test_dedup_positions() {
+        let position = |low: &str, quantity: &str| Position {
+            buying_prices: vec![Range(dec(low), dec("2"))],
+            selling_prices: vec![Range(dec("3"), dec("4"))],
+            quote_quantity: dec(quantity),
+            ..Default::default()
+        };
+
+        let positions = vec![
+            position("1.10", "10"),
+            position("1.1000", "10.00"),
+            position("1.2", "10"),
+            position("1.1", "10"),
+            position("1.1", "20"),
+        ];
+
+        assert_eq!(
+            dedup_positions(positions),
+            vec![
+                position("1.10", "10"),
+                position("1.2", "10"),
+                position("1.1", "20")
+            ]
+        );
+
+        // The same band from two runs, only the metadata and the range order differ
+        let first = Position {
+            buying_prices: vec![Range(dec("1"), dec("2")), Range(dec("5"), dec("6"))],
+            id: Some(0),
+            label: Some("1..2".into()),
+            symbol: Some("BTCUSDT".into()),
+            ..position("1", "10")
+        };
+        let second = Position {
+            buying_prices: vec![Range(dec("5.0"), dec("6")), Range(dec("1"), dec("2"))],
+            id: Some(7),
+            label: Some("grid 7".into()),
+            stop_loss: Some(dec("0.5")),
+            churn_policy: ChurnPolicy::Skip,
+            state: Some(PositionState::Holding),
+            ..position("1", "10")
+        };
+        assert_ne!(first, second);
+
+        let deduped = dedup_positions(vec![first.clone(), second, position("1", "10")]);
+        assert_eq!(deduped.len(), 2);
+        assert_eq!(deduped[0].id, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_trap_symbol() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250"))],
+            base_quantity: dec("0"),
+            quote_quantity: dec("1000"),
+            symbol: Some("BTCUSDT".into()),
+            ..Default::default()
+        };
+
+        let agent = TradeAgent::default();
+        let trades = position.trap(&agent, &dec("50")).await.unwrap();
+        assert_eq!(trades[0].symbol.as_deref(), Some("BTCUSDT"));
+
+        let trades = position.trap(&agent, &dec("220")).await.unwrap();
+        assert_eq!(trades[0].symbol.as_deref(), Some("BTCUSDT"));
+
+        let json = serde_json::to_string(&Position::default()).unwrap();
+        assert!(!json.contains("symbol"));
+    }
+
+    struct SlippingAgent;
+
+    // Fills 0.1% away from the requested price, against the caller
+    impl Trader for SlippingAgent {
+        async fn buy(
+            &self,
+            price: &Price,
+            quote_quantity: &QuoteQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            let price = price * dec("1.001");
+            let base_quantity = quote_quantity / price;
+            Ok(vec![Trade::with_buy(price, base_quantity, *quote_quantity)])
+        }
+
+        async fn sell(
+            &self,
+            price: &Price,
+            base_quantity: &BaseQuantity,
+        ) -> Result<Vec<Trade>, Box<dyn Error>> {
+            let price = price * dec("0.999");
+            let quote_quantity = base_quantity * price;
+            Ok(vec![Trade::with_sell(
+                price,
+                *base_quantity,
+                quote_quantity,
+            )])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trap_requested_price() {
+        let mut position = Position {
+            buying_prices: vec![Range(dec("30"), dec("80"))],
+            selling_prices: vec![Range(dec("210"), dec("250"))],
+            base_quantity: dec("0"),
+            quote_quantity: dec("1001"),
+            ..Default::default()
         };
 
+        let mut trades = position.trap(&SlippingAgent, &dec("50")).await.unwrap();
+        assert_eq!(trades[0].requested_price, Some(dec("50")));
+        assert_eq!(trades[0].slippage(), Some(dec("0.05")));
+        assert_eq!(trades[0].slippage_cost(), Some(dec("1")));
+
+        trades.extend(position.trap(&SlippingAgent, &dec("220")).await.unwrap());
+        assert_eq!(trades[1].requested_price, Some(dec("220")));
+        assert_eq!(trades[1].slippage(), Some(dec("-0.22")));
+        assert_eq!(trades[1].slippage_cost(), Some(dec("4.4")));
+
+        let report = trades.evaluate().await;
+        assert_eq!(report.slippage, dec("5.4"));
+        assert_eq!(report.avg_slippage, Some(dec("2.7")));
+    }
+
+    #[tokio::test]
+    async fn test_trap_attributed() {
+        let position = |quote: &str| Position {
+            buying_prices: vec![Range(dec("40"), dec("60"))],
+            selling_prices: vec![Range(dec("190"), dec("210"))],
+            quote_quantity: dec(quote),
+            ..Default::default()
+        };
+        let mut positions = vec![position("20"), position("100")];
         let agent = TradeAgent::default();
 
-        let trades = position.clone().min_profit_trades(&agent).await.unwrap();
-        assert_eq!(
-            trades,
-            vec![
-                Trade::with_buy(dec("50"), dec("0.4"), dec("20.0")),
-                Trade::with_sell(dec("200"), dec("0.4"), dec("80.0"))
-            ]
+        let mut trades = trap_attributed(&mut positions, &agent, &dec("50"))
+            .await
+            .unwrap();
+        trades.extend(
+            trap_attributed(&mut positions, &agent, &dec("200"))
+                .await
+                .unwrap(),
         );
+        let owners: Vec<usize> = trades.iter().map(|(index, _)| *index).collect();
+        assert_eq!(owners, vec![0, 1, 0, 1]);
 
-        let agent = TradeAgent::with_commission("0.001");
-        let trades = position.clone().min_profit_trades(&agent).await.unwrap();
-        assert_eq!(
-            trades,
-            vec![
-                Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
-                Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800"))
-            ]
-        );
+        let reports = evaluate_per_position(&positions, &trades);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].volume_quote_quantity, dec("100"));
+        assert_eq!(reports[0].leave_quote_quantity, dec("60"));
+        assert_eq!(reports[1].volume_quote_quantity, dec("500"));
+        assert_eq!(reports[1].leave_quote_quantity, dec("300"));
+
+        let flat: Vec<Trade> = trades.iter().map(|(_, trade)| trade.clone()).collect();
+        let combined = flat.evaluate().await;
+        let merged = reports[0].merge(&reports[1]);
+        assert_eq!(merged.volume_base_quantity, combined.volume_base_quantity);
+        assert_eq!(merged.volume_quote_quantity, combined.volume_quote_quantity);
+        assert_eq!(merged.leave_base_quantity, combined.leave_base_quantity);
+        assert_eq!(merged.leave_quote_quantity, combined.leave_quote_quantity);
+        assert_eq!(merged.buy_count, combined.buy_count);
+        assert_eq!(merged.sell_count, combined.sell_count);
+        assert_eq!(merged.costs, combined.costs);
+        assert_eq!(merged.round_trips, combined.round_trips);
+        assert_eq!(merged.gross_profit, combined.gross_profit);
+
+        // The flat API reports the same trades without the owners
+        let mut positions = vec![position("20"), position("100")];
+        let trades = positions.trap(&agent, &dec("50")).await.unwrap();
+        assert_eq!(trades, flat[..2].to_vec());
     }
 
     #[tokio::test]
-    async fn test_min_profit_trades() {
+    async fn test_trap_with_fraction() {
         let mut position = Position {
-            buying_prices: vec![Range(dec("30"), dec("80"))],
-            selling_prices: vec![Range(dec("210"), dec("250"))],
-            base_quantity: dec("5.0"),
-            quote_quantity: dec("20.0"),
+            buying_prices: vec![Range(dec("40"), dec("60"))],
+            selling_prices: vec![Range(dec("190"), dec("210"))],
+            quote_quantity: dec("100"),
+            ..Default::default()
         };
+        let agent = TradeAgent::default();
 
+        // Half the quote at 50, the other half once the price falls to 40
         let trades = position
-            .min_profit_trades(&TradeAgent::with_commission("0"))
+            .trap_with_fraction(&agent, &dec("50"), dec("0.5"))
             .await
             .unwrap();
         assert_eq!(
             trades,
-            vec![
-                Trade::with_sell(dec("210"), dec("5"), dec("1050")),
-                Trade::with_buy(dec("80"), dec("13.375"), dec("1070")),
-                Trade::with_sell(dec("210"), dec("13.375"), dec("2808.75"))
-            ]
+            vec![Trade::with_buy(dec("50"), dec("1"), dec("50"))]
         );
-    }
+        assert_eq!(position.quote_quantity, dec("50"));
 
-    #[tokio::test]
-    async fn test_min_profit_trades_with_mulit_prices() {
-        let mut position = Position {
-            buying_prices: vec![Range(dec("30"), dec("80")), Range(dec("90"), dec("100"))],
-            selling_prices: vec![Range(dec("210"), dec("250")), Range(dec("205"), dec("200"))],
-            base_quantity: dec("5.0"),
-            quote_quantity: dec("20.0"),
-        };
+        let trades = position.trap(&agent, &dec("40")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("40"), dec("1.25"), dec("50"))]
+        );
+        assert_eq!(position.quote_quantity, dec("0"));
+        assert_eq!(position.base_quantity, dec("2.25"));
 
         let trades = position
-            .min_profit_trades(&TradeAgent::with_commission("0"))
+            .trap_with_fraction(&agent, &dec("200"), dec("0.2"))
             .await
             .unwrap();
         assert_eq!(
             trades,
-            vec![
-                Trade::with_sell(dec("200"), dec("5"), dec("1000")),
-                Trade::with_buy(dec("100"), dec("10.2"), dec("1020")),
-                Trade::with_sell(dec("200"), dec("10.2"), dec("2040"))
-            ]
+            vec![Trade::with_sell(dec("200"), dec("0.45"), dec("90"))]
+        );
+        assert_eq!(position.base_quantity, dec("1.80"));
+
+        // A quarter of the remaining quote is below the minimum order and is not sent
+        let mut position = Position {
+            buying_prices: vec![Range(dec("40"), dec("60"))],
+            quote_quantity: dec("100"),
+            min_quote_quantity: Some(dec("30")),
+            ..Default::default()
+        };
+        let trades = position
+            .trap_with_fraction(&agent, &dec("50"), dec("0.25"))
+            .await
+            .unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(position.quote_quantity, dec("100"));
+
+        for fraction in ["0", "-0.5", "1.5"] {
+            let error = position
+                .trap_with_fraction(&agent, &dec("50"), dec(fraction))
+                .await
+                .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("fill fraction {} is not within (0, 1]", fraction)
+            );
+        }
+    }
+
+    #[test]
+    fn test_recenter_all() {
+        let position = |buying: &str, selling: &str| Position {
+            buying_prices: vec![Range(dec(buying), dec(buying) + dec("10"))],
+            selling_prices: vec![Range(dec(selling), dec(selling) + dec("10"))],
+            base_quantity: dec("0.1"),
+            quote_quantity: dec("10"),
+            ..Default::default()
+        };
+        let grid = vec![
+            position("40", "50"),
+            position("50", "60"),
+            position("60", "70"),
+        ];
+
+        // Prices span 40..80, a mid of 60 moved up by 20%
+        let mut recentered = grid.clone();
+        assert_eq!(recenter_all(&mut recentered, &dec("72")), Ok(()));
+        assert_eq!(
+            recentered[0].buying_prices,
+            vec![Range(dec("52"), dec("62"))]
+        );
+        assert_eq!(
+            recentered[2].selling_prices,
+            vec![Range(dec("82"), dec("92"))]
+        );
+        for (before, after) in grid.iter().zip(recentered.iter()) {
+            let shift = |a: &Vec<Range<Price>>, b: &Vec<Range<Price>>| {
+                (*b[0].min() - *a[0].min(), *b[0].max() - *a[0].max())
+            };
+            let delta = (dec("12"), dec("12"));
+            assert_eq!(shift(&before.buying_prices, &after.buying_prices), delta);
+            assert_eq!(shift(&before.selling_prices, &after.selling_prices), delta);
+            assert_eq!(after.base_quantity, before.base_quantity);
+            assert_eq!(after.quote_quantity, before.quote_quantity);
+        }
+
+        let mut single = position("40", "50");
+        single.stop_loss = Some(dec("35"));
+        assert_eq!(single.recenter(&dec("100")), Ok(()));
+        assert_eq!(single.buying_prices, vec![Range(dec("90"), dec("100"))]);
+        assert_eq!(single.stop_loss, Some(dec("85")));
+
+        // Down to a mid of 10 the lowest level clamps at zero, the next one just reaches it
+        let mut clamped = grid.clone();
+        let error = recenter_all(&mut clamped, &dec("10")).unwrap_err();
+        assert_eq!(error, RecenterError { clamped: vec![0] });
+        assert_eq!(
+            error.to_string(),
+            "prices clamped at zero for positions [0]"
+        );
+        assert_eq!(clamped[0].buying_prices, vec![Range(dec("0"), dec("0"))]);
+        assert_eq!(clamped[1].buying_prices, vec![Range(dec("0"), dec("10"))]);
+        assert_eq!(clamped[2].buying_prices, vec![Range(dec("10"), dec("20"))]);
+
+        let mut empty: Vec<Position> = vec![];
+        assert_eq!(recenter_all(&mut empty, &dec("10")), Ok(()));
+    }
+
+    #[test]
+    fn test_merge() {
+        let lower = Position {
+            buying_prices: vec![Range(dec("40"), dec("50"))],
+            selling_prices: vec![Range(dec("60"), dec("70"))],
+            base_quantity: dec("0.5"),
+            quote_quantity: dec("10"),
+            symbol: Some("BTCUSDT".into()),
+            ..Default::default()
+        };
+        let upper = Position {
+            buying_prices: vec![Range(dec("50"), dec("60"))],
+            selling_prices: vec![Range(dec("70"), dec("80"))],
+            base_quantity: dec("0.25"),
+            quote_quantity: dec("20"),
+            ..Default::default()
+        };
+
+        let merged = lower.merge(&upper).unwrap();
+        assert_eq!(merged.base_quantity, dec("0.75"));
+        assert_eq!(merged.quote_quantity, dec("30"));
+        assert_eq!(merged.buying_prices, vec![Range(dec("40"), dec("60"))]);
+        assert_eq!(merged.selling_prices, vec![Range(dec("60"), dec("80"))]);
+        assert_eq!(merged.symbol, Some("BTCUSDT".into()));
+        assert_eq!(merged.state(), PositionState::Holding);
+        for price in ["40", "50", "60"] {
+            assert!(merged.is_within_buying_price(&dec(price)));
+        }
+
+        let other_symbol = Position {
+            symbol: Some("ETHUSDT".into()),
+            ..upper.clone()
+        };
+        assert_eq!(
+            lower.merge(&other_symbol),
+            Err(MergeError::SymbolConflict(
+                "BTCUSDT".into(),
+                "ETHUSDT".into()
+            ))
+        );
+
+        let (a, b) = (
+            Position {
+                id: Some(1),
+                ..lower.clone()
+            },
+            Position {
+                id: Some(2),
+                ..upper.clone()
+            },
+        );
+        assert_eq!(a.merge(&b), Err(MergeError::IdConflict(1, 2)));
+        assert_eq!(a.merge(&upper).unwrap().id, Some(1));
+
+        let halted = Position {
+            state: Some(PositionState::Halted),
+            ..upper
+        };
+        assert_eq!(
+            lower.merge(&halted).unwrap_err().to_string(),
+            "cannot merge a halted position"
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let position = Position {
+            buying_prices: vec![Range(dec("40"), dec("50")), Range(dec("60"), dec("61"))],
+            selling_prices: vec![Range(dec("80"), dec("90"))],
+            base_quantity: dec("1"),
+            quote_quantity: dec("100"),
+            id: Some(3),
+            label: Some(String::from("x")),
+            ..Default::default()
+        };
+
+        let parts = position.split(3).unwrap();
+        assert_eq!(parts.len(), 3);
+
+        let base: Decimal = parts.iter().map(|part| part.base_quantity).sum();
+        let quote: Decimal = parts.iter().map(|part| part.quote_quantity).sum();
+        assert_eq!(base, position.base_quantity);
+        assert_eq!(quote, position.quote_quantity);
+        assert_eq!(parts[0].quote_quantity, dec("100") / dec("3"));
+
+        // The slices cover the first buying range end to end
+        assert_eq!(parts[0].buying_prices[0].min(), &dec("40"));
+        assert_eq!(parts[2].buying_prices[0].max(), &dec("50"));
+        for pair in parts.windows(2) {
+            assert_eq!(
+                pair[0].buying_prices[0].max(),
+                pair[1].buying_prices[0].min()
+            );
+        }
+        for part in parts.iter() {
+            assert_eq!(part.buying_prices[1], Range(dec("60"), dec("61")));
+            assert_eq!(part.selling_prices, position.selling_prices);
+            assert_eq!(part.id, None);
+            assert_eq!(part.label, None);
+        }
+        assert_eq!(parts.find_by_id(3), None);
+
+        assert_eq!(position.split(1).unwrap(), vec![position.clone()]);
+        assert_eq!(position.split(0), Err(SplitError::NoParts));
+
+        let degenerate = Position {
+            buying_prices: vec![Range(dec("40"), dec("40"))],
+            ..position
+        };
+        assert_eq!(
+            degenerate.split(2).unwrap_err().to_string(),
+            "price range cannot be split into 2 parts"
         );
     }
 
-    impl PartialEq for Trade {
-        fn eq(&self, other: &Self) -> bool {
-            self.side == other.side
-                && self.price == other.price
-                && self.base_quantity == other.base_quantity
-                && self.quote_quantity == other.quote_quantity
+    #[test]
+    fn test_merge_adjacent() {
+        let position = |min: &str, max: &str, base: &str| Position {
+            buying_prices: vec![Range(dec(min), dec(max))],
+            selling_prices: vec![Range(dec("100"), dec("110"))],
+            base_quantity: dec(base),
+            quote_quantity: dec("10"),
+            ..Default::default()
+        };
+        let positions = vec![
+            position("40", "45", "1"),
+            position("70", "75", "2"),
+            position("46", "50", "3"),
+            position("50", "55", "4"),
+        ];
+
+        let merged = merge_adjacent(positions.clone(), dec("1"));
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged[0].buying_prices,
+            vec![Range(dec("40"), dec("45")), Range(dec("46"), dec("55"))]
+        );
+        assert_eq!(merged[1], positions[1]);
+
+        let base: Decimal = merged.iter().map(|position| position.base_quantity).sum();
+        let quote: Decimal = merged.iter().map(|position| position.quote_quantity).sum();
+        assert_eq!(base, dec("10"));
+        assert_eq!(quote, dec("40"));
+        for original in positions.iter() {
+            let price = original.buying_prices[0].min();
+            assert!(merged
+                .iter()
+                .any(|position| position.is_within_buying_price(price)));
         }
+
+        // Touching ranges only merge with a zero gap
+        let merged = merge_adjacent(positions.clone(), dec("0"));
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[2].buying_prices, vec![Range(dec("46"), dec("55"))]);
+
+        assert_eq!(merge_adjacent(positions.clone(), dec("100")).len(), 1);
     }
 
     #[tokio::test]
-    async fn test_trap_same_price() {
-        let mut position = Position {
+    async fn test_trap_slice() {
+        let mut positions = [
+            Position {
+                buying_prices: vec![Range(dec("40"), dec("50"))],
+                quote_quantity: dec("10"),
+                ..Default::default()
+            },
+            Position {
+                buying_prices: vec![Range(dec("45"), dec("60"))],
+                quote_quantity: dec("30"),
+                ..Default::default()
+            },
+        ];
+
+        // Only the tail of the grid is driven
+        let mut tail = &mut positions[1..];
+        let trades = tail.trap(&TradeAgent::default(), &dec("50")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("50"), dec("0.6"), dec("30"))]
+        );
+        assert_eq!(positions[0].quote_quantity, dec("10"));
+        assert_eq!(positions[1].base_quantity, dec("0.6"));
+    }
+
+    #[tokio::test]
+    async fn test_trap_symbol_map() {
+        let grid = |min: &str, max: &str| {
+            vec![Position {
+                buying_prices: vec![Range(dec(min), dec(max))],
+                selling_prices: vec![Range(dec(max) * dec("2"), dec(max) * dec("3"))],
+                quote_quantity: dec("100"),
+                ..Default::default()
+            }]
+        };
+        let mut markets = HashMap::from([
+            ("BTCUSDT".to_string(), grid("40", "50")),
+            ("ETHUSDT".to_string(), grid("4", "5")),
+        ]);
+        let agent = TradeAgent::default();
+
+        // A BTC price inside the ETH band leaves ETH alone
+        let trades = markets
+            .trap_symbol(&agent, "BTCUSDT", &dec("5"))
+            .await
+            .unwrap();
+        assert!(trades.is_empty());
+
+        let trades = markets
+            .trap_symbol(&agent, "ETHUSDT", &dec("5"))
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].symbol.as_deref(), Some("ETHUSDT"));
+        assert_eq!(markets["ETHUSDT"][0].base_quantity, dec("20"));
+        assert_eq!(markets["BTCUSDT"][0].quote_quantity, dec("100"));
+
+        let trades = markets
+            .trap_symbol(&agent, "BTCUSDT", &dec("50"))
+            .await
+            .unwrap();
+        assert_eq!(trades[0].symbol.as_deref(), Some("BTCUSDT"));
+        assert_eq!(markets["BTCUSDT"][0].base_quantity, dec("2"));
+        assert_eq!(markets["ETHUSDT"][0].base_quantity, dec("20"));
+
+        // Each symbol sells at its own price
+        let trades = markets
+            .trap_symbol(&agent, "ETHUSDT", &dec("12"))
+            .await
+            .unwrap();
+        let mut sell = Trade::with_sell(dec("12"), dec("20"), dec("240"));
+        sell.symbol = Some("ETHUSDT".into());
+        assert_eq!(trades, vec![sell]);
+        assert_eq!(markets["BTCUSDT"][0].base_quantity, dec("2"));
+
+        let trades = markets
+            .trap_symbol(&agent, "SOLUSDT", &dec("12"))
+            .await
+            .unwrap();
+        assert!(trades.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_try_trap() {
+        let position = Position {
             buying_prices: vec![Range(dec("30"), dec("80"))],
             selling_prices: vec![Range(dec("70"), dec("80"))],
-            base_quantity: dec("5.0"),
-            quote_quantity: dec("20.0"),
+            base_quantity: dec("5"),
+            quote_quantity: dec("20"),
+            ..Default::default()
         };
+        let sell = Trade::with_sell(dec("80"), dec("5"), dec("400"));
 
-        let trades = position
-            .trap(&TradeAgent::with_commission("0"), &dec("80"))
+        // The sell goes through, the buy after it fails
+        let mut transactional = position.clone();
+        let error = transactional
+            .try_trap(&FailingAgent::default(), &dec("80"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.executed, vec![sell.clone()]);
+        assert_eq!(error.source.to_string(), "Second Trade Error");
+        assert_eq!(
+            error.to_string(),
+            "trap failed after 1 trades: Second Trade Error"
+        );
+        assert_eq!(transactional, position);
+
+        // The plain trap keeps the sell applied
+        let mut plain = position.clone();
+        let error = plain
+            .trap(&FailingAgent::default(), &dec("80"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Second Trade Error");
+        assert_eq!(plain.base_quantity, dec("0"));
+        assert_eq!(plain.quote_quantity, dec("420"));
+
+        let mut transactional = position.clone();
+        let trades = transactional
+            .try_trap(&TradeAgent::default(), &dec("80"))
             .await
             .unwrap();
+        assert_eq!(
+            trades,
+            vec![sell, Trade::with_buy(dec("80"), dec("5.25"), dec("420"))]
+        );
+        assert_eq!(transactional.base_quantity, dec("5.25"));
+        assert_eq!(transactional.quote_quantity, dec("0"));
+    }
+
+    #[tokio::test]
+    async fn test_trap_state() {
+        let position = |ignore_state| Position {
+            buying_prices: vec![Range(dec("40"), dec("60"))],
+            selling_prices: vec![Range(dec("190"), dec("210"))],
+            quote_quantity: dec("100"),
+            ignore_state,
+            ..Default::default()
+        };
+        let agent = TradeAgent::default();
+
+        // More quote arrives after the buy, the next price in the band still does not buy
+        let mut holding = position(false);
+        let mut trades = holding.trap(&agent, &dec("50")).await.unwrap();
+        holding.quote_quantity += dec("40");
+        trades.extend(holding.trap(&agent, &dec("40")).await.unwrap());
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("50"), dec("2"), dec("100"))]
+        );
+        assert_eq!(holding.state, Some(PositionState::Holding));
+
+        // The state survives a restart
+        let json = serde_json::to_string(&holding).unwrap();
+        assert!(json.contains(r#""state":"HOLDING""#));
+        let mut restarted: Position = serde_json::from_str(&json).unwrap();
+        assert!(restarted.trap(&agent, &dec("50")).await.unwrap().is_empty());
+
+        let trades = restarted.trap(&agent, &dec("200")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_sell(dec("200"), dec("2"), dec("400"))]
+        );
+        assert_eq!(restarted.state(), PositionState::WaitingToBuy);
+
+        let mut legacy = position(true);
+        let mut trades = legacy.trap(&agent, &dec("50")).await.unwrap();
+        legacy.quote_quantity += dec("40");
+        trades.extend(legacy.trap(&agent, &dec("40")).await.unwrap());
         assert_eq!(
             trades,
             vec![
-                Trade::with_sell(dec("80"), dec("5"), dec("400")),
-                Trade::with_buy(dec("80"), dec("5.250"), dec("420.0"))
+                Trade::with_buy(dec("50"), dec("2"), dec("100")),
+                Trade::with_buy(dec("40"), dec("1"), dec("40")),
             ]
         );
+
+        // Partial buys keep buying until the quote is spent
+        let mut scaling = position(false);
+        scaling
+            .trap_with_fraction(&agent, &dec("50"), dec("0.5"))
+            .await
+            .unwrap();
+        assert_eq!(scaling.state(), PositionState::WaitingToBuy);
+        let trades = scaling.trap(&agent, &dec("40")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("40"), dec("1.25"), dec("50"))]
+        );
+        assert_eq!(scaling.state(), PositionState::Holding);
+
+        // A partial buy can still take profit on what it bought
+        let mut partial = position(false);
+        partial
+            .trap_with_fraction(&agent, &dec("50"), dec("0.5"))
+            .await
+            .unwrap();
+        assert_eq!(partial.base_quantity, dec("1"));
+        let trades = partial
+            .trap_with_fraction(&agent, &dec("200"), dec("0.5"))
+            .await
+            .unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_sell(dec("200"), dec("0.5"), dec("100"))]
+        );
+        let trades = partial.trap(&agent, &dec("200")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_sell(dec("200"), dec("0.5"), dec("100"))]
+        );
+        assert_eq!(partial.base_quantity, dec("0"));
+        assert_eq!(partial.state(), PositionState::WaitingToBuy);
+    }
+
+    #[test]
+    fn test_position_state_serde() {
+        // Without a stored state the quantities decide
+        let json = r#"{"buying_prices":[["40","60"]],"selling_prices":[["190","210"]],"base_quantity":"1","quote_quantity":"100"}"#;
+        let position: Position = serde_json::from_str(json).unwrap();
+        assert_eq!(position.state, None);
+        assert_eq!(position.state(), PositionState::Holding);
+        assert_eq!(serde_json::to_string(&position).unwrap(), json);
+
+        let short = Position {
+            quote_quantity: dec("100"),
+            ..Default::default()
+        };
+        assert_eq!(short.state(), PositionState::WaitingToBuy);
+        assert_eq!(Position::default().state(), PositionState::WaitingToBuy);
+
+        let waiting = json.replace("}", r#","state":"WAITING_TO_BUY"}"#);
+        let position: Position = serde_json::from_str(&waiting).unwrap();
+        assert_eq!(position.state(), PositionState::WaitingToBuy);
     }
 
     #[tokio::test]
-    async fn test_trap() {
-        let mut position = Position {
-            buying_prices: vec![Range(dec("10"), dec("20"))],
-            selling_prices: vec![Range(dec("50"), dec("80"))],
-            base_quantity: dec("0"),
-            quote_quantity: dec("20.0"),
+    async fn test_trap_stop_loss() {
+        let position = |halt_on_stop| Position {
+            buying_prices: vec![Range(dec("40"), dec("60"))],
+            selling_prices: vec![Range(dec("190"), dec("210"))],
+            quote_quantity: dec("100"),
+            stop_loss: Some(dec("30")),
+            halt_on_stop,
+            ..Default::default()
         };
+        let agent = TradeAgent::default();
 
-        let trades = position
-            .trap(&TradeAgent::with_commission("0"), &dec("80"))
+        let mut halting = position(true);
+        let trades = halting.trap(&agent, &dec("50")).await.unwrap();
+        assert_eq!(trades[0].kind, Some(TradeKind::Entry));
+
+        // Nothing happens above the stop, below the buying band
+        assert!(halting.trap(&agent, &dec("35")).await.unwrap().is_empty());
+
+        let trades = halting.trap(&agent, &dec("25")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_sell(dec("25"), dec("2"), dec("50"))]
+        );
+        assert_eq!(trades[0].kind, Some(TradeKind::StopLoss));
+        assert_eq!(trades[0].requested_price, Some(dec("25")));
+        assert_eq!(halting.state(), PositionState::Halted);
+
+        // Back inside the buying band the halted position stays out
+        assert!(halting.trap(&agent, &dec("50")).await.unwrap().is_empty());
+        assert_eq!(halting.quote_quantity, dec("50"));
+        assert_eq!(halting.base_quantity, dec("0"));
+
+        let mut resuming = position(false);
+        resuming.trap(&agent, &dec("50")).await.unwrap();
+        resuming.trap(&agent, &dec("25")).await.unwrap();
+        assert_eq!(resuming.state(), PositionState::WaitingToBuy);
+
+        let trades = resuming.trap(&agent, &dec("50")).await.unwrap();
+        assert_eq!(
+            trades,
+            vec![Trade::with_buy(dec("50"), dec("1"), dec("50"))]
+        );
+
+        let trades = resuming.trap(&agent, &dec("200")).await.unwrap();
+        assert_eq!(trades[0].kind, Some(TradeKind::TakeProfit));
+    }
+
+    #[tokio::test]
+    async fn test_position_ids() {
+        use crate::strategy::{grid::Grid, Strategy};
+
+        let grid = Grid::new(
+            dec("30"),
+            PriceRange::new(dec("50"), dec("100")).unwrap(),
+            2,
+        );
+        let mut positions = grid.assign_position();
+        let ids: Vec<Option<u64>> = positions.iter().map(|position| position.id).collect();
+        assert_eq!(ids, vec![Some(0), Some(1)]);
+        assert_eq!(positions[1].label.as_deref(), Some("66.666666..74.999999"));
+
+        let trades = positions
+            .trap(&TradeAgent::default(), &dec("70"))
             .await
             .unwrap();
-        assert_eq!(trades, vec![]);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].position_id, Some(1));
 
-        let trades = position
-            .trap(&TradeAgent::with_commission("0"), &dec("20"))
+        let owner = positions.find_by_id(1).unwrap();
+        assert_eq!(owner.quote_quantity, dec("0"));
+        assert_eq!(positions.find_by_id(2), None);
+        assert_eq!(positions[1..].find_by_id(0), None);
+
+        let json = serde_json::to_string(&positions[0]).unwrap();
+        assert!(json.contains(r#""id":0,"label":"50..58.333333""#));
+
+        let json = r#"{"buying_prices":[["30","80"]],"selling_prices":[],"base_quantity":"0","quote_quantity":"20"}"#;
+        let position: Position = serde_json::from_str(json).unwrap();
+        assert_eq!((position.id, position.label.clone()), (None, None));
+        assert_eq!(serde_json::to_string(&position).unwrap(), json);
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_positions() {
+        let position = |id| Position {
+            buying_prices: vec![Range(dec("40"), dec("60"))],
+            quote_quantity: dec("100"),
+            id: Some(id),
+            ..Default::default()
+        };
+        let mut positions = vec![position(0), position(1)];
+
+        let trades = positions
+            .trap(&TradeAgent::default(), &dec("50"))
             .await
             .unwrap();
-        assert_eq!(trades, vec![
-            Trade::with_buy(dec("20"), dec("1"), dec("20"))
-        ]);
+        assert_eq!(trades.len(), 2);
+
+        // Fills of two positions at the same price stay apart
+        let merged = Trade::aggregate(&trades);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].position_id, Some(0));
+        assert_eq!(merged[1].position_id, Some(1));
+        assert_eq!(merged[1].base_quantity, dec("2"));
     }
 }
@@ -0,0 +1,57 @@
+// Timestamps as decimal strings, for consumers that truncate numbers above 2^53.
+// Use with `#[serde(with = "serde_ts")]`, deserialization accepts both strings and numbers.
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::types::Timestamp;
+
+pub fn serialize<S: Serializer>(value: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+    Timestamp::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::trade::Trade;
+    use crate::types::Timestamp;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Stamped {
+        #[serde(with = "super")]
+        timestamp: Timestamp,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = Stamped {
+            timestamp: Timestamp((1 << 53) + 1),
+        };
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"timestamp":"9007199254740993"}"#);
+        assert_eq!(serde_json::from_str::<Stamped>(&json).unwrap(), value);
+
+        let json = r#"{"timestamp":9007199254740993}"#;
+        assert_eq!(serde_json::from_str::<Stamped>(json).unwrap(), value);
+
+        assert!(serde_json::from_str::<Stamped>(r#"{"timestamp":-1}"#).is_err());
+        assert!(serde_json::from_str::<Stamped>(r#"{"timestamp":"x"}"#).is_err());
+    }
+
+    #[test]
+    fn test_trade_accepts_both() {
+        let json = r#"{"side":"BUY","price":"10","base_quantity":"1","quote_quantity":"10","timestamp":"9007199254740993"}"#;
+        let trade: Trade = serde_json::from_str(json).unwrap();
+        assert_eq!(trade.timestamp, Timestamp(9007199254740993));
+
+        let json = r#"{"side":"BUY","price":"10","base_quantity":"1","quote_quantity":"10","timestamp":9007199254740993}"#;
+        let trade: Trade = serde_json::from_str(json).unwrap();
+        assert_eq!(trade.timestamp, Timestamp(9007199254740993));
+        assert_eq!(serde_json::to_string(&trade).unwrap(), json);
+    }
+}
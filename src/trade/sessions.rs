@@ -0,0 +1,77 @@
+use std::ops::Range;
+
+use crate::types::BaseQuantity;
+
+use super::{Trade, TradeSide};
+
+// Index ranges of the round trips in `trades`, each ending where the held base returns to
+// `initial_base`; trades after the last such point form a trailing open session.
+// Only the net flow decides where the cuts fall, the starting inventory shifts both sides.
+pub fn split_indices(trades: &[Trade], initial_base: BaseQuantity) -> Vec<Range<usize>> {
+    let mut sessions = Vec::new();
+    let mut base = initial_base;
+    let mut start = 0;
+    for (index, trade) in trades.iter().enumerate() {
+        match trade.side {
+            TradeSide::Buy => base += trade.base_quantity,
+            TradeSide::Sell => base -= trade.base_quantity,
+        }
+
+        if base == initial_base {
+            sessions.push(start..index + 1);
+            start = index + 1;
+        }
+    }
+
+    if start < trades.len() {
+        sessions.push(start..trades.len());
+    }
+
+    sessions
+}
+
+pub fn split(trades: &[Trade], initial_base: BaseQuantity) -> Vec<Vec<Trade>> {
+    split_indices(trades, initial_base)
+        .into_iter()
+        .map(|range| trades[range].to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trade::Trade;
+    use crate::types::Decimal;
+
+    use super::{split, split_indices};
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_split() {
+        let trades = vec![
+            Trade::with_buy(dec("50"), dec("0.3996"), dec("20.0")),
+            Trade::with_sell(dec("200"), dec("0.3996"), dec("79.8400800")),
+            Trade::with_buy(dec("50"), dec("9.99"), dec("500.0")),
+        ];
+
+        assert_eq!(split_indices(&trades, dec("0")), vec![0..2, 2..3]);
+        assert_eq!(
+            split(&trades, dec("0")),
+            vec![trades[..2].to_vec(), trades[2..].to_vec()]
+        );
+
+        // Seeded with 5 base, sold off and bought back before a new session opens
+        let trades = vec![
+            Trade::with_sell(dec("210"), dec("5"), dec("1050")),
+            Trade::with_buy(dec("80"), dec("5"), dec("400")),
+            Trade::with_sell(dec("210"), dec("2"), dec("420")),
+        ];
+
+        assert_eq!(split_indices(&trades, dec("5")), vec![0..2, 2..3]);
+        assert_eq!(split_indices(&trades, dec("0")), vec![0..2, 2..3]);
+        assert_eq!(split_indices(&[], dec("0")), vec![]);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::types::{Decimal, Timestamp};
+
+use super::Trade;
+
+// Decimal places shown by `render_table`, longer values are truncated for display only
+pub const DISPLAY_SCALE: u32 = 8;
+
+// Fixed-width table of the trades, one row per trade
+pub fn render_table(trades: &[Trade]) -> String {
+    render_table_with_scale(trades, DISPLAY_SCALE)
+}
+
+pub fn render_table_with_scale(trades: &[Trade], scale: u32) -> String {
+    let header = ["side", "price", "base", "quote", "cost", "timestamp"].map(String::from);
+    let rows: Vec<[String; 6]> = trades
+        .iter()
+        .map(|trade| {
+            [
+                trade.side.to_string(),
+                display_decimal(trade.price, scale),
+                display_decimal(trade.base_quantity, scale),
+                display_decimal(trade.quote_quantity, scale),
+                display_decimal(trade.implied_costs(), scale),
+                display_timestamp(trade.timestamp),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = std::cmp::max(*width, cell.len());
+        }
+    }
+
+    let mut lines = Vec::with_capacity(rows.len() + 1);
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .enumerate()
+            .map(|(i, (cell, width))| match i == 0 {
+                true => format!("{:<width$}", cell, width = width),
+                false => format!("{:>width$}", cell, width = width),
+            })
+            .collect();
+        lines.push(cells.join("  ").trim_end().to_string());
+    }
+
+    lines.join("\n")
+}
+
+pub(crate) fn display_decimal(value: Decimal, scale: u32) -> String {
+    value.trunc_with_scale(scale).normalize().to_string()
+}
+
+#[cfg(feature = "chrono")]
+pub(crate) fn display_timestamp(timestamp: Timestamp) -> String {
+    let millis = i64::try_from(timestamp.as_millis()).ok();
+    match millis.and_then(chrono::DateTime::from_timestamp_millis) {
+        Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+#[cfg(not(feature = "chrono"))]
+pub(crate) fn display_timestamp(timestamp: Timestamp) -> String {
+    timestamp.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::trade::TradeSide;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn fixed_trades() -> Vec<Trade> {
+        vec![
+            Trade::new(
+                TradeSide::Buy,
+                dec("50"),
+                dec("0.3996"),
+                dec("20"),
+                Timestamp(1704067200000),
+            ),
+            Trade::new(
+                TradeSide::Sell,
+                dec("200"),
+                dec("0.3996"),
+                dec("79.84008"),
+                Timestamp(1704070800000),
+            ),
+            Trade::new(
+                TradeSide::Buy,
+                dec("3"),
+                dec("3.33"),
+                dec("10"),
+                Timestamp(1704074400500),
+            ),
+        ]
+    }
+
+    #[cfg(not(feature = "chrono"))]
+    #[test]
+    fn test_render_table() {
+        let expected = [
+            "side  price    base     quote        cost      timestamp",
+            "BUY      50  0.3996        20        0.02  1704067200000",
+            "SELL    200  0.3996  79.84008     0.07992  1704070800000",
+            "BUY       3    3.33        10  0.00999999  1704074400500",
+        ];
+        assert_eq!(render_table(&fixed_trades()), expected.join("\n"));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_render_table() {
+        let expected = [
+            "side  price    base     quote        cost                timestamp",
+            "BUY      50  0.3996        20        0.02  2024-01-01 00:00:00.000",
+            "SELL    200  0.3996  79.84008     0.07992  2024-01-01 01:00:00.000",
+            "BUY       3    3.33        10  0.00999999  2024-01-01 02:00:00.500",
+        ];
+        assert_eq!(render_table(&fixed_trades()), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_render_table_with_scale() {
+        let trades = fixed_trades();
+        let table = render_table_with_scale(&trades, 2);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[1].starts_with("BUY      50  0.39     20  0.02  "));
+        assert!(lines[3].starts_with("BUY       3  3.33     10     0  "));
+
+        // Display truncation leaves the trades untouched
+        assert_eq!(trades[0].base_quantity, dec("0.3996"));
+        assert_eq!(
+            render_table(&[]),
+            "side  price  base  quote  cost  timestamp"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+// camelCase form of `Trade` for services that expect exchange style keys. Loading also
+// accepts the snake_case keys of `Trade` itself, and decimals are always strings.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{BaseQuantity, Price, Quantity, QuoteQuantity, Timestamp};
+
+use super::{Trade, TradeKind, TradeSide};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeWire {
+    pub side: TradeSide,
+
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Price,
+
+    #[serde(alias = "base_quantity", with = "rust_decimal::serde::str")]
+    pub base_quantity: BaseQuantity,
+
+    #[serde(alias = "quote_quantity", with = "rust_decimal::serde::str")]
+    pub quote_quantity: QuoteQuantity,
+
+    pub timestamp: Timestamp,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub fee: Option<Quantity>,
+
+    #[serde(default, alias = "fee_asset", skip_serializing_if = "Option::is_none")]
+    pub fee_asset: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    #[serde(default, alias = "order_id", skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+
+    #[serde(
+        default,
+        alias = "requested_price",
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub requested_price: Option<Price>,
+
+    #[serde(
+        default,
+        alias = "position_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub position_id: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<TradeKind>,
+}
+
+impl From<Trade> for TradeWire {
+    fn from(trade: Trade) -> Self {
+        Self {
+            side: trade.side,
+            price: trade.price,
+            base_quantity: trade.base_quantity,
+            quote_quantity: trade.quote_quantity,
+            timestamp: trade.timestamp,
+            fee: trade.fee,
+            fee_asset: trade.fee_asset,
+            id: trade.id,
+            order_id: trade.order_id,
+            symbol: trade.symbol,
+            requested_price: trade.requested_price,
+            position_id: trade.position_id,
+            kind: trade.kind,
+        }
+    }
+}
+
+impl From<TradeWire> for Trade {
+    fn from(wire: TradeWire) -> Self {
+        let mut trade = Trade::new(
+            wire.side,
+            wire.price,
+            wire.base_quantity,
+            wire.quote_quantity,
+            wire.timestamp,
+        );
+        trade.fee = wire.fee;
+        trade.fee_asset = wire.fee_asset;
+        trade.id = wire.id;
+        trade.order_id = wire.order_id;
+        trade.symbol = wire.symbol;
+        trade.requested_price = wire.requested_price;
+        trade.position_id = wire.position_id;
+        trade.kind = wire.kind;
+        trade
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trade::{Trade, TradeSide};
+    use crate::types::{Decimal, Timestamp};
+
+    use super::TradeWire;
+
+    fn dec(value: &str) -> Decimal {
+        use std::str::FromStr;
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let trade = Trade::builder()
+            .side(TradeSide::Sell)
+            .price(dec("200"))
+            .base_quantity(dec("0.3996"))
+            .quote_quantity(dec("79.84008"))
+            .timestamp(Timestamp(1716000000000))
+            .fee(dec("0.0001"), Some("BNB".into()))
+            .order_id("12")
+            .build();
+
+        let json = serde_json::to_string(&TradeWire::from(trade.clone())).unwrap();
+        assert_eq!(
+            json,
+            r#"{"side":"SELL","price":"200","baseQuantity":"0.3996","quoteQuantity":"79.84008","timestamp":1716000000000,"fee":"0.0001","feeAsset":"BNB","orderId":"12"}"#
+        );
+
+        let wire: TradeWire = serde_json::from_str(&json).unwrap();
+        assert!(Trade::from(wire).eq_with_timestamp(&trade));
+
+        let json = serde_json::to_string(&trade).unwrap();
+        let wire: TradeWire = serde_json::from_str(&json).unwrap();
+        assert!(Trade::from(wire).eq_with_timestamp(&trade));
+    }
+}